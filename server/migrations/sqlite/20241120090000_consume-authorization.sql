@@ -0,0 +1,5 @@
+alter table authorizations add column consumed_at datetime;
+
+alter table sessions add column authorization_code text
+    references authorizations(code) on delete cascade;
+alter table sessions add column revoked_at datetime;
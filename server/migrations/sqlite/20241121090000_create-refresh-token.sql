@@ -0,0 +1,10 @@
+create table refresh_tokens (
+    token text not null primary key,
+    family text not null,
+    access_token text not null references sessions(access_token) on delete cascade,
+    consumed_at datetime,
+    created_at datetime not null,
+    valid_until datetime not null
+);
+
+create index refresh_tokens_family_idx on refresh_tokens(family);
@@ -0,0 +1,28 @@
+alter table applications add column kind tinyint not null default 1;
+update applications set kind = 0 where secrets = '';
+
+-- sessions created with the client credentials grant don't belong to any user,
+-- sqlite requires to rebuild the table to drop the not null constraint.
+-- dropping the table cascades on the refresh tokens, so they are restored afterwards.
+create temporary table refresh_tokens_backup as select * from refresh_tokens;
+
+create table sessions_next (
+    access_token text not null primary key,
+    client_id text not null references applications(id) on delete cascade,
+    user_id text references users(id) on delete cascade,
+    scope text,
+    created_at datetime not null,
+    valid_until datetime not null,
+    authorization_code text references authorizations(code) on delete cascade,
+    revoked_at datetime
+);
+
+insert into sessions_next (access_token, client_id, user_id, scope, created_at, valid_until, authorization_code, revoked_at)
+select access_token, client_id, user_id, scope, created_at, valid_until, authorization_code, revoked_at
+from sessions;
+
+drop table sessions;
+alter table sessions_next rename to sessions;
+
+insert into refresh_tokens select * from refresh_tokens_backup;
+drop table refresh_tokens_backup;
@@ -0,0 +1 @@
+alter table applications add column allow_password_grant boolean not null default false;
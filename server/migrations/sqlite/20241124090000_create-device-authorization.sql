@@ -0,0 +1,12 @@
+create table device_authorizations (
+    device_code text not null primary key,
+    user_code text not null unique,
+    client_id text not null references applications(id) on delete cascade,
+    user_id text references users(id) on delete cascade,
+    scope text,
+    interval integer not null,
+    last_polled_at datetime,
+    consumed_at datetime,
+    created_at datetime not null,
+    valid_until datetime not null
+);
@@ -0,0 +1,6 @@
+-- the redirect uris are stored separated by spaces, like the scopes
+alter table applications rename column redirect_uri to redirect_uris;
+
+alter table authorizations add column redirect_uri text not null default '';
+update authorizations
+set redirect_uri = (select redirect_uris from applications where applications.id = authorizations.client_id);
@@ -0,0 +1,2 @@
+alter table applications add column scopes text not null default '';
+alter table applications add column default_scopes text not null default '';
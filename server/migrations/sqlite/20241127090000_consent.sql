@@ -0,0 +1,12 @@
+alter table applications add column require_consent boolean not null default false;
+
+-- the code can't be exchanged before the user approves the request
+alter table authorizations add column consent_pending boolean not null default false;
+
+create table consents (
+    user_id text not null references users(id) on delete cascade,
+    client_id text not null references applications(id) on delete cascade,
+    scope text not null,
+    created_at datetime not null,
+    primary key (user_id, client_id, scope)
+);
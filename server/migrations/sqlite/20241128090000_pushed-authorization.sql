@@ -0,0 +1,11 @@
+alter table applications add column require_pushed_authorization boolean not null default false;
+
+-- RFC 9126: the authorization parameters are stored as a urlencoded string
+create table pushed_authorizations (
+    request_uri text not null primary key,
+    client_id text not null references applications(id) on delete cascade,
+    parameters text not null,
+    consumed_at datetime,
+    created_at datetime not null,
+    valid_until datetime not null
+);
@@ -0,0 +1,2 @@
+-- RFC 7591 §2: the public keys of the client, stored as a JWK set
+alter table applications add column jwks text not null default '{"keys":[]}';
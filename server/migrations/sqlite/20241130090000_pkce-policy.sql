@@ -0,0 +1,40 @@
+-- RFC 7636: public clients always use PKCE, confidential clients can opt out
+alter table applications add column require_pkce boolean not null default true;
+alter table applications add column allow_plain_pkce boolean not null default true;
+
+-- authorizations requested without PKCE don't have any code challenge,
+-- sqlite requires to rebuild the table to drop the not null constraints.
+-- dropping the table cascades on the sessions and the refresh tokens when the foreign keys are enforced,
+-- so they are restored afterwards, skipping the rows that survived when they are not.
+create temporary table sessions_backup as select * from sessions;
+create temporary table refresh_tokens_backup as select * from refresh_tokens;
+
+create table authorizations_next (
+    code text not null primary key,
+    client_id text not null references applications(id) on delete cascade,
+    user_id text not null references users(id) on delete cascade,
+    state text not null,
+    scope text,
+
+    code_challenge text,
+    code_challenge_method tinyint,
+    response_type tinyint not null,
+
+    created_at datetime not null,
+    valid_until datetime not null,
+    consumed_at datetime,
+    redirect_uri text not null default '',
+    consent_pending boolean not null default false
+);
+
+insert into authorizations_next (code, client_id, user_id, state, scope, code_challenge, code_challenge_method, response_type, created_at, valid_until, consumed_at, redirect_uri, consent_pending)
+select code, client_id, user_id, state, scope, code_challenge, code_challenge_method, response_type, created_at, valid_until, consumed_at, redirect_uri, consent_pending
+from authorizations;
+
+drop table authorizations;
+alter table authorizations_next rename to authorizations;
+
+insert or ignore into sessions select * from sessions_backup;
+insert or ignore into refresh_tokens select * from refresh_tokens_backup;
+drop table sessions_backup;
+drop table refresh_tokens_backup;
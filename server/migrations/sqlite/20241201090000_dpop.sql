@@ -0,0 +1,15 @@
+-- RFC 9449 §6: the access token is bound to the thumbprint of the proof key
+alter table sessions add column dpop_jkt text;
+
+create table dpop_nonces (
+    nonce text not null primary key,
+    created_at datetime not null,
+    valid_until datetime not null
+);
+
+-- RFC 9449 §11.1: the proofs identifiers are kept to detect replays
+create table dpop_proofs (
+    jti text not null primary key,
+    created_at datetime not null,
+    valid_until datetime not null
+);
@@ -0,0 +1,3 @@
+-- RFC 8693 §4: the target of an exchanged token and the chain of actors, as a json object
+alter table sessions add column audience text;
+alter table sessions add column actor text;
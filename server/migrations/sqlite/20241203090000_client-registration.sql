@@ -0,0 +1,8 @@
+-- RFC 7592: the applications registered through the api, managed with their registration access token.
+-- only a hash of the token is kept, with the metadata as registered.
+create table registrations (
+    client_id text not null primary key references applications(id) on delete cascade,
+    access_token_hash text not null unique,
+    metadata text not null,
+    created_at datetime not null
+);
@@ -0,0 +1,3 @@
+-- RFC 8707: the resources an application can request tokens for, separated by spaces
+alter table applications add column resources text not null default '';
+alter table authorizations add column resource text;
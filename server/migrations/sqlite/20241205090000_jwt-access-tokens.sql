@@ -0,0 +1,2 @@
+-- RFC 9068: the application receives signed JWT access tokens instead of opaque ones
+alter table applications add column jwt_access_token boolean not null default false;
@@ -0,0 +1,8 @@
+-- the keys generated to sign the tokens when no key file is provided, stored as PKCS#8 documents.
+-- a rotated key remains published until retired_at, so the tokens it signed can still be verified.
+create table signing_keys (
+    kid text not null primary key,
+    private_key blob not null,
+    created_at datetime not null,
+    retired_at datetime
+);
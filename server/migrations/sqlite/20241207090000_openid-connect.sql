@@ -0,0 +1,2 @@
+-- OpenID Connect Core §3.1.2.1: the nonce is given back in the id token
+alter table authorizations add column nonce text;
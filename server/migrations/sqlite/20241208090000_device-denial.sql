@@ -0,0 +1,2 @@
+-- RFC 8628 §3.5: the user can deny the request, the device is then told so
+alter table device_authorizations add column denied_at datetime;
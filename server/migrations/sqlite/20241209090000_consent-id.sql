@@ -0,0 +1,3 @@
+-- the consent form refers to the authorization with its own single-use identifier
+alter table authorizations add column consent_id text;
+create unique index authorizations_consent_id on authorizations (consent_id);
@@ -0,0 +1,17 @@
+-- RFC 9101 §5.2: the request objects passed by reference are served by the server itself
+create table request_objects (
+    id text not null primary key,
+    client_id text not null references applications(id) on delete cascade,
+    request text not null,
+    created_at datetime not null,
+    valid_until datetime not null
+);
+
+-- RFC 7519 §4.1.7: the identifiers of the request objects are kept to detect replays
+create table request_object_jtis (
+    client_id text not null references applications(id) on delete cascade,
+    jti text not null,
+    created_at datetime not null,
+    valid_until datetime not null,
+    primary key (client_id, jti)
+);
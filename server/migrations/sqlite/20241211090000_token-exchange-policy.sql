@@ -0,0 +1,2 @@
+-- RFC 8693 §5: exchanging tokens lets a client act with the tokens of others, it has to be enabled explicitly
+alter table applications add column allow_token_exchange boolean not null default false;
@@ -0,0 +1,4 @@
+-- RFC 9068 §2.2: the sessions of the JWT access tokens are recorded by an identifier of their own,
+-- so that the opaque key of the session never leaves the server
+alter table sessions add column jti text;
+create unique index sessions_jti on sessions (jti);
@@ -0,0 +1,4 @@
+-- RFC 7591 §2: the grant types and the token endpoint authentication method of the registered
+-- applications, null for the configured ones that are only restricted by their other settings
+alter table applications add column grant_types text;
+alter table applications add column token_endpoint_auth_method text;
@@ -0,0 +1,4 @@
+-- OpenID Connect Core §2: the time the user authenticated is given back in the id token,
+-- the codes issued before were created at the login
+alter table authorizations add column authenticated_at datetime;
+update authorizations set authenticated_at = created_at;
@@ -6,13 +6,19 @@ use tower_http::compression::CompressionLayer;
 use tower_http::trace::TraceLayer;
 
 use crate::helper::parse_env_or;
+use crate::service::base_url::BaseUrl;
+use crate::service::keys::Keys;
+use crate::service::registration::InitialAccessToken;
 
 pub(crate) struct Config {
     host: std::net::IpAddr,
     port: u16,
+    base_url: Option<String>,
+    initial_access_token: Option<String>,
 
     database: crate::service::database::Config,
     dataset: crate::service::dataset::Config,
+    keys: crate::service::keys::Config,
 }
 
 impl Config {
@@ -20,9 +26,12 @@ impl Config {
         Ok(Self {
             host: parse_env_or("HOST", IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)))?,
             port: parse_env_or("PORT", 3010)?,
+            base_url: std::env::var("BASE_URL").ok(),
+            initial_access_token: std::env::var("INITIAL_ACCESS_TOKEN").ok(),
 
             database: crate::service::database::Config::from_env()?,
             dataset: crate::service::dataset::Config::from_env()?,
+            keys: crate::service::keys::Config::from_env()?,
         })
     }
 
@@ -31,9 +40,19 @@ impl Config {
         database.upgrade().await?;
 
         self.dataset.synchronize(&database).await?;
+        let keys = self.keys.build(&database).await?;
+
+        let socket_address = SocketAddr::from((self.host, self.port));
+        let base_url = match self.base_url {
+            Some(value) => BaseUrl::new(value),
+            None => BaseUrl::new(format!("http://{socket_address}")),
+        };
 
         Ok(Application {
-            socket_address: SocketAddr::from((self.host, self.port)),
+            socket_address,
+            base_url,
+            initial_access_token: InitialAccessToken::new(self.initial_access_token),
+            keys,
             database,
         })
     }
@@ -41,12 +60,18 @@ impl Config {
 
 pub(crate) struct Application {
     socket_address: SocketAddr,
+    base_url: BaseUrl,
+    initial_access_token: InitialAccessToken,
+    keys: Keys,
     database: crate::service::database::Pool,
 }
 
 impl Application {
     fn router(&self) -> axum::Router {
         crate::router::create()
+            .layer(Extension(self.base_url.clone()))
+            .layer(Extension(self.initial_access_token.clone()))
+            .layer(Extension(self.keys.clone()))
             .layer(Extension(self.database.clone()))
             .layer(CompressionLayer::new())
             .layer(TraceLayer::new_for_http())
@@ -56,6 +81,7 @@ impl Application {
         tracing::debug!("binding socket to {}", self.socket_address);
         let listener = TcpListener::bind(self.socket_address).await?;
         tracing::info!("listening on {}", self.socket_address);
+        self.keys.schedule_rotation(self.database.clone());
         axum::serve(listener, self.router()).await?;
         Ok(())
     }
@@ -78,17 +104,38 @@ impl Application {
             .synchronize(&database)
             .await
             .unwrap();
+        let keys = crate::service::keys::Config::default()
+            .build(&database)
+            .await
+            .unwrap();
 
         Self {
             socket_address: SocketAddr::from((Ipv4Addr::new(127, 0, 0, 1), port)),
+            base_url: BaseUrl::new(format!("http://localhost:{port}")),
+            initial_access_token: InitialAccessToken::default(),
+            keys,
             database,
         }
     }
 
+    pub(crate) fn with_initial_access_token(mut self, value: &str) -> Self {
+        self.initial_access_token = InitialAccessToken::new(Some(value.to_string()));
+        self
+    }
+
+    pub(crate) fn without_signing_keys(mut self) -> Self {
+        self.keys = crate::service::keys::Keys::empty();
+        self
+    }
+
     pub(crate) fn database(&self) -> &sqlx::SqlitePool {
         self.database.as_ref()
     }
 
+    pub(crate) fn keys(&self) -> &Keys {
+        &self.keys
+    }
+
     pub(crate) async fn handle(
         &self,
         req: axum::http::Request<axum::body::Body>,
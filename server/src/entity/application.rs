@@ -1,12 +1,258 @@
 use std::collections::HashSet;
 
+use axum::http::Uri;
 use uuid::Uuid;
 
+use crate::entity::code_challenge::CodeChallengeMethod;
+use crate::jose::JwkSet;
+
+pub(crate) const PUBLIC_CODE: u8 = 0;
+pub(crate) const CONFIDENTIAL_CODE: u8 = 1;
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ApplicationKind {
+    Public,
+    Confidential,
+}
+
+impl ApplicationKind {
+    pub const fn as_code(&self) -> u8 {
+        match self {
+            Self::Public => PUBLIC_CODE,
+            Self::Confidential => CONFIDENTIAL_CODE,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ApplicationKindDecodeError(pub u8);
+
+impl std::error::Error for ApplicationKindDecodeError {}
+
+impl std::fmt::Display for ApplicationKindDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid application kind {:?}", self.0)
+    }
+}
+
+impl TryFrom<u8> for ApplicationKind {
+    type Error = ApplicationKindDecodeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            PUBLIC_CODE => Ok(Self::Public),
+            CONFIDENTIAL_CODE => Ok(Self::Confidential),
+            other => Err(ApplicationKindDecodeError(other)),
+        }
+    }
+}
+
+// RFC 7591 §2: how the application authenticates on the token endpoint
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AuthMethod {
+    ClientSecretBasic,
+    ClientSecretPost,
+    None,
+}
+
+impl AuthMethod {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::ClientSecretBasic => "client_secret_basic",
+            Self::ClientSecretPost => "client_secret_post",
+            Self::None => "none",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct AuthMethodDecodeError(pub String);
+
+impl std::error::Error for AuthMethodDecodeError {}
+
+impl std::fmt::Display for AuthMethodDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid token endpoint auth method {:?}", self.0)
+    }
+}
+
+impl std::str::FromStr for AuthMethod {
+    type Err = AuthMethodDecodeError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "client_secret_basic" => Ok(Self::ClientSecretBasic),
+            "client_secret_post" => Ok(Self::ClientSecretPost),
+            "none" => Ok(Self::None),
+            other => Err(AuthMethodDecodeError(other.to_string())),
+        }
+    }
+}
+
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct Entity {
     pub id: Uuid,
+    pub kind: ApplicationKind,
     pub secrets: HashSet<String>,
-    pub redirect_uri: String,
+    pub redirect_uris: Vec<String>,
+    pub allow_password_grant: bool,
+    pub scopes: Vec<String>,
+    pub default_scopes: Vec<String>,
+    pub require_consent: bool,
+    pub require_pushed_authorization: bool,
+    pub jwks: JwkSet,
+    pub require_pkce: bool,
+    pub allow_plain_pkce: bool,
+    pub resources: Vec<String>,
+    pub jwt_access_token: bool,
+    pub allow_token_exchange: bool,
+    // RFC 7591 §2: the registered applications are restricted to the grant types they declared
+    pub grant_types: Option<Vec<String>>,
+    pub token_endpoint_auth_method: Option<AuthMethod>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct UnknownScopeError(pub String);
+
+impl std::error::Error for UnknownScopeError {}
+
+impl std::fmt::Display for UnknownScopeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown scope {:?}", self.0)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct UnknownResourceError(pub String);
+
+impl std::error::Error for UnknownResourceError {}
+
+impl std::fmt::Display for UnknownResourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown resource {:?}", self.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CodeChallengeError {
+    Missing,
+    MethodNotAllowed,
+}
+
+impl std::error::Error for CodeChallengeError {}
+
+impl std::fmt::Display for CodeChallengeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing => write!(f, "code challenge required"),
+            // RFC 7636 §4.4.1
+            Self::MethodNotAllowed => write!(f, "transform algorithm not supported"),
+        }
+    }
+}
+
+impl Entity {
+    pub fn allows_grant_type(&self, grant_type: &str) -> bool {
+        self.grant_types
+            .as_ref()
+            .is_none_or(|items| items.iter().any(|item| item == grant_type))
+    }
+
+    pub fn allows_auth_method(&self, method: AuthMethod) -> bool {
+        self.token_endpoint_auth_method
+            .is_none_or(|expected| expected == method)
+    }
+
+    // RFC 6749 §3.3: the default scopes are used when the client doesn't request any
+    pub fn grant_scope(
+        &self,
+        requested: Option<&str>,
+    ) -> Result<Option<String>, UnknownScopeError> {
+        let requested: Vec<&str> = match requested {
+            Some(value) => value.split_whitespace().collect(),
+            None => self.default_scopes.iter().map(String::as_str).collect(),
+        };
+        if let Some(unknown) = requested
+            .iter()
+            .find(|item| !self.scopes.iter().any(|scope| scope == *item))
+        {
+            return Err(UnknownScopeError(unknown.to_string()));
+        }
+        let granted = self
+            .scopes
+            .iter()
+            .map(String::as_str)
+            .filter(|scope| requested.contains(scope))
+            .collect::<Vec<_>>();
+        Ok(if granted.is_empty() {
+            None
+        } else {
+            Some(granted.join(" "))
+        })
+    }
+
+    // RFC 7636 §4.3: the method defaults to plain when only the challenge is provided
+    pub fn check_code_challenge(
+        &self,
+        challenge: Option<&str>,
+        method: Option<CodeChallengeMethod>,
+    ) -> Result<Option<CodeChallengeMethod>, CodeChallengeError> {
+        match (challenge, method) {
+            (None, None) if !self.require_pkce => Ok(None),
+            (None, _) => Err(CodeChallengeError::Missing),
+            (Some(_), method) => {
+                let method = method.unwrap_or(CodeChallengeMethod::Plain);
+                if method == CodeChallengeMethod::Plain && !self.allow_plain_pkce {
+                    Err(CodeChallengeError::MethodNotAllowed)
+                } else {
+                    Ok(Some(method))
+                }
+            }
+        }
+    }
+
+    // RFC 8707 §2: the requested resource becomes the audience of the token
+    pub fn grant_resource<'r>(
+        &self,
+        requested: Option<&'r str>,
+    ) -> Result<Option<&'r str>, UnknownResourceError> {
+        match requested {
+            Some(value) if !self.resources.iter().any(|item| item == value) => {
+                Err(UnknownResourceError(value.to_string()))
+            }
+            other => Ok(other),
+        }
+    }
+
+    pub fn accepts_redirect_uri(&self, requested: &str) -> bool {
+        self.redirect_uris
+            .iter()
+            .any(|registered| redirect_uri_matches(registered, requested))
+    }
+}
+
+fn is_loopback(uri: &Uri) -> bool {
+    uri.scheme_str() == Some("http") && matches!(uri.host(), Some("127.0.0.1" | "[::1]"))
+}
+
+// RFC 8252 §7.3: native apps listening on a loopback interface get a random port,
+// so only the port can differ from the registered redirect uri
+fn redirect_uri_matches(registered: &str, requested: &str) -> bool {
+    if registered == requested {
+        return true;
+    }
+    let (Ok(registered), Ok(requested)) = (registered.parse::<Uri>(), requested.parse::<Uri>())
+    else {
+        return false;
+    };
+    is_loopback(&registered)
+        && is_loopback(&requested)
+        && registered.host() == requested.host()
+        && registered.path_and_query() == requested.path_and_query()
 }
 
 impl<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> for Entity {
@@ -14,30 +260,173 @@ impl<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> for Entity {
         use sqlx::Row;
 
         let secrets: String = row.try_get(1)?;
+        let redirect_uris: String = row.try_get(2)?;
+        let scopes: String = row.try_get(5)?;
+        let default_scopes: String = row.try_get(6)?;
+        let jwks: String = row.try_get(9)?;
+        let resources: String = row.try_get(12)?;
+        let jwks = serde_json::from_str(&jwks).map_err(|err| sqlx::Error::ColumnDecode {
+            index: "jwks".into(),
+            source: Box::new(err),
+        })?;
+
+        let grant_types: Option<String> = row.try_get(15)?;
+        let token_endpoint_auth_method: Option<String> = row.try_get(16)?;
+        let token_endpoint_auth_method = token_endpoint_auth_method
+            .map(|value| value.parse::<AuthMethod>())
+            .transpose()
+            .map_err(|err| sqlx::Error::ColumnDecode {
+                index: "token_endpoint_auth_method".into(),
+                source: Box::new(err),
+            })?;
+
+        let kind: u8 = row.try_get(3)?;
+        let kind = ApplicationKind::try_from(kind).map_err(|err| sqlx::Error::ColumnDecode {
+            index: "kind".into(),
+            source: Box::new(err),
+        })?;
 
         Ok(Self {
             id: row.try_get(0)?,
-            secrets: HashSet::from_iter(secrets.split(',').map(String::from)),
-            redirect_uri: row.try_get(2)?,
+            kind,
+            secrets: HashSet::from_iter(
+                secrets
+                    .split(',')
+                    .filter(|item| !item.is_empty())
+                    .map(String::from),
+            ),
+            redirect_uris: redirect_uris.split_whitespace().map(String::from).collect(),
+            allow_password_grant: row.try_get(4)?,
+            scopes: scopes.split_whitespace().map(String::from).collect(),
+            default_scopes: default_scopes
+                .split_whitespace()
+                .map(String::from)
+                .collect(),
+            require_consent: row.try_get(7)?,
+            require_pushed_authorization: row.try_get(8)?,
+            jwks,
+            require_pkce: row.try_get(10)?,
+            allow_plain_pkce: row.try_get(11)?,
+            resources: resources.split_whitespace().map(String::from).collect(),
+            jwt_access_token: row.try_get(13)?,
+            allow_token_exchange: row.try_get(14)?,
+            grant_types: grant_types
+                .map(|value| value.split_whitespace().map(String::from).collect()),
+            token_endpoint_auth_method,
         })
     }
 }
 
 pub struct Upsert<'a> {
     id: Uuid,
+    kind: ApplicationKind,
     secrets: &'a HashSet<String>,
-    redirect_uri: &'a str,
+    redirect_uris: &'a [String],
+    allow_password_grant: bool,
+    scopes: &'a [String],
+    default_scopes: &'a [String],
+    require_consent: bool,
+    require_pushed_authorization: bool,
+    jwks: Option<&'a JwkSet>,
+    require_pkce: bool,
+    allow_plain_pkce: bool,
+    resources: &'a [String],
+    jwt_access_token: bool,
+    allow_token_exchange: bool,
+    grant_types: Option<&'a [String]>,
+    token_endpoint_auth_method: Option<AuthMethod>,
 }
 
 impl<'a> Upsert<'a> {
-    pub fn new(id: Uuid, secrets: &'a HashSet<String>, redirect_uri: &'a str) -> Self {
+    pub fn new(id: Uuid, secrets: &'a HashSet<String>, redirect_uris: &'a [String]) -> Self {
         Self {
             id,
+            // an application without secret can't authenticate
+            kind: if secrets.is_empty() {
+                ApplicationKind::Public
+            } else {
+                ApplicationKind::Confidential
+            },
             secrets,
-            redirect_uri,
+            redirect_uris,
+            allow_password_grant: false,
+            scopes: &[],
+            default_scopes: &[],
+            require_consent: false,
+            require_pushed_authorization: false,
+            jwks: None,
+            require_pkce: true,
+            allow_plain_pkce: true,
+            resources: &[],
+            jwt_access_token: false,
+            allow_token_exchange: false,
+            grant_types: None,
+            token_endpoint_auth_method: None,
         }
     }
 
+    pub fn with_kind(mut self, kind: ApplicationKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_password_grant(mut self, allow: bool) -> Self {
+        self.allow_password_grant = allow;
+        self
+    }
+
+    pub fn with_scopes(mut self, scopes: &'a [String], default_scopes: &'a [String]) -> Self {
+        self.scopes = scopes;
+        self.default_scopes = default_scopes;
+        self
+    }
+
+    pub fn with_require_consent(mut self, require: bool) -> Self {
+        self.require_consent = require;
+        self
+    }
+
+    pub fn with_require_pushed_authorization(mut self, require: bool) -> Self {
+        self.require_pushed_authorization = require;
+        self
+    }
+
+    pub fn with_jwks(mut self, jwks: &'a JwkSet) -> Self {
+        self.jwks = Some(jwks);
+        self
+    }
+
+    pub fn with_pkce(mut self, require: bool, allow_plain: bool) -> Self {
+        self.require_pkce = require;
+        self.allow_plain_pkce = allow_plain;
+        self
+    }
+
+    pub fn with_resources(mut self, resources: &'a [String]) -> Self {
+        self.resources = resources;
+        self
+    }
+
+    pub fn with_jwt_access_token(mut self, enabled: bool) -> Self {
+        self.jwt_access_token = enabled;
+        self
+    }
+
+    pub fn with_token_exchange(mut self, allow: bool) -> Self {
+        self.allow_token_exchange = allow;
+        self
+    }
+
+    pub fn with_registered_grant_types(mut self, grant_types: &'a [String]) -> Self {
+        self.grant_types = Some(grant_types);
+        self
+    }
+
+    pub fn with_token_endpoint_auth_method(mut self, method: AuthMethod) -> Self {
+        self.token_endpoint_auth_method = Some(method);
+        self
+    }
+
     pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
         &self,
         executor: E,
@@ -45,16 +434,37 @@ impl<'a> Upsert<'a> {
         let mut secrets = self.secrets.iter().map(|v| v.as_str()).collect::<Vec<_>>();
         secrets.sort();
         let secrets = secrets.join(",");
+        let redirect_uris = self.redirect_uris.join(" ");
+        let scopes = self.scopes.join(" ");
+        let default_scopes = self.default_scopes.join(" ");
+        let resources = self.resources.join(" ");
+        let grant_types = self.grant_types.map(|items| items.join(" "));
+        let jwks = serde_json::to_string(self.jwks.unwrap_or(&JwkSet::default()))
+            .map_err(|err| sqlx::Error::Encode(Box::new(err)))?;
         sqlx::query_as(
-            r#"insert into applications (id, secrets, redirect_uri)
-values ($1, $2, $3)
+            r#"insert into applications (id, secrets, redirect_uris, kind, allow_password_grant, scopes, default_scopes, require_consent, require_pushed_authorization, jwks, require_pkce, allow_plain_pkce, resources, jwt_access_token, allow_token_exchange, grant_types, token_endpoint_auth_method)
+values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
 on conflict (id)
-do update set secrets = excluded.secrets, redirect_uri = excluded.redirect_uri
-returning id, secrets, redirect_uri"#,
+do update set secrets = excluded.secrets, redirect_uris = excluded.redirect_uris, kind = excluded.kind, allow_password_grant = excluded.allow_password_grant, scopes = excluded.scopes, default_scopes = excluded.default_scopes, require_consent = excluded.require_consent, require_pushed_authorization = excluded.require_pushed_authorization, jwks = excluded.jwks, require_pkce = excluded.require_pkce, allow_plain_pkce = excluded.allow_plain_pkce, resources = excluded.resources, jwt_access_token = excluded.jwt_access_token, allow_token_exchange = excluded.allow_token_exchange, grant_types = excluded.grant_types, token_endpoint_auth_method = excluded.token_endpoint_auth_method
+returning id, secrets, redirect_uris, kind, allow_password_grant, scopes, default_scopes, require_consent, require_pushed_authorization, jwks, require_pkce, allow_plain_pkce, resources, jwt_access_token, allow_token_exchange, grant_types, token_endpoint_auth_method"#,
         )
         .bind(self.id)
         .bind(&secrets)
-        .bind(self.redirect_uri)
+        .bind(&redirect_uris)
+        .bind(self.kind.as_code())
+        .bind(self.allow_password_grant)
+        .bind(&scopes)
+        .bind(&default_scopes)
+        .bind(self.require_consent)
+        .bind(self.require_pushed_authorization)
+        .bind(&jwks)
+        .bind(self.require_pkce)
+        .bind(self.allow_plain_pkce)
+        .bind(&resources)
+        .bind(self.jwt_access_token)
+        .bind(self.allow_token_exchange)
+        .bind(&grant_types)
+        .bind(self.token_endpoint_auth_method.map(|method| method.as_str()))
         .fetch_one(executor)
         .await
     }
@@ -74,7 +484,7 @@ impl FindById {
         executor: E,
     ) -> Result<Option<Entity>, sqlx::Error> {
         sqlx::query_as(
-            r#"select id, secrets, redirect_uri
+            r#"select id, secrets, redirect_uris, kind, allow_password_grant, scopes, default_scopes, require_consent, require_pushed_authorization, jwks, require_pkce, allow_plain_pkce, resources, jwt_access_token, allow_token_exchange, grant_types, token_endpoint_auth_method
 from applications
 where id = $1
 limit 1"#,
@@ -84,3 +494,178 @@ limit 1"#,
         .await
     }
 }
+
+// the related rows are removed by cascade
+pub(crate) struct Delete {
+    id: Uuid,
+}
+
+impl Delete {
+    pub fn new(id: Uuid) -> Self {
+        Self { id }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("delete from applications where id = $1")
+            .bind(self.id)
+            .execute(executor)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+pub(crate) struct List;
+
+impl List {
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Vec<Entity>, sqlx::Error> {
+        sqlx::query_as(
+            r#"select id, secrets, redirect_uris, kind, allow_password_grant, scopes, default_scopes, require_consent, require_pushed_authorization, jwks, require_pkce, allow_plain_pkce, resources, jwt_access_token, allow_token_exchange, grant_types, token_endpoint_auth_method
+from applications
+order by id"#,
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use uuid::Uuid;
+
+    use super::{redirect_uri_matches, ApplicationKind, CodeChallengeError, Entity};
+    use crate::entity::code_challenge::CodeChallengeMethod;
+
+    fn entity(scopes: &[&str], default_scopes: &[&str]) -> Entity {
+        Entity {
+            id: Uuid::new_v4(),
+            kind: ApplicationKind::Public,
+            secrets: HashSet::new(),
+            redirect_uris: Vec::new(),
+            allow_password_grant: false,
+            scopes: scopes.iter().map(|item| item.to_string()).collect(),
+            default_scopes: default_scopes.iter().map(|item| item.to_string()).collect(),
+            require_consent: false,
+            require_pushed_authorization: false,
+            jwks: Default::default(),
+            require_pkce: true,
+            allow_plain_pkce: true,
+            resources: vec!["https://api.example.com".into()],
+            jwt_access_token: false,
+            allow_token_exchange: false,
+            grant_types: None,
+            token_endpoint_auth_method: None,
+        }
+    }
+
+    #[test]
+    fn should_grant_requested_scope() {
+        let app = entity(&["profile", "email", "admin"], &["profile"]);
+        assert_eq!(
+            app.grant_scope(Some("email profile email")).unwrap(),
+            Some("profile email".into())
+        );
+        assert_eq!(app.grant_scope(None).unwrap(), Some("profile".into()));
+        assert_eq!(app.grant_scope(Some("")).unwrap(), None);
+        assert_eq!(
+            app.grant_scope(Some("profile unknown")).unwrap_err().0,
+            "unknown"
+        );
+        assert_eq!(entity(&[], &[]).grant_scope(None).unwrap(), None);
+    }
+
+    #[test]
+    fn should_apply_pkce_policy() {
+        let mut app = entity(&[], &[]);
+        assert_eq!(
+            app.check_code_challenge(None, None).unwrap_err(),
+            CodeChallengeError::Missing
+        );
+        assert_eq!(
+            app.check_code_challenge(Some("challenge"), None).unwrap(),
+            Some(CodeChallengeMethod::Plain)
+        );
+
+        app.require_pkce = false;
+        app.allow_plain_pkce = false;
+        assert_eq!(app.check_code_challenge(None, None).unwrap(), None);
+        assert_eq!(
+            app.check_code_challenge(Some("challenge"), None)
+                .unwrap_err(),
+            CodeChallengeError::MethodNotAllowed
+        );
+        assert_eq!(
+            app.check_code_challenge(Some("challenge"), Some(CodeChallengeMethod::S256))
+                .unwrap(),
+            Some(CodeChallengeMethod::S256)
+        );
+    }
+
+    #[test]
+    fn should_match_exact_redirect_uri() {
+        assert!(redirect_uri_matches(
+            "https://service/callback",
+            "https://service/callback"
+        ));
+        assert!(!redirect_uri_matches(
+            "https://service/callback",
+            "https://service/callback?foo=bar"
+        ));
+        assert!(!redirect_uri_matches(
+            "https://service/callback",
+            "https://service:8443/callback"
+        ));
+        assert!(!redirect_uri_matches(
+            "http://localhost/callback",
+            "http://localhost:3000/callback"
+        ));
+    }
+
+    #[test]
+    fn should_ignore_port_for_loopback_redirect_uri() {
+        assert!(redirect_uri_matches(
+            "http://127.0.0.1/callback",
+            "http://127.0.0.1:51004/callback"
+        ));
+        assert!(redirect_uri_matches(
+            "http://127.0.0.1:8080/callback",
+            "http://127.0.0.1:51004/callback"
+        ));
+        assert!(redirect_uri_matches(
+            "http://[::1]/callback",
+            "http://[::1]:51004/callback"
+        ));
+        assert!(!redirect_uri_matches(
+            "http://127.0.0.1/callback",
+            "http://127.0.0.1:51004/other"
+        ));
+        assert!(!redirect_uri_matches(
+            "http://127.0.0.1/callback",
+            "http://[::1]:51004/callback"
+        ));
+        assert!(!redirect_uri_matches(
+            "https://127.0.0.1/callback",
+            "https://127.0.0.1:51004/callback"
+        ));
+    }
+
+    #[test]
+    fn should_grant_resource() {
+        let app = entity(&[], &[]);
+        assert_eq!(app.grant_resource(None).unwrap(), None);
+        assert_eq!(
+            app.grant_resource(Some("https://api.example.com")).unwrap(),
+            Some("https://api.example.com")
+        );
+        assert!(app
+            .grant_resource(Some("https://other.example.com"))
+            .is_err());
+    }
+}
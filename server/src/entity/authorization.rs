@@ -12,22 +12,29 @@ pub struct Entity {
     pub user_id: Uuid,
     pub state: String,
     pub scope: Option<String>,
-    pub code_challenge: String,
-    pub code_challenge_method: CodeChallengeMethod, // S256
-    pub response_type: ResponseType,                // code
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<CodeChallengeMethod>, // S256
+    pub response_type: ResponseType,                        // code
+    pub consumed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub redirect_uri: String,
+    pub consent_pending: bool,
+    pub resource: Option<String>,
+    pub nonce: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub authenticated_at: chrono::DateTime<chrono::Utc>,
 }
 
 impl<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> for Entity {
     fn from_row(row: &'r sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
         use sqlx::Row;
 
-        let code_challenge_method: u8 = row.try_get(6)?;
-        let code_challenge_method =
-            CodeChallengeMethod::try_from(code_challenge_method).map_err(|err| {
-                sqlx::Error::ColumnDecode {
-                    index: "code_challenge_method".into(),
-                    source: Box::new(err),
-                }
+        let code_challenge_method: Option<u8> = row.try_get(6)?;
+        let code_challenge_method = code_challenge_method
+            .map(CodeChallengeMethod::try_from)
+            .transpose()
+            .map_err(|err| sqlx::Error::ColumnDecode {
+                index: "code_challenge_method".into(),
+                source: Box::new(err),
             })?;
 
         let response_type: u8 = row.try_get(7)?;
@@ -46,6 +53,13 @@ impl<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> for Entity {
             code_challenge: row.try_get(5)?,
             code_challenge_method,
             response_type,
+            consumed_at: row.try_get(8)?,
+            redirect_uri: row.try_get(9)?,
+            consent_pending: row.try_get(10)?,
+            resource: row.try_get(11)?,
+            nonce: row.try_get(12)?,
+            created_at: row.try_get(13)?,
+            authenticated_at: row.try_get(14)?,
         })
     }
 }
@@ -56,9 +70,14 @@ pub struct Create<'a> {
     pub user_id: Uuid,
     pub state: &'a str,
     pub scope: Option<&'a str>,
-    pub code_challenge: &'a str,
-    pub code_challenge_method: CodeChallengeMethod, // S256
-    pub response_type: ResponseType,                // code
+    pub code_challenge: Option<&'a str>,
+    pub code_challenge_method: Option<CodeChallengeMethod>, // S256
+    pub response_type: ResponseType,                        // code
+    pub redirect_uri: &'a str,
+    pub consent_pending: bool,
+    pub resource: Option<&'a str>,
+    pub nonce: Option<&'a str>,
+    pub authenticated_at: chrono::DateTime<chrono::Utc>,
     pub time_to_live: Duration,
 }
 
@@ -70,9 +89,9 @@ impl Create<'_> {
         let now = chrono::Utc::now();
         let until = now + self.time_to_live;
         sqlx::query_as(
-            r#"insert into authorizations (code, client_id, user_id, state, scope, code_challenge, code_challenge_method, response_type, created_at, valid_until)
-values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
-returning code, client_id, user_id, state, scope, code_challenge, code_challenge_method, response_type"#,
+            r#"insert into authorizations (code, client_id, user_id, state, scope, code_challenge, code_challenge_method, response_type, redirect_uri, consent_pending, resource, nonce, created_at, valid_until, authenticated_at)
+values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+returning code, client_id, user_id, state, scope, code_challenge, code_challenge_method, response_type, consumed_at, redirect_uri, consent_pending, resource, nonce, created_at, authenticated_at"#,
         )
         .bind(self.code)
         .bind(self.client_id)
@@ -80,10 +99,15 @@ returning code, client_id, user_id, state, scope, code_challenge, code_challenge
         .bind(self.state)
         .bind(self.scope)
         .bind(self.code_challenge)
-        .bind(self.code_challenge_method.as_code())
+        .bind(self.code_challenge_method.map(|method| method.as_code()))
         .bind(self.response_type.as_code())
+        .bind(self.redirect_uri)
+        .bind(self.consent_pending)
+        .bind(self.resource)
+        .bind(self.nonce)
         .bind(now)
         .bind(until)
+        .bind(self.authenticated_at)
         .fetch_one(executor)
         .await
     }
@@ -104,7 +128,7 @@ impl<'a> FindByCode<'a> {
     ) -> Result<Option<Entity>, sqlx::Error> {
         let now = chrono::Utc::now();
         sqlx::query_as(
-            r#"select code, client_id, user_id, state, scope, code_challenge, code_challenge_method, response_type
+            r#"select code, client_id, user_id, state, scope, code_challenge, code_challenge_method, response_type, consumed_at, redirect_uri, consent_pending, resource, nonce, created_at, authenticated_at
 from authorizations
 where code = $1 and valid_until > $2
 limit 1"#,
@@ -115,3 +139,110 @@ limit 1"#,
         .await
     }
 }
+
+pub(crate) struct Consume<'a> {
+    pub code: &'a str,
+}
+
+impl<'a> Consume<'a> {
+    pub fn new(code: &'a str) -> Self {
+        Self { code }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<bool, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let result = sqlx::query(
+            r#"update authorizations
+set consumed_at = $2
+where code = $1 and consumed_at is null"#,
+        )
+        .bind(self.code)
+        .bind(now)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+}
+
+// the consent identifier is only given to the user who logged in
+pub(crate) struct StartConsent<'a> {
+    pub code: &'a str,
+    pub consent_id: &'a str,
+}
+
+impl<'a> StartConsent<'a> {
+    pub fn new(code: &'a str, consent_id: &'a str) -> Self {
+        Self { code, consent_id }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"update authorizations
+set consent_id = $2
+where code = $1 and consent_pending and consumed_at is null"#,
+        )
+        .bind(self.code)
+        .bind(self.consent_id)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+}
+
+pub(crate) struct FindPendingConsent<'a> {
+    pub consent_id: &'a str,
+}
+
+impl<'a> FindPendingConsent<'a> {
+    pub fn new(consent_id: &'a str) -> Self {
+        Self { consent_id }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Option<Entity>, sqlx::Error> {
+        let now = chrono::Utc::now();
+        sqlx::query_as(
+            r#"select code, client_id, user_id, state, scope, code_challenge, code_challenge_method, response_type, consumed_at, redirect_uri, consent_pending, resource, nonce, created_at, authenticated_at
+from authorizations
+where consent_id = $1 and consent_pending and consumed_at is null and valid_until > $2
+limit 1"#,
+        )
+        .bind(self.consent_id)
+        .bind(now)
+        .fetch_optional(executor)
+        .await
+    }
+}
+
+pub(crate) struct ApproveConsent<'a> {
+    pub code: &'a str,
+}
+
+impl<'a> ApproveConsent<'a> {
+    pub fn new(code: &'a str) -> Self {
+        Self { code }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"update authorizations
+set consent_pending = false, consent_id = null
+where code = $1 and consent_pending and consumed_at is null"#,
+        )
+        .bind(self.code)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+}
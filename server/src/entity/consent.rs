@@ -0,0 +1,84 @@
+use uuid::Uuid;
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Entity {
+    pub user_id: Uuid,
+    pub client_id: Uuid,
+    pub scope: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> for Entity {
+    fn from_row(row: &'r sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        Ok(Self {
+            user_id: row.try_get(0)?,
+            client_id: row.try_get(1)?,
+            scope: row.try_get(2)?,
+            created_at: row.try_get(3)?,
+        })
+    }
+}
+
+pub struct Create<'a> {
+    pub user_id: Uuid,
+    pub client_id: Uuid,
+    pub scope: &'a str,
+}
+
+impl Create<'_> {
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Entity, sqlx::Error> {
+        let now = chrono::Utc::now();
+        sqlx::query_as(
+            r#"insert into consents (user_id, client_id, scope, created_at)
+values ($1, $2, $3, $4)
+on conflict (user_id, client_id, scope)
+do update set created_at = excluded.created_at
+returning user_id, client_id, scope, created_at"#,
+        )
+        .bind(self.user_id)
+        .bind(self.client_id)
+        .bind(self.scope)
+        .bind(now)
+        .fetch_one(executor)
+        .await
+    }
+}
+
+// the scope is the one granted by the application, so the same set of scopes is always in the same order
+pub(crate) struct FindByScope<'a> {
+    user_id: Uuid,
+    client_id: Uuid,
+    scope: &'a str,
+}
+
+impl<'a> FindByScope<'a> {
+    pub fn new(user_id: Uuid, client_id: Uuid, scope: &'a str) -> Self {
+        Self {
+            user_id,
+            client_id,
+            scope,
+        }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Option<Entity>, sqlx::Error> {
+        sqlx::query_as(
+            r#"select user_id, client_id, scope, created_at
+from consents
+where user_id = $1 and client_id = $2 and scope = $3
+limit 1"#,
+        )
+        .bind(self.user_id)
+        .bind(self.client_id)
+        .bind(self.scope)
+        .fetch_optional(executor)
+        .await
+    }
+}
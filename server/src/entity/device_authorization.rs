@@ -0,0 +1,236 @@
+use std::time::Duration;
+
+use uuid::Uuid;
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Entity {
+    pub device_code: String,
+    pub user_code: String,
+    pub client_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub scope: Option<String>,
+    pub interval: u32,
+    pub last_polled_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub consumed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub valid_until: chrono::DateTime<chrono::Utc>,
+    pub denied_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> for Entity {
+    fn from_row(row: &'r sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        Ok(Self {
+            device_code: row.try_get(0)?,
+            user_code: row.try_get(1)?,
+            client_id: row.try_get(2)?,
+            user_id: row.try_get(3)?,
+            scope: row.try_get(4)?,
+            interval: row.try_get(5)?,
+            last_polled_at: row.try_get(6)?,
+            consumed_at: row.try_get(7)?,
+            valid_until: row.try_get(8)?,
+            denied_at: row.try_get(9)?,
+        })
+    }
+}
+
+pub struct Create<'a> {
+    pub device_code: &'a str,
+    pub user_code: &'a str,
+    pub client_id: Uuid,
+    pub scope: Option<&'a str>,
+    pub interval: u32,
+    pub time_to_live: Duration,
+}
+
+impl Create<'_> {
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Entity, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let until = now + self.time_to_live;
+        sqlx::query_as(
+            r#"insert into device_authorizations (device_code, user_code, client_id, scope, interval, created_at, valid_until)
+values ($1, $2, $3, $4, $5, $6, $7)
+returning device_code, user_code, client_id, user_id, scope, interval, last_polled_at, consumed_at, valid_until, denied_at"#,
+        )
+        .bind(self.device_code)
+        .bind(self.user_code)
+        .bind(self.client_id)
+        .bind(self.scope)
+        .bind(self.interval)
+        .bind(now)
+        .bind(until)
+        .fetch_one(executor)
+        .await
+    }
+}
+
+pub(crate) struct FindByDeviceCode<'a> {
+    device_code: &'a str,
+}
+
+impl<'a> FindByDeviceCode<'a> {
+    pub fn new(device_code: &'a str) -> Self {
+        Self { device_code }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Option<Entity>, sqlx::Error> {
+        sqlx::query_as(
+            r#"select device_code, user_code, client_id, user_id, scope, interval, last_polled_at, consumed_at, valid_until, denied_at
+from device_authorizations
+where device_code = $1
+limit 1"#,
+        )
+        .bind(self.device_code)
+        .fetch_optional(executor)
+        .await
+    }
+}
+
+pub(crate) struct FindPendingByUserCode<'a> {
+    user_code: &'a str,
+}
+
+impl<'a> FindPendingByUserCode<'a> {
+    pub fn new(user_code: &'a str) -> Self {
+        Self { user_code }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Option<Entity>, sqlx::Error> {
+        let now = chrono::Utc::now();
+        sqlx::query_as(
+            r#"select device_code, user_code, client_id, user_id, scope, interval, last_polled_at, consumed_at, valid_until, denied_at
+from device_authorizations
+where user_code = $1 and user_id is null and denied_at is null and valid_until > $2
+limit 1"#,
+        )
+        .bind(self.user_code)
+        .bind(now)
+        .fetch_optional(executor)
+        .await
+    }
+}
+
+pub(crate) struct Approve<'a> {
+    user_code: &'a str,
+    user_id: Uuid,
+}
+
+impl<'a> Approve<'a> {
+    pub fn new(user_code: &'a str, user_id: Uuid) -> Self {
+        Self { user_code, user_id }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<bool, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let result = sqlx::query(
+            r#"update device_authorizations
+set user_id = $2
+where user_code = $1 and user_id is null and denied_at is null and valid_until > $3"#,
+        )
+        .bind(self.user_code)
+        .bind(self.user_id)
+        .bind(now)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+}
+
+pub(crate) struct Deny<'a> {
+    user_code: &'a str,
+}
+
+impl<'a> Deny<'a> {
+    pub fn new(user_code: &'a str) -> Self {
+        Self { user_code }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<bool, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let result = sqlx::query(
+            r#"update device_authorizations
+set denied_at = $2
+where user_code = $1 and user_id is null and denied_at is null and valid_until > $2"#,
+        )
+        .bind(self.user_code)
+        .bind(now)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+}
+
+pub(crate) struct Poll<'a> {
+    device_code: &'a str,
+    interval: u32,
+}
+
+impl<'a> Poll<'a> {
+    pub fn new(device_code: &'a str, interval: u32) -> Self {
+        Self {
+            device_code,
+            interval,
+        }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<(), sqlx::Error> {
+        let now = chrono::Utc::now();
+        sqlx::query(
+            r#"update device_authorizations
+set last_polled_at = $2, interval = $3
+where device_code = $1"#,
+        )
+        .bind(self.device_code)
+        .bind(now)
+        .bind(self.interval)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
+
+pub(crate) struct Consume<'a> {
+    device_code: &'a str,
+}
+
+impl<'a> Consume<'a> {
+    pub fn new(device_code: &'a str) -> Self {
+        Self { device_code }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<bool, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let result = sqlx::query(
+            r#"update device_authorizations
+set consumed_at = $2
+where device_code = $1 and consumed_at is null"#,
+        )
+        .bind(self.device_code)
+        .bind(now)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+}
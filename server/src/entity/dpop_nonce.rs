@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+pub struct Create<'a> {
+    pub nonce: &'a str,
+    pub time_to_live: Duration,
+}
+
+impl Create<'_> {
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<(), sqlx::Error> {
+        let now = chrono::Utc::now();
+        let until = now + self.time_to_live;
+        sqlx::query(
+            r#"insert into dpop_nonces (nonce, created_at, valid_until)
+values ($1, $2, $3)"#,
+        )
+        .bind(self.nonce)
+        .bind(now)
+        .bind(until)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
+
+// a nonce can be used by several proofs until it expires, the replays are detected with the jti
+pub(crate) struct IsValid<'a> {
+    nonce: &'a str,
+}
+
+impl<'a> IsValid<'a> {
+    pub fn new(nonce: &'a str) -> Self {
+        Self { nonce }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<bool, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let found: Option<(String,)> = sqlx::query_as(
+            r#"select nonce
+from dpop_nonces
+where nonce = $1 and valid_until > $2
+limit 1"#,
+        )
+        .bind(self.nonce)
+        .bind(now)
+        .fetch_optional(executor)
+        .await?;
+        Ok(found.is_some())
+    }
+}
+
+// the expired nonces are useless, they are dropped so that the table doesn't grow forever
+pub(crate) struct DeleteExpired;
+
+impl DeleteExpired {
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<u64, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let result = sqlx::query(
+            r#"delete from dpop_nonces
+where valid_until <= $1"#,
+        )
+        .bind(now)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
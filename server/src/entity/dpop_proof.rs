@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+// RFC 9449 §11.1: returns false when a proof with the same identifier has already been seen
+pub struct Create<'a> {
+    pub jti: &'a str,
+    pub time_to_live: Duration,
+}
+
+impl Create<'_> {
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<bool, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let until = now + self.time_to_live;
+        // an expired identifier can be reused
+        let result = sqlx::query(
+            r#"insert into dpop_proofs (jti, created_at, valid_until)
+values ($1, $2, $3)
+on conflict (jti) do update
+set created_at = excluded.created_at, valid_until = excluded.valid_until
+where dpop_proofs.valid_until <= excluded.created_at"#,
+        )
+        .bind(self.jti)
+        .bind(now)
+        .bind(until)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+// the expired proof identifiers are useless, they are dropped so that the table doesn't grow forever
+pub(crate) struct DeleteExpired;
+
+impl DeleteExpired {
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<u64, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let result = sqlx::query(
+            r#"delete from dpop_proofs
+where valid_until <= $1"#,
+        )
+        .bind(now)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
@@ -1,7 +1,16 @@
 pub(crate) mod application;
 pub(crate) mod authorization;
 pub(crate) mod code_challenge;
+pub(crate) mod consent;
+pub(crate) mod device_authorization;
+pub(crate) mod dpop_nonce;
+pub(crate) mod dpop_proof;
 pub(crate) mod provider;
+pub(crate) mod pushed_authorization;
+pub(crate) mod refresh_token;
+pub(crate) mod registration;
+pub(crate) mod request_object;
 pub(crate) mod response_type;
 pub(crate) mod session;
+pub(crate) mod signing_key;
 pub(crate) mod user;
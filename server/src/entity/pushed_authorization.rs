@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use uuid::Uuid;
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Entity {
+    pub request_uri: String,
+    pub client_id: Uuid,
+    pub parameters: String,
+    pub consumed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub valid_until: chrono::DateTime<chrono::Utc>,
+}
+
+impl<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> for Entity {
+    fn from_row(row: &'r sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        Ok(Self {
+            request_uri: row.try_get(0)?,
+            client_id: row.try_get(1)?,
+            parameters: row.try_get(2)?,
+            consumed_at: row.try_get(3)?,
+            valid_until: row.try_get(4)?,
+        })
+    }
+}
+
+pub struct Create<'a> {
+    pub request_uri: &'a str,
+    pub client_id: Uuid,
+    pub parameters: &'a str,
+    pub time_to_live: Duration,
+}
+
+impl Create<'_> {
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Entity, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let until = now + self.time_to_live;
+        sqlx::query_as(
+            r#"insert into pushed_authorizations (request_uri, client_id, parameters, created_at, valid_until)
+values ($1, $2, $3, $4, $5)
+returning request_uri, client_id, parameters, consumed_at, valid_until"#,
+        )
+        .bind(self.request_uri)
+        .bind(self.client_id)
+        .bind(self.parameters)
+        .bind(now)
+        .bind(until)
+        .fetch_one(executor)
+        .await
+    }
+}
+
+// only returns the requests that have not expired nor been used
+pub(crate) struct FindByRequestUri<'a> {
+    request_uri: &'a str,
+}
+
+impl<'a> FindByRequestUri<'a> {
+    pub fn new(request_uri: &'a str) -> Self {
+        Self { request_uri }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Option<Entity>, sqlx::Error> {
+        let now = chrono::Utc::now();
+        sqlx::query_as(
+            r#"select request_uri, client_id, parameters, consumed_at, valid_until
+from pushed_authorizations
+where request_uri = $1 and consumed_at is null and valid_until > $2
+limit 1"#,
+        )
+        .bind(self.request_uri)
+        .bind(now)
+        .fetch_optional(executor)
+        .await
+    }
+}
+
+// RFC 9126 §4: the request uri should be used only once
+pub(crate) struct Consume<'a> {
+    request_uri: &'a str,
+}
+
+impl<'a> Consume<'a> {
+    pub fn new(request_uri: &'a str) -> Self {
+        Self { request_uri }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<bool, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let result = sqlx::query(
+            r#"update pushed_authorizations
+set consumed_at = $2
+where request_uri = $1 and consumed_at is null and valid_until > $2"#,
+        )
+        .bind(self.request_uri)
+        .bind(now)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use uuid::Uuid;
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Entity {
+    pub token: String,
+    pub family: String,
+    pub client_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub scope: Option<String>,
+    pub authorization_code: Option<String>,
+    pub consumed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub dpop_jkt: Option<String>,
+    pub audience: Option<String>,
+}
+
+impl<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> for Entity {
+    fn from_row(row: &'r sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        Ok(Self {
+            token: row.try_get(0)?,
+            family: row.try_get(1)?,
+            client_id: row.try_get(2)?,
+            user_id: row.try_get(3)?,
+            scope: row.try_get(4)?,
+            authorization_code: row.try_get(5)?,
+            consumed_at: row.try_get(6)?,
+            dpop_jkt: row.try_get(7)?,
+            audience: row.try_get(8)?,
+        })
+    }
+}
+
+pub struct Create<'a> {
+    pub token: &'a str,
+    pub family: &'a str,
+    pub access_token: &'a str,
+    pub time_to_live: Duration,
+}
+
+impl Create<'_> {
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<(), sqlx::Error> {
+        let now = chrono::Utc::now();
+        let until = now + self.time_to_live;
+        sqlx::query(
+            r#"insert into refresh_tokens (token, family, access_token, created_at, valid_until)
+values ($1, $2, $3, $4, $5)"#,
+        )
+        .bind(self.token)
+        .bind(self.family)
+        .bind(self.access_token)
+        .bind(now)
+        .bind(until)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
+
+pub(crate) struct FindByToken<'a> {
+    token: &'a str,
+}
+
+impl<'a> FindByToken<'a> {
+    pub fn new(token: &'a str) -> Self {
+        Self { token }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Option<Entity>, sqlx::Error> {
+        let now = chrono::Utc::now();
+        sqlx::query_as(
+            r#"select refresh_tokens.token, refresh_tokens.family, sessions.client_id, sessions.user_id, sessions.scope, sessions.authorization_code, refresh_tokens.consumed_at, sessions.dpop_jkt, sessions.audience
+from refresh_tokens
+join sessions on sessions.access_token = refresh_tokens.access_token
+where refresh_tokens.token = $1 and refresh_tokens.valid_until > $2 and sessions.revoked_at is null
+limit 1"#,
+        )
+        .bind(self.token)
+        .bind(now)
+        .fetch_optional(executor)
+        .await
+    }
+}
+
+pub(crate) struct Consume<'a> {
+    token: &'a str,
+}
+
+impl<'a> Consume<'a> {
+    pub fn new(token: &'a str) -> Self {
+        Self { token }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<bool, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let result = sqlx::query(
+            r#"update refresh_tokens
+set consumed_at = $2
+where token = $1 and consumed_at is null"#,
+        )
+        .bind(self.token)
+        .bind(now)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,118 @@
+use uuid::Uuid;
+
+// the registration access token is a bearer credential, only its hash is stored
+fn hash_token(token: &str) -> String {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use sha2::{Digest, Sha256};
+
+    URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Entity {
+    pub client_id: Uuid,
+    // the metadata as provided by the client, encoded in json
+    pub metadata: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> for Entity {
+    fn from_row(row: &'r sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        Ok(Self {
+            client_id: row.try_get(0)?,
+            metadata: row.try_get(1)?,
+            created_at: row.try_get(2)?,
+        })
+    }
+}
+
+pub struct Create<'a> {
+    pub client_id: Uuid,
+    pub access_token: &'a str,
+    pub metadata: &'a str,
+}
+
+impl Create<'_> {
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Entity, sqlx::Error> {
+        let now = chrono::Utc::now();
+        sqlx::query_as(
+            r#"insert into registrations (client_id, access_token_hash, metadata, created_at)
+values ($1, $2, $3, $4)
+returning client_id, metadata, created_at"#,
+        )
+        .bind(self.client_id)
+        .bind(hash_token(self.access_token))
+        .bind(self.metadata)
+        .bind(now)
+        .fetch_one(executor)
+        .await
+    }
+}
+
+// RFC 7592 §2.2: the metadata are replaced when the client updates its registration
+pub(crate) struct UpdateMetadata<'a> {
+    client_id: Uuid,
+    metadata: &'a str,
+}
+
+impl<'a> UpdateMetadata<'a> {
+    pub fn new(client_id: Uuid, metadata: &'a str) -> Self {
+        Self {
+            client_id,
+            metadata,
+        }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Option<Entity>, sqlx::Error> {
+        sqlx::query_as(
+            r#"update registrations
+set metadata = $2
+where client_id = $1
+returning client_id, metadata, created_at"#,
+        )
+        .bind(self.client_id)
+        .bind(self.metadata)
+        .fetch_optional(executor)
+        .await
+    }
+}
+
+// RFC 7592 §3: the registration access token only gives access to its own application
+pub(crate) struct FindByAccessToken<'a> {
+    client_id: Uuid,
+    access_token: &'a str,
+}
+
+impl<'a> FindByAccessToken<'a> {
+    pub fn new(client_id: Uuid, access_token: &'a str) -> Self {
+        Self {
+            client_id,
+            access_token,
+        }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Option<Entity>, sqlx::Error> {
+        sqlx::query_as(
+            r#"select client_id, metadata, created_at
+from registrations
+where client_id = $1 and access_token_hash = $2
+limit 1"#,
+        )
+        .bind(self.client_id)
+        .bind(hash_token(self.access_token))
+        .fetch_optional(executor)
+        .await
+    }
+}
@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use uuid::Uuid;
+
+#[derive(Clone, Debug)]
+pub struct Entity {
+    pub client_id: Uuid,
+    pub request: String,
+}
+
+impl<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> for Entity {
+    fn from_row(row: &'r sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        Ok(Self {
+            client_id: row.try_get(0)?,
+            request: row.try_get(1)?,
+        })
+    }
+}
+
+pub struct Create<'a> {
+    pub id: &'a str,
+    pub client_id: Uuid,
+    pub request: &'a str,
+    pub time_to_live: Duration,
+}
+
+impl Create<'_> {
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Entity, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let until = now + self.time_to_live;
+        sqlx::query_as(
+            r#"insert into request_objects (id, client_id, request, created_at, valid_until)
+values ($1, $2, $3, $4, $5)
+returning client_id, request"#,
+        )
+        .bind(self.id)
+        .bind(self.client_id)
+        .bind(self.request)
+        .bind(now)
+        .bind(until)
+        .fetch_one(executor)
+        .await
+    }
+}
+
+// only returns the request objects that have not expired
+pub(crate) struct FindById<'a> {
+    id: &'a str,
+}
+
+impl<'a> FindById<'a> {
+    pub fn new(id: &'a str) -> Self {
+        Self { id }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Option<Entity>, sqlx::Error> {
+        let now = chrono::Utc::now();
+        sqlx::query_as(
+            r#"select client_id, request
+from request_objects
+where id = $1 and valid_until > $2
+limit 1"#,
+        )
+        .bind(self.id)
+        .bind(now)
+        .fetch_optional(executor)
+        .await
+    }
+}
+
+// RFC 7519 §4.1.7: returns false when the client already used the identifier
+pub struct CreateJti<'a> {
+    pub client_id: Uuid,
+    pub jti: &'a str,
+    pub valid_until: chrono::DateTime<chrono::Utc>,
+}
+
+impl CreateJti<'_> {
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<bool, sqlx::Error> {
+        let now = chrono::Utc::now();
+        // an expired identifier can be reused
+        let result = sqlx::query(
+            r#"insert into request_object_jtis (client_id, jti, created_at, valid_until)
+values ($1, $2, $3, $4)
+on conflict (client_id, jti) do update
+set created_at = excluded.created_at, valid_until = excluded.valid_until
+where request_object_jtis.valid_until <= excluded.created_at"#,
+        )
+        .bind(self.client_id)
+        .bind(self.jti)
+        .bind(now)
+        .bind(self.valid_until)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+// the expired request objects and identifiers are dropped so that the tables don't grow forever
+pub(crate) struct DeleteExpired;
+
+impl DeleteExpired {
+    pub async fn execute(&self, conn: &mut sqlx::SqliteConnection) -> Result<u64, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let objects = sqlx::query("delete from request_objects where valid_until <= $1")
+            .bind(now)
+            .execute(&mut *conn)
+            .await?;
+        let jtis = sqlx::query("delete from request_object_jtis where valid_until <= $1")
+            .bind(now)
+            .execute(&mut *conn)
+            .await?;
+        Ok(objects.rows_affected() + jtis.rows_affected())
+    }
+}
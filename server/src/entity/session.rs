@@ -2,34 +2,65 @@ use std::time::Duration;
 
 use uuid::Uuid;
 
+// RFC 8693 §4.1: the party acting on behalf of the subject, the nested actors being the previous ones
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Actor {
+    pub sub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Box<Actor>>,
+}
+
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct Entity {
     pub access_token: String,
     pub client_id: Uuid,
-    pub user_id: Uuid,
+    pub user_id: Option<Uuid>,
     pub scope: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
     pub valid_until: chrono::DateTime<chrono::Utc>,
+    pub dpop_jkt: Option<String>,
+    pub audience: Option<String>,
+    pub actor: Option<Actor>,
 }
 
 impl<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> for Entity {
     fn from_row(row: &'r sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
         use sqlx::Row;
 
+        let actor: Option<String> = row.try_get(8)?;
+        let actor = actor
+            .map(|value| serde_json::from_str(&value))
+            .transpose()
+            .map_err(|err| sqlx::Error::ColumnDecode {
+                index: "actor".into(),
+                source: Box::new(err),
+            })?;
+
         Ok(Self {
             access_token: row.try_get(0)?,
             client_id: row.try_get(1)?,
             user_id: row.try_get(2)?,
             scope: row.try_get(3)?,
-            valid_until: row.try_get(4)?,
+            created_at: row.try_get(4)?,
+            valid_until: row.try_get(5)?,
+            dpop_jkt: row.try_get(6)?,
+            audience: row.try_get(7)?,
+            actor,
         })
     }
 }
 
 pub struct Create<'a> {
     pub access_token: &'a str,
+    // RFC 9068 §2.2: the identifier of the JWT access token, if any
+    pub jti: Option<&'a str>,
     pub client_id: Uuid,
-    pub user_id: Uuid,
+    pub user_id: Option<Uuid>,
     pub scope: Option<&'a str>,
+    pub authorization_code: Option<&'a str>,
+    pub dpop_jkt: Option<&'a str>,
+    pub audience: Option<&'a str>,
+    pub actor: Option<&'a Actor>,
     pub time_to_live: Duration,
 }
 
@@ -40,18 +71,169 @@ impl Create<'_> {
     ) -> Result<Entity, sqlx::Error> {
         let now = chrono::Utc::now();
         let until = now + self.time_to_live;
+        let actor = self
+            .actor
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|err| sqlx::Error::Encode(Box::new(err)))?;
         sqlx::query_as(
-            r#"insert into sessions (access_token, client_id, user_id, scope, created_at, valid_until)
-values ($1, $2, $3, $4, $5, $6)
-returning access_token, client_id, user_id, scope, valid_until"#,
+            r#"insert into sessions (access_token, client_id, user_id, scope, authorization_code, dpop_jkt, audience, actor, created_at, valid_until, jti)
+values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+returning access_token, client_id, user_id, scope, created_at, valid_until, dpop_jkt, audience, actor"#,
         )
         .bind(self.access_token)
         .bind(self.client_id)
         .bind(self.user_id)
         .bind(self.scope)
+        .bind(self.authorization_code)
+        .bind(self.dpop_jkt)
+        .bind(self.audience)
+        .bind(actor)
         .bind(now)
         .bind(until)
+        .bind(self.jti)
         .fetch_one(executor)
         .await
     }
 }
+
+// The sessions of the JWT access tokens are only found by the identifier of a verified token,
+// never by a raw string
+pub(crate) struct FindByAccessToken<'a> {
+    access_token: &'a str,
+    jwt: bool,
+}
+
+impl<'a> FindByAccessToken<'a> {
+    pub fn new(access_token: &'a str) -> Self {
+        Self {
+            access_token,
+            jwt: false,
+        }
+    }
+
+    pub fn jwt(jti: &'a str) -> Self {
+        Self {
+            access_token: jti,
+            jwt: true,
+        }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Option<Entity>, sqlx::Error> {
+        let now = chrono::Utc::now();
+        sqlx::query_as(
+            r#"select access_token, client_id, user_id, scope, created_at, valid_until, dpop_jkt, audience, actor
+from sessions
+where case when $3 then jti = $1 else access_token = $1 and jti is null end
+    and valid_until > $2 and revoked_at is null
+limit 1"#,
+        )
+        .bind(self.access_token)
+        .bind(now)
+        .bind(self.jwt)
+        .fetch_optional(executor)
+        .await
+    }
+}
+
+pub(crate) struct RevokeByAuthorizationCode<'a> {
+    code: &'a str,
+}
+
+impl<'a> RevokeByAuthorizationCode<'a> {
+    pub fn new(code: &'a str) -> Self {
+        Self { code }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<u64, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let result = sqlx::query(
+            r#"update sessions
+set revoked_at = $2
+where authorization_code = $1 and revoked_at is null"#,
+        )
+        .bind(self.code)
+        .bind(now)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+pub(crate) struct RevokeByRefreshFamily<'a> {
+    family: &'a str,
+}
+
+impl<'a> RevokeByRefreshFamily<'a> {
+    pub fn new(family: &'a str) -> Self {
+        Self { family }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<u64, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let result = sqlx::query(
+            r#"update sessions
+set revoked_at = $2
+where revoked_at is null
+    and access_token in (select access_token from refresh_tokens where family = $1)"#,
+        )
+        .bind(self.family)
+        .bind(now)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+pub(crate) struct RevokeByAccessToken<'a> {
+    access_token: &'a str,
+    jwt: bool,
+    client_id: Uuid,
+}
+
+impl<'a> RevokeByAccessToken<'a> {
+    pub fn new(access_token: &'a str, client_id: Uuid) -> Self {
+        Self {
+            access_token,
+            jwt: false,
+            client_id,
+        }
+    }
+
+    pub fn jwt(jti: &'a str, client_id: Uuid) -> Self {
+        Self {
+            access_token: jti,
+            jwt: true,
+            client_id,
+        }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<bool, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let result = sqlx::query(
+            r#"update sessions
+set revoked_at = $3
+where case when $4 then jti = $1 else access_token = $1 and jti is null end
+    and client_id = $2 and revoked_at is null"#,
+        )
+        .bind(self.access_token)
+        .bind(self.client_id)
+        .bind(now)
+        .bind(self.jwt)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,114 @@
+use chrono::{DateTime, Utc};
+
+#[derive(Clone, Debug)]
+pub struct Entity {
+    pub kid: String,
+    pub private_key: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+    pub retired_at: Option<DateTime<Utc>>,
+}
+
+impl<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> for Entity {
+    fn from_row(row: &'r sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        Ok(Self {
+            kid: row.try_get(0)?,
+            private_key: row.try_get(1)?,
+            created_at: row.try_get(2)?,
+            retired_at: row.try_get(3)?,
+        })
+    }
+}
+
+pub struct Create<'a> {
+    pub kid: &'a str,
+    pub private_key: &'a [u8],
+}
+
+impl Create<'_> {
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Entity, sqlx::Error> {
+        let now = chrono::Utc::now();
+        sqlx::query_as(
+            r#"insert into signing_keys (kid, private_key, created_at)
+values ($1, $2, $3)
+returning kid, private_key, created_at, retired_at"#,
+        )
+        .bind(self.kid)
+        .bind(self.private_key)
+        .bind(now)
+        .fetch_one(executor)
+        .await
+    }
+}
+
+// the keys that are not retired yet, the most recent first
+pub(crate) struct ListPublished;
+
+impl ListPublished {
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<Vec<Entity>, sqlx::Error> {
+        let now = chrono::Utc::now();
+        sqlx::query_as(
+            r#"select kid, private_key, created_at, retired_at
+from signing_keys
+where retired_at is null or retired_at > $1
+order by created_at desc"#,
+        )
+        .bind(now)
+        .fetch_all(executor)
+        .await
+    }
+}
+
+// schedules the retirement of every key but the given one
+pub(crate) struct RetireOthers<'a> {
+    pub kid: &'a str,
+    pub retired_at: DateTime<Utc>,
+}
+
+impl<'a> RetireOthers<'a> {
+    pub fn new(kid: &'a str, retired_at: DateTime<Utc>) -> Self {
+        Self { kid, retired_at }
+    }
+
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            r#"update signing_keys
+set retired_at = $2
+where kid != $1 and retired_at is null"#,
+        )
+        .bind(self.kid)
+        .bind(self.retired_at)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+pub(crate) struct DeleteRetired;
+
+impl DeleteRetired {
+    pub async fn execute<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+    ) -> Result<u64, sqlx::Error> {
+        let now = chrono::Utc::now();
+        let result = sqlx::query(
+            r#"delete from signing_keys
+where retired_at is not null and retired_at <= $1"#,
+        )
+        .bind(now)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
@@ -16,7 +16,7 @@ pub struct Entity {
 
 impl Entity {
     pub fn check_password(&self, expected: &str) -> bool {
-        self.password.as_ref().map_or(false, |hash| {
+        self.password.as_ref().is_some_and(|hash| {
             password_auth::verify_password(expected.as_bytes(), hash.as_str()).is_ok()
         })
     }
@@ -104,7 +104,7 @@ impl<'a> FindByAccessToken<'a> {
             r#"select users.id, users.login, users.email, users.password
 from users
 join sessions on sessions.user_id = users.id
-where sessions.access_token = $1 and sessions.valid_until > $2
+where sessions.access_token = $1 and sessions.valid_until > $2 and sessions.revoked_at is null
 limit 1"#,
         )
         .bind(self.access_token)
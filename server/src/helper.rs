@@ -40,3 +40,38 @@ pub(crate) fn generate_token(length: usize) -> String {
         .map(char::from)
         .collect()
 }
+
+// RFC 8628 §6.1: avoid vowels and ambiguous characters in user codes
+const USER_CODE_CHARSET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+
+pub(crate) fn generate_user_code() -> String {
+    use rand::{thread_rng, Rng};
+
+    let mut rng = thread_rng();
+    (0..8)
+        .map(|_| USER_CODE_CHARSET[rng.gen_range(0..USER_CODE_CHARSET.len())] as char)
+        .collect()
+}
+
+pub(crate) fn normalize_user_code(input: &str) -> String {
+    input
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+pub(crate) fn format_user_code(code: &str) -> String {
+    if code.len() == 8 {
+        format!("{}-{}", &code[..4], &code[4..])
+    } else {
+        code.to_string()
+    }
+}
+
+// RFC 8707 §2: a single resource is supported, so a repeated parameter is refused
+pub(crate) fn has_repeated_resource(form: &[u8]) -> bool {
+    serde_urlencoded::from_bytes::<Vec<(String, String)>>(form)
+        .map(|pairs| pairs.iter().filter(|(key, _)| key == "resource").count() > 1)
+        .unwrap_or(false)
+}
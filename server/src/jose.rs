@@ -0,0 +1,391 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+// RFC 7518 §3.1: only asymmetric algorithms are supported, "none" is rejected
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub(crate) enum Algorithm {
+    RS256,
+    PS256,
+    ES256,
+    ES384,
+    EdDSA,
+}
+
+pub(crate) const SUPPORTED_ALGORITHMS: [Algorithm; 5] = [
+    Algorithm::RS256,
+    Algorithm::PS256,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+impl Algorithm {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::RS256 => "RS256",
+            Self::PS256 => "PS256",
+            Self::ES256 => "ES256",
+            Self::ES384 => "ES384",
+            Self::EdDSA => "EdDSA",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "kty")]
+pub(crate) enum JwkKey {
+    #[serde(rename = "RSA")]
+    Rsa { n: String, e: String },
+    #[serde(rename = "EC")]
+    Ec { crv: String, x: String, y: String },
+    #[serde(rename = "OKP")]
+    Okp { crv: String, x: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub(crate) struct Jwk {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alg: Option<Algorithm>,
+    #[serde(flatten)]
+    pub key: JwkKey,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub(crate) struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+// RFC 7517 §5: the keys with a type or an algorithm that isn't understood are ignored
+impl<'de> serde::Deserialize<'de> for JwkSet {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct RawJwkSet {
+            keys: Vec<serde_json::Value>,
+        }
+
+        let raw = RawJwkSet::deserialize(deserializer)?;
+        let keys = raw
+            .keys
+            .into_iter()
+            .filter_map(|value| match serde_json::from_value(value) {
+                Ok(key) => Some(key),
+                Err(err) => {
+                    tracing::debug!(message = "ignoring unsupported key", error = %err);
+                    None
+                }
+            })
+            .collect();
+        Ok(Self { keys })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum JoseError {
+    Malformed,
+    KeyNotFound,
+    InvalidSignature,
+    Signing,
+}
+
+impl std::error::Error for JoseError {}
+
+impl std::fmt::Display for JoseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "malformed token"),
+            Self::KeyNotFound => write!(f, "no key matching the token"),
+            Self::InvalidSignature => write!(f, "invalid signature"),
+            Self::Signing => write!(f, "unable to sign token"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct Header {
+    pub alg: Algorithm,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub typ: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwk: Option<Jwk>,
+}
+
+fn decode_part(value: &str) -> Result<Vec<u8>, JoseError> {
+    URL_SAFE_NO_PAD
+        .decode(value)
+        .map_err(|_| JoseError::Malformed)
+}
+
+impl Jwk {
+    // RFC 7638 §3.2: hash of the required members only, without whitespace and in lexicographic order
+    pub(crate) fn thumbprint(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        let members = match &self.key {
+            JwkKey::Rsa { n, e } => format!(r#"{{"e":"{e}","kty":"RSA","n":"{n}"}}"#),
+            JwkKey::Ec { crv, x, y } => {
+                format!(r#"{{"crv":"{crv}","kty":"EC","x":"{x}","y":"{y}"}}"#)
+            }
+            JwkKey::Okp { crv, x } => format!(r#"{{"crv":"{crv}","kty":"OKP","x":"{x}"}}"#),
+        };
+        URL_SAFE_NO_PAD.encode(Sha256::digest(members.as_bytes()))
+    }
+
+    fn accepts(&self, alg: Algorithm) -> bool {
+        if self.alg.is_some_and(|value| value != alg) {
+            return false;
+        }
+        match (&self.key, alg) {
+            (JwkKey::Rsa { .. }, Algorithm::RS256 | Algorithm::PS256) => true,
+            (JwkKey::Ec { crv, .. }, Algorithm::ES256) => crv == "P-256",
+            (JwkKey::Ec { crv, .. }, Algorithm::ES384) => crv == "P-384",
+            (JwkKey::Okp { crv, .. }, Algorithm::EdDSA) => crv == "Ed25519",
+            _ => false,
+        }
+    }
+
+    fn verify(&self, alg: Algorithm, message: &[u8], signature: &[u8]) -> Result<(), JoseError> {
+        use ring::signature;
+
+        match &self.key {
+            JwkKey::Rsa { n, e } => {
+                let params = match alg {
+                    Algorithm::PS256 => &signature::RSA_PSS_2048_8192_SHA256,
+                    _ => &signature::RSA_PKCS1_2048_8192_SHA256,
+                };
+                signature::RsaPublicKeyComponents {
+                    n: decode_part(n)?,
+                    e: decode_part(e)?,
+                }
+                .verify(params, message, signature)
+            }
+            JwkKey::Ec { x, y, .. } => {
+                let params = match alg {
+                    Algorithm::ES384 => &signature::ECDSA_P384_SHA384_FIXED,
+                    _ => &signature::ECDSA_P256_SHA256_FIXED,
+                };
+                // SEC1 uncompressed point
+                let mut point = vec![0x04];
+                point.extend(decode_part(x)?);
+                point.extend(decode_part(y)?);
+                signature::UnparsedPublicKey::new(params, point).verify(message, signature)
+            }
+            JwkKey::Okp { x, .. } => {
+                signature::UnparsedPublicKey::new(&signature::ED25519, decode_part(x)?)
+                    .verify(message, signature)
+            }
+        }
+        .map_err(|_| JoseError::InvalidSignature)
+    }
+}
+
+fn decode_header(token: &str) -> Result<Header, JoseError> {
+    let header = token.split('.').next().ok_or(JoseError::Malformed)?;
+    serde_json::from_slice(&decode_part(header)?).map_err(|_| JoseError::Malformed)
+}
+
+// RFC 9449 §4.2: the token is signed by the key given in its own header
+pub(crate) fn verify_with_embedded_key<T: serde::de::DeserializeOwned>(
+    token: &str,
+) -> Result<(Header, T), JoseError> {
+    let header = decode_header(token)?;
+    let mut key = header.jwk.ok_or(JoseError::KeyNotFound)?;
+    key.kid = header.kid;
+    JwkSet { keys: vec![key] }.verify(token)
+}
+
+// RFC 7515 §7.1: encodes a compact JWS, the signature being computed over the encoded parts
+pub(crate) fn encode<T: serde::Serialize>(
+    header: &Header,
+    claims: &T,
+    sign: impl FnOnce(&[u8]) -> Result<Vec<u8>, JoseError>,
+) -> Result<String, JoseError> {
+    let header = serde_json::to_vec(header).map_err(|_| JoseError::Signing)?;
+    let claims = serde_json::to_vec(claims).map_err(|_| JoseError::Signing)?;
+    let message = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(header),
+        URL_SAFE_NO_PAD.encode(claims)
+    );
+    let signature = sign(message.as_bytes())?;
+    Ok(format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature)))
+}
+
+impl JwkSet {
+    // RFC 7515 §7.1: decodes a compact JWS, once its signature is verified with one of the keys
+    pub(crate) fn verify<T: serde::de::DeserializeOwned>(
+        &self,
+        token: &str,
+    ) -> Result<(Header, T), JoseError> {
+        let mut parts = token.split('.');
+        let (Some(header), Some(payload), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(JoseError::Malformed);
+        };
+        let header_value: Header =
+            serde_json::from_slice(&decode_part(header)?).map_err(|_| JoseError::Malformed)?;
+        let signature = decode_part(signature)?;
+        let message = &token[..(header.len() + 1 + payload.len())];
+
+        let mut candidates = self
+            .keys
+            .iter()
+            .filter(|key| header_value.kid.is_none() || key.kid == header_value.kid)
+            .filter(|key| key.accepts(header_value.alg))
+            .peekable();
+        if candidates.peek().is_none() {
+            return Err(JoseError::KeyNotFound);
+        }
+        if !candidates.any(|key| {
+            key.verify(header_value.alg, message.as_bytes(), &signature)
+                .is_ok()
+        }) {
+            return Err(JoseError::InvalidSignature);
+        }
+
+        let claims =
+            serde_json::from_slice(&decode_part(payload)?).map_err(|_| JoseError::Malformed)?;
+        Ok((header_value, claims))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    use super::*;
+
+    pub(crate) fn ed25519_key(kid: &str) -> (Ed25519KeyPair, Jwk) {
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let jwk = Jwk {
+            kid: Some(kid.into()),
+            alg: Some(Algorithm::EdDSA),
+            key: JwkKey::Okp {
+                crv: "Ed25519".into(),
+                x: URL_SAFE_NO_PAD.encode(pair.public_key().as_ref()),
+            },
+        };
+        (pair, jwk)
+    }
+
+    pub(crate) fn sign(pair: &Ed25519KeyPair, kid: &str, claims: &serde_json::Value) -> String {
+        sign_with_header(
+            pair,
+            &serde_json::json!({ "alg": "EdDSA", "kid": kid }),
+            claims,
+        )
+    }
+
+    pub(crate) fn sign_with_header(
+        pair: &Ed25519KeyPair,
+        header: &serde_json::Value,
+        claims: &serde_json::Value,
+    ) -> String {
+        let message = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = pair.sign(message.as_bytes());
+        format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature.as_ref()))
+    }
+
+    #[test]
+    fn should_verify_signature() {
+        let (pair, jwk) = ed25519_key("first");
+        let (_, other) = ed25519_key("second");
+        let claims = serde_json::json!({ "iss": "client" });
+        let token = sign(&pair, "first", &claims);
+
+        let keys = JwkSet {
+            keys: vec![other.clone(), jwk],
+        };
+        let (header, decoded) = keys.verify::<serde_json::Value>(&token).unwrap();
+        assert_eq!(header.alg, Algorithm::EdDSA);
+        assert_eq!(decoded, claims);
+
+        let keys = JwkSet { keys: vec![other] };
+        assert!(matches!(
+            keys.verify::<serde_json::Value>(&token),
+            Err(JoseError::KeyNotFound)
+        ));
+    }
+
+    #[test]
+    fn should_reject_tampered_token() {
+        let (pair, jwk) = ed25519_key("first");
+        let token = sign(&pair, "first", &serde_json::json!({ "scope": "profile" }));
+        let (header, _, signature) = {
+            let mut parts = token.split('.');
+            (
+                parts.next().unwrap(),
+                parts.next().unwrap(),
+                parts.next().unwrap(),
+            )
+        };
+        let payload = URL_SAFE_NO_PAD.encode(r#"{"scope":"admin"}"#);
+        let tampered = format!("{header}.{payload}.{signature}");
+
+        let keys = JwkSet { keys: vec![jwk] };
+        assert!(matches!(
+            keys.verify::<serde_json::Value>(&tampered),
+            Err(JoseError::InvalidSignature)
+        ));
+        assert!(matches!(
+            keys.verify::<serde_json::Value>("not-a-token"),
+            Err(JoseError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn should_compute_thumbprint() {
+        // RFC 7638 §3.1
+        let jwk: Jwk = serde_json::from_str(
+            r#"{"kty":"RSA","n":"0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw","e":"AQAB","alg":"RS256","kid":"2011-04-29"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            jwk.thumbprint(),
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+    }
+
+    #[test]
+    fn should_verify_with_embedded_key() {
+        let (pair, jwk) = ed25519_key("first");
+        let (other, _) = ed25519_key("second");
+        let header = serde_json::json!({ "alg": "EdDSA", "typ": "dpop+jwt", "jwk": jwk });
+        let claims = serde_json::json!({ "jti": "abc" });
+
+        let token = sign_with_header(&pair, &header, &claims);
+        let (decoded, _) = verify_with_embedded_key::<serde_json::Value>(&token).unwrap();
+        assert_eq!(decoded.jwk.unwrap().thumbprint(), jwk.thumbprint());
+
+        let token = sign_with_header(&other, &header, &claims);
+        assert!(matches!(
+            verify_with_embedded_key::<serde_json::Value>(&token),
+            Err(JoseError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn should_parse_key_set() {
+        let keys: JwkSet = serde_json::from_str(
+            r#"{"keys":[{"kty":"EC","kid":"a","crv":"P-256","x":"eA","y":"eQ"},{"kty":"oct","k":"c2VjcmV0"},{"kty":"RSA","n":"bg","e":"AQAB","alg":"PS256"},{"kty":"RSA","n":"bg","e":"AQAB","alg":"HS256"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(keys.keys.len(), 2);
+        assert!(keys.keys[0].accepts(Algorithm::ES256));
+        assert!(!keys.keys[0].accepts(Algorithm::ES384));
+        assert!(keys.keys[1].accepts(Algorithm::PS256));
+        assert!(!keys.keys[1].accepts(Algorithm::RS256));
+    }
+}
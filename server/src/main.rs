@@ -1,6 +1,7 @@
 mod app;
 mod entity;
 mod helper;
+mod jose;
 mod router;
 mod service;
 
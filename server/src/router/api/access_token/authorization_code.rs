@@ -0,0 +1,794 @@
+use super::{
+    grant_resource, AccessTokenSigner, Authentication, Issue, ResponseError, ResponsePayload,
+    ACCESS_TOKEN_TTL, OPENID_SCOPE,
+};
+
+#[derive(serde::Deserialize)]
+#[cfg_attr(test, derive(Debug, serde::Serialize))]
+pub(crate) struct RequestPayload {
+    pub code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_verifier: Option<String>,
+    pub redirect_uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+}
+
+// RFC 6749 §4.1.2: a code used twice revokes every token issued from it
+async fn revoke_reused_code(
+    mut tx: sqlx::Transaction<'_, sqlx::Sqlite>,
+    code: &str,
+) -> Result<ResponsePayload, ResponseError> {
+    let revoked = crate::entity::session::RevokeByAuthorizationCode::new(code)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    tracing::warn!(
+        message = "authorization code reused",
+        revoked_sessions = revoked
+    );
+    Err(ResponseError::CodeAlreadyUsed)
+}
+
+pub(super) async fn handle(
+    database: &crate::service::database::Pool,
+    client: &crate::entity::application::Entity,
+    dpop_jkt: Option<&str>,
+    signer: Option<AccessTokenSigner<'_>>,
+    payload: RequestPayload,
+) -> Result<ResponsePayload, ResponseError> {
+    let mut tx = database.as_ref().begin().await?;
+    let state = crate::entity::authorization::FindByCode::new(payload.code.as_str())
+        .execute(&mut *tx)
+        .await?
+        .ok_or(ResponseError::CodeNotFound)?;
+
+    if state.client_id != client.id {
+        tracing::warn!(message = "code used by another client", client_id = %client.id);
+        return Err(ResponseError::CodeClientMismatch);
+    }
+
+    if state.consent_pending {
+        tracing::warn!(message = "code used before the user approved the request", client_id = %client.id);
+        return Err(ResponseError::CodeNotFound);
+    }
+
+    if state.consumed_at.is_some() {
+        return revoke_reused_code(tx, &state.code).await;
+    }
+
+    // RFC 7636 §4.6: a verifier is expected only when a challenge was provided
+    match (
+        state.code_challenge.as_deref(),
+        state.code_challenge_method,
+        payload.code_verifier.as_deref(),
+    ) {
+        (Some(challenge), Some(method), Some(verifier)) => {
+            let hashed_verifier = method.hash(verifier);
+            if !hashed_verifier.eq(challenge) {
+                tracing::warn!(message = "invalid code verifier", expected = %challenge, hash = %hashed_verifier);
+                return Err(ResponseError::InvalidCodeVerifier);
+            }
+        }
+        (None, _, None) => {}
+        _ => {
+            tracing::warn!(message = "code verifier doesn't match the authorization", client_id = %client.id);
+            return Err(ResponseError::InvalidCodeVerifier);
+        }
+    }
+
+    // RFC 6749 §4.1.3: the redirect uri must be identical to the one used to get the code
+    if !state.redirect_uri.eq(payload.redirect_uri.as_str()) {
+        return Err(ResponseError::InvalidRedirectUri);
+    }
+
+    let audience = grant_resource(
+        client,
+        payload.resource.as_deref(),
+        state.resource.as_deref(),
+    )?;
+
+    // another request consumed the code in the meantime
+    if !crate::entity::authorization::Consume::new(&state.code)
+        .execute(&mut *tx)
+        .await?
+    {
+        return revoke_reused_code(tx, &state.code).await;
+    }
+
+    let refresh_family = crate::helper::generate_token(24);
+    let mut response = Issue {
+        client_id: state.client_id,
+        user_id: Some(state.user_id),
+        scope: state.scope.as_deref(),
+        authorization_code: Some(state.code.as_str()),
+        refresh_family: Some(refresh_family.as_str()),
+        dpop_jkt,
+        audience,
+        actor: None,
+        time_to_live: ACCESS_TOKEN_TTL,
+        signer,
+    }
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    if state
+        .scope
+        .iter()
+        .flat_map(|scope| scope.split_whitespace())
+        .any(|item| item == OPENID_SCOPE)
+    {
+        response.authentication = Some(Authentication {
+            user_id: state.user_id,
+            auth_time: state.authenticated_at,
+            nonce: state.nonce,
+        });
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::time::Duration;
+
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
+
+    use crate::entity::code_challenge::CodeChallengeMethod;
+    use crate::entity::response_type::ResponseType;
+    use crate::router::api::access_token::{
+        access_token_hash, GrantPayload, IdTokenClaims, RequestPayload, ResponsePayload,
+    };
+    use crate::router::api::prelude::basic_authorization;
+    use crate::router::api::prelude::ClientCredentials;
+    use crate::service::dataset::{
+        ALICE_ID, CLIENT_ID, CLIENT_SECRET, LOOPBACK_REDIRECT_URI, REDIRECT_URI, RESOURCE,
+    };
+
+    const SHORT_TTL: Duration = Duration::new(5, 0);
+
+    fn request_payload(client: ClientCredentials) -> RequestPayload {
+        RequestPayload {
+            client,
+            grant: GrantPayload::AuthorizationCode(super::RequestPayload {
+                code: "aaaaaaaaaaaaaaaaaaa".into(),
+                code_verifier: Some("code-challenge".into()),
+                redirect_uri: REDIRECT_URI.into(),
+                resource: None,
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_create_access_token_without_defined_type() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        crate::entity::authorization::Create {
+            code: "aaaaaaaaaaaaaaaaaaa",
+            client_id: CLIENT_ID,
+            user_id: ALICE_ID,
+            state: "state",
+            scope: None,
+            code_challenge: Some("Cuib-0-lo1-9KOlQ5wI4iPoPxUqwtHV3by9YggLlyKE"),
+            code_challenge_method: Some(CodeChallengeMethod::S256),
+            response_type: ResponseType::Code,
+            redirect_uri: REDIRECT_URI,
+            consent_pending: false,
+            resource: None,
+            nonce: None,
+            authenticated_at: chrono::Utc::now(),
+            time_to_live: SHORT_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+
+        let req = Request::builder()
+            .uri("/api/access-token")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .header("Content-Type", "application/json")
+            .method("POST")
+            .body(Body::from(
+                serde_json::to_vec(&request_payload(ClientCredentials::default())).unwrap(),
+            ))
+            .unwrap();
+        let res = app.handle(req).await;
+        let status = res.status();
+        assert_eq!(status, StatusCode::OK);
+
+        let ctype = res
+            .headers()
+            .get("Content-Type")
+            .and_then(|h| h.to_str().ok())
+            .unwrap();
+        assert_eq!(ctype, "application/x-www-form-urlencoded");
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: ResponsePayload = serde_urlencoded::from_bytes(&body).unwrap();
+        assert!(body.scope.is_none())
+    }
+
+    #[tokio::test]
+    async fn should_create_access_token_with_json_type() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        crate::entity::authorization::Create {
+            code: "aaaaaaaaaaaaaaaaaaa",
+            client_id: CLIENT_ID,
+            user_id: ALICE_ID,
+            state: "state",
+            scope: None,
+            code_challenge: Some("code-challenge"),
+            code_challenge_method: Some(CodeChallengeMethod::Plain),
+            response_type: ResponseType::Code,
+            redirect_uri: REDIRECT_URI,
+            consent_pending: false,
+            resource: None,
+            nonce: None,
+            authenticated_at: chrono::Utc::now(),
+            time_to_live: SHORT_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+
+        let req = Request::builder()
+            .uri("/api/access-token")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .method("POST")
+            .body(Body::from(
+                serde_json::to_vec(&request_payload(ClientCredentials::default())).unwrap(),
+            ))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let ctype = res
+            .headers()
+            .get("Content-Type")
+            .and_then(|h| h.to_str().ok())
+            .unwrap();
+        assert_eq!(ctype, "application/json");
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: ResponsePayload = serde_json::from_slice(&body).unwrap();
+        assert!(body.scope.is_none());
+        assert!(body.refresh_token.is_some());
+    }
+
+    #[tokio::test]
+    async fn should_create_access_token_with_form_type() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+
+        crate::entity::authorization::Create {
+            code: "aaaaaaaaaaaaaaaaaaa",
+            client_id: CLIENT_ID,
+            user_id: ALICE_ID,
+            state: "state",
+            scope: None,
+            code_challenge: Some("Cuib-0-lo1-9KOlQ5wI4iPoPxUqwtHV3by9YggLlyKE"),
+            code_challenge_method: Some(CodeChallengeMethod::S256),
+            response_type: ResponseType::Code,
+            redirect_uri: REDIRECT_URI,
+            consent_pending: false,
+            resource: None,
+            nonce: None,
+            authenticated_at: chrono::Utc::now(),
+            time_to_live: SHORT_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+
+        let req = Request::builder()
+            .uri("/api/access-token")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .header("Accept", "application/x-www-form-urlencoded")
+            .header("Content-Type", "application/json")
+            .method("POST")
+            .body(Body::from(
+                serde_json::to_vec(&request_payload(ClientCredentials::default())).unwrap(),
+            ))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let ctype = res
+            .headers()
+            .get("Content-Type")
+            .and_then(|h| h.to_str().ok())
+            .unwrap();
+        assert_eq!(ctype, "application/x-www-form-urlencoded");
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: ResponsePayload = serde_urlencoded::from_bytes(&body).unwrap();
+        assert!(body.scope.is_none())
+    }
+
+    #[tokio::test]
+    async fn should_revoke_sessions_when_code_is_reused() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        crate::entity::authorization::Create {
+            code: "aaaaaaaaaaaaaaaaaaa",
+            client_id: CLIENT_ID,
+            user_id: ALICE_ID,
+            state: "state",
+            scope: None,
+            code_challenge: Some("code-challenge"),
+            code_challenge_method: Some(CodeChallengeMethod::Plain),
+            response_type: ResponseType::Code,
+            redirect_uri: REDIRECT_URI,
+            consent_pending: false,
+            resource: None,
+            nonce: None,
+            authenticated_at: chrono::Utc::now(),
+            time_to_live: SHORT_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+
+        let payload = serde_json::to_vec(&request_payload(ClientCredentials::default())).unwrap();
+        let build_request = || {
+            Request::builder()
+                .uri("/api/access-token")
+                .header(
+                    "Authorization",
+                    basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+                )
+                .header("Accept", "application/json")
+                .header("Content-Type", "application/json")
+                .method("POST")
+                .body(Body::from(payload.clone()))
+                .unwrap()
+        };
+
+        let res = app.handle(build_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: ResponsePayload = serde_json::from_slice(&body).unwrap();
+
+        let res = app.handle(build_request()).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let err = res.into_body().collect().await.unwrap().to_bytes();
+        let err: serde_json::Value = serde_json::from_slice(&err).unwrap();
+        assert_eq!(err["error"], "invalid_grant");
+        assert_eq!(
+            err["error_description"],
+            "provided code has already been used"
+        );
+
+        let req = Request::builder()
+            .uri("/api/user-info")
+            .header("Authorization", format!("Bearer {}", body.access_token))
+            .method("GET")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    async fn create_authorization(app: &crate::app::Application) {
+        crate::entity::authorization::Create {
+            code: "aaaaaaaaaaaaaaaaaaa",
+            client_id: CLIENT_ID,
+            user_id: ALICE_ID,
+            state: "state",
+            scope: None,
+            code_challenge: Some("code-challenge"),
+            code_challenge_method: Some(CodeChallengeMethod::Plain),
+            response_type: ResponseType::Code,
+            redirect_uri: REDIRECT_URI,
+            consent_pending: false,
+            resource: None,
+            nonce: None,
+            authenticated_at: chrono::Utc::now(),
+            time_to_live: SHORT_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_authenticate_client_with_post_credentials() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        create_authorization(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/access-token")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .method("POST")
+            .body(Body::from(
+                serde_urlencoded::to_string(request_payload(ClientCredentials {
+                    client_id: Some(CLIENT_ID.to_string()),
+                    client_secret: Some(CLIENT_SECRET.into()),
+                }))
+                .unwrap(),
+            ))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_client_secret() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        create_authorization(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/access-token")
+            .header("Content-Type", "application/json")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), "wrong-secret"),
+            )
+            .method("POST")
+            .body(Body::from(
+                serde_json::to_vec(&request_payload(ClientCredentials::default())).unwrap(),
+            ))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert!(res.headers().contains_key("WWW-Authenticate"));
+    }
+
+    #[tokio::test]
+    async fn should_reject_missing_client_authentication() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        create_authorization(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/access-token")
+            .header("Content-Type", "application/json")
+            .method("POST")
+            .body(Body::from(
+                serde_json::to_vec(&request_payload(ClientCredentials {
+                    client_id: Some(CLIENT_ID.to_string()),
+                    client_secret: None,
+                }))
+                .unwrap(),
+            ))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn should_check_redirect_uri_used_for_authorization() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        let loopback = "http://127.0.0.1:51004/redirect";
+        crate::entity::authorization::Create {
+            code: "aaaaaaaaaaaaaaaaaaa",
+            client_id: CLIENT_ID,
+            user_id: ALICE_ID,
+            state: "state",
+            scope: None,
+            code_challenge: Some("code-challenge"),
+            code_challenge_method: Some(CodeChallengeMethod::Plain),
+            response_type: ResponseType::Code,
+            redirect_uri: loopback,
+            consent_pending: false,
+            resource: None,
+            nonce: None,
+            authenticated_at: chrono::Utc::now(),
+            time_to_live: SHORT_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+
+        let build_request = |redirect_uri: &str| {
+            Request::builder()
+                .uri("/api/access-token")
+                .header(
+                    "Authorization",
+                    basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+                )
+                .header("Content-Type", "application/json")
+                .method("POST")
+                .body(Body::from(
+                    serde_json::to_vec(&RequestPayload {
+                        client: ClientCredentials::default(),
+                        grant: GrantPayload::AuthorizationCode(super::RequestPayload {
+                            code: "aaaaaaaaaaaaaaaaaaa".into(),
+                            code_verifier: Some("code-challenge".into()),
+                            redirect_uri: redirect_uri.into(),
+                            resource: None,
+                        }),
+                    })
+                    .unwrap(),
+                ))
+                .unwrap()
+        };
+
+        // registered for the application, but not the one used to get the code
+        let res = app.handle(build_request(REDIRECT_URI)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res = app.handle(build_request(LOOPBACK_REDIRECT_URI)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let res = app.handle(build_request(loopback)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_reject_verifier_without_challenge() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        crate::entity::authorization::Create {
+            code: "aaaaaaaaaaaaaaaaaaa",
+            client_id: CLIENT_ID,
+            user_id: ALICE_ID,
+            state: "state",
+            scope: None,
+            code_challenge: None,
+            code_challenge_method: None,
+            response_type: ResponseType::Code,
+            redirect_uri: REDIRECT_URI,
+            consent_pending: false,
+            resource: None,
+            nonce: None,
+            authenticated_at: chrono::Utc::now(),
+            time_to_live: SHORT_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+
+        let build_request = |payload: &RequestPayload| {
+            Request::builder()
+                .uri("/api/access-token")
+                .header(
+                    "Authorization",
+                    basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+                )
+                .header("Content-Type", "application/json")
+                .method("POST")
+                .body(Body::from(serde_json::to_vec(payload).unwrap()))
+                .unwrap()
+        };
+
+        let mut payload = request_payload(ClientCredentials::default());
+        let res = app.handle(build_request(&payload)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        if let GrantPayload::AuthorizationCode(ref mut inner) = payload.grant {
+            inner.code_verifier = None;
+        }
+        let res = app.handle(build_request(&payload)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_restrict_audience_to_authorized_resource() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        crate::entity::authorization::Create {
+            code: "aaaaaaaaaaaaaaaaaaa",
+            client_id: CLIENT_ID,
+            user_id: ALICE_ID,
+            state: "state",
+            scope: None,
+            code_challenge: Some("code-challenge"),
+            code_challenge_method: Some(CodeChallengeMethod::Plain),
+            response_type: ResponseType::Code,
+            redirect_uri: REDIRECT_URI,
+            consent_pending: false,
+            resource: Some(RESOURCE),
+            nonce: None,
+            authenticated_at: chrono::Utc::now(),
+            time_to_live: SHORT_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+
+        let build_request = |payload: &RequestPayload| {
+            Request::builder()
+                .uri("/api/access-token")
+                .header(
+                    "Authorization",
+                    basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+                )
+                .header("Accept", "application/json")
+                .header("Content-Type", "application/json")
+                .method("POST")
+                .body(Body::from(serde_json::to_vec(payload).unwrap()))
+                .unwrap()
+        };
+
+        // the code was issued for another resource
+        let mut payload = request_payload(ClientCredentials::default());
+        if let GrantPayload::AuthorizationCode(ref mut inner) = payload.grant {
+            inner.resource = Some("http://other/api".into());
+        }
+        let res = app.handle(build_request(&payload)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_target");
+
+        let payload = request_payload(ClientCredentials::default());
+        let res = app.handle(build_request(&payload)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: ResponsePayload = serde_json::from_slice(&body).unwrap();
+        let session = crate::entity::session::FindByAccessToken::new(&body.access_token)
+            .execute(app.database())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.audience.as_deref(), Some(RESOURCE));
+    }
+
+    #[tokio::test]
+    async fn should_issue_id_token_for_openid_scope() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        let query = serde_urlencoded::to_string([
+            ("client_id", CLIENT_ID.to_string().as_str()),
+            ("redirect_uri", REDIRECT_URI),
+            ("state", "the-state"),
+            ("code_challenge", "code-challenge"),
+            ("code_challenge_method", "plain"),
+            ("response_type", "code"),
+            ("scope", "openid profile"),
+            ("nonce", "the-nonce"),
+            ("user", ALICE_ID.to_string().as_str()),
+        ])
+        .unwrap();
+        let req = Request::builder()
+            .uri(format!("/authorize/profiles/login?{query}"))
+            .method("GET")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let page = String::from_utf8(body.to_vec()).unwrap();
+        let index = page.find("code=").unwrap() + 5;
+        let code: String = page[index..]
+            .chars()
+            .take_while(char::is_ascii_alphanumeric)
+            .collect();
+
+        let mut payload = request_payload(ClientCredentials::default());
+        if let GrantPayload::AuthorizationCode(ref mut inner) = payload.grant {
+            inner.code = code;
+        }
+        let req = Request::builder()
+            .uri("/api/access-token")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .method("POST")
+            .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: ResponsePayload = serde_json::from_slice(&body).unwrap();
+        let id_token = body.id_token.unwrap();
+
+        let (header, claims): (_, IdTokenClaims) = app.keys().verify(&id_token).unwrap();
+        assert_eq!(header.typ.as_deref(), Some("JWT"));
+        assert_eq!(claims.iss, "http://localhost:8080");
+        assert_eq!(claims.sub, ALICE_ID.to_string());
+        assert_eq!(claims.aud, CLIENT_ID.to_string());
+        assert_eq!(claims.nonce.as_deref(), Some("the-nonce"));
+        assert!(claims.auth_time <= claims.iat);
+        assert!(claims.iat < claims.exp);
+        assert_eq!(
+            claims.at_hash,
+            Some(access_token_hash(header.alg, &body.access_token))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_give_authentication_time_in_id_token() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        // the user logged in before the code was issued, e.g. while consenting
+        let authenticated_at = chrono::Utc::now() - chrono::Duration::minutes(10);
+        crate::entity::authorization::Create {
+            code: "aaaaaaaaaaaaaaaaaaa",
+            client_id: CLIENT_ID,
+            user_id: ALICE_ID,
+            state: "state",
+            scope: Some("openid"),
+            code_challenge: Some("code-challenge"),
+            code_challenge_method: Some(CodeChallengeMethod::Plain),
+            response_type: ResponseType::Code,
+            redirect_uri: REDIRECT_URI,
+            consent_pending: false,
+            resource: None,
+            nonce: None,
+            authenticated_at,
+            time_to_live: SHORT_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+
+        let req = Request::builder()
+            .uri("/api/access-token")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .method("POST")
+            .body(Body::from(
+                serde_json::to_vec(&request_payload(ClientCredentials::default())).unwrap(),
+            ))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: ResponsePayload = serde_json::from_slice(&body).unwrap();
+
+        let (_, claims): (_, IdTokenClaims) = app.keys().verify(&body.id_token.unwrap()).unwrap();
+        assert_eq!(claims.auth_time, authenticated_at.timestamp());
+    }
+
+    #[tokio::test]
+    async fn should_not_issue_id_token_without_openid_scope() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        create_authorization(&app).await;
+
+        let req = Request::builder()
+            .uri("/api/access-token")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .method("POST")
+            .body(Body::from(
+                serde_json::to_vec(&request_payload(ClientCredentials::default())).unwrap(),
+            ))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(body.get("id_token").is_none());
+    }
+}
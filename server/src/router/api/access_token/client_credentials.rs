@@ -0,0 +1,254 @@
+use super::{
+    grant_resource, AccessTokenSigner, Issue, ResponseError, ResponsePayload, ACCESS_TOKEN_TTL,
+};
+use crate::entity::application::ApplicationKind;
+
+#[derive(serde::Deserialize)]
+#[cfg_attr(test, derive(Debug, serde::Serialize))]
+pub(crate) struct RequestPayload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+}
+
+pub(super) async fn handle(
+    database: &crate::service::database::Pool,
+    client: &crate::entity::application::Entity,
+    dpop_jkt: Option<&str>,
+    signer: Option<AccessTokenSigner<'_>>,
+    payload: RequestPayload,
+) -> Result<ResponsePayload, ResponseError> {
+    if client.kind != ApplicationKind::Confidential {
+        tracing::warn!(message = "client credentials requested by a public client", client_id = %client.id);
+        return Err(ResponseError::UnauthorizedClient);
+    }
+
+    let scope = client
+        .grant_scope(payload.scope.as_deref())
+        .map_err(|_| ResponseError::InvalidScope)?;
+    let audience = grant_resource(client, payload.resource.as_deref(), None)?;
+
+    let mut tx = database.as_ref().begin().await?;
+    // RFC 6749 §4.4.3: a refresh token should not be included
+    let response = Issue {
+        client_id: client.id,
+        user_id: None,
+        scope: scope.as_deref(),
+        authorization_code: None,
+        refresh_family: None,
+        dpop_jkt,
+        audience,
+        actor: None,
+        time_to_live: ACCESS_TOKEN_TTL,
+        signer,
+    }
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::collections::HashSet;
+
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
+    use uuid::Uuid;
+
+    use crate::router::api::access_token::{GrantPayload, RequestPayload, ResponsePayload};
+    use crate::router::api::prelude::{basic_authorization, ClientCredentials};
+    use crate::service::dataset::{CLIENT_ID, CLIENT_SECRET, REDIRECT_URI, RESOURCE};
+
+    fn request(client: ClientCredentials, authorization: Option<String>) -> Request<Body> {
+        request_with_scope(client, authorization, "service")
+    }
+
+    fn request_with_scope(
+        client: ClientCredentials,
+        authorization: Option<String>,
+        scope: &str,
+    ) -> Request<Body> {
+        let builder = Request::builder()
+            .uri("/api/access-token")
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .method("POST");
+        let builder = match authorization {
+            Some(value) => builder.header("Authorization", value),
+            None => builder,
+        };
+        builder
+            .body(Body::from(
+                serde_urlencoded::to_string(RequestPayload {
+                    client,
+                    grant: GrantPayload::ClientCredentials(super::RequestPayload {
+                        scope: Some(scope.into()),
+                        resource: None,
+                    }),
+                })
+                .unwrap(),
+            ))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_create_session_without_user() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+
+        let res = app
+            .handle(request(
+                ClientCredentials::default(),
+                Some(basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET)),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: ResponsePayload = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.scope.as_deref(), Some("service"));
+        assert!(body.refresh_token.is_none());
+
+        let session = crate::entity::session::FindByAccessToken::new(&body.access_token)
+            .execute(app.database())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.client_id, CLIENT_ID);
+        assert!(session.user_id.is_none());
+
+        let req = Request::builder()
+            .uri("/api/user-info")
+            .header("Authorization", format!("Bearer {}", body.access_token))
+            .method("GET")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn should_reject_public_client() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        let public_id = Uuid::new_v4();
+        crate::entity::application::Upsert::new(public_id, &HashSet::new(), &[REDIRECT_URI.into()])
+            .execute(app.database())
+            .await
+            .unwrap();
+
+        let res = app
+            .handle(request(
+                ClientCredentials {
+                    client_id: Some(public_id.to_string()),
+                    client_secret: None,
+                },
+                None,
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn should_reject_unknown_scope() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+
+        let res = app
+            .handle(request_with_scope(
+                ClientCredentials::default(),
+                Some(basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET)),
+                "service admin",
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_scope");
+    }
+
+    #[tokio::test]
+    async fn should_set_audience_from_resource() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        let build_request = |resource: &str| {
+            Request::builder()
+                .uri("/api/access-token")
+                .header("Accept", "application/json")
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .header(
+                    "Authorization",
+                    basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+                )
+                .method("POST")
+                .body(Body::from(
+                    serde_urlencoded::to_string([
+                        ("grant_type", "client_credentials"),
+                        ("resource", resource),
+                    ])
+                    .unwrap(),
+                ))
+                .unwrap()
+        };
+
+        let res = app.handle(build_request(RESOURCE)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: ResponsePayload = serde_json::from_slice(&body).unwrap();
+
+        // the audience is exposed to the resource servers
+        let req = Request::builder()
+            .uri("/api/introspect")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .method("POST")
+            .body(Body::from(format!("token={}", body.access_token)))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["aud"], RESOURCE);
+
+        let res = app.handle(build_request("http://other/api")).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_target");
+
+        // a repeated resource is refused instead of failing to decode
+        let req = Request::builder()
+            .uri("/api/access-token")
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .method("POST")
+            .body(Body::from(
+                serde_urlencoded::to_string([
+                    ("grant_type", "client_credentials"),
+                    ("resource", RESOURCE),
+                    ("resource", RESOURCE),
+                ])
+                .unwrap(),
+            ))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_target");
+    }
+}
@@ -0,0 +1,251 @@
+use super::{
+    grant_resource, AccessTokenSigner, Issue, ResponseError, ResponsePayload, ACCESS_TOKEN_TTL,
+};
+
+// RFC 8628 §3.5: the interval is increased by 5 seconds on every slow_down
+const SLOW_DOWN_INCREMENT: u32 = 5;
+
+#[derive(serde::Deserialize)]
+#[cfg_attr(test, derive(Debug, serde::Serialize))]
+pub(crate) struct RequestPayload {
+    pub device_code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+}
+
+pub(super) async fn handle(
+    database: &crate::service::database::Pool,
+    client: &crate::entity::application::Entity,
+    dpop_jkt: Option<&str>,
+    signer: Option<AccessTokenSigner<'_>>,
+    payload: RequestPayload,
+) -> Result<ResponsePayload, ResponseError> {
+    let mut tx = database.as_ref().begin().await?;
+    let state =
+        crate::entity::device_authorization::FindByDeviceCode::new(payload.device_code.as_str())
+            .execute(&mut *tx)
+            .await?
+            .ok_or(ResponseError::DeviceCodeNotFound)?;
+
+    if state.client_id != client.id {
+        tracing::warn!(message = "device code used by another client", client_id = %client.id);
+        return Err(ResponseError::DeviceCodeNotFound);
+    }
+    if state.consumed_at.is_some() {
+        return Err(ResponseError::DeviceCodeNotFound);
+    }
+    if state.denied_at.is_some() {
+        return Err(ResponseError::AccessDenied);
+    }
+    let audience = grant_resource(client, payload.resource.as_deref(), None)?;
+
+    let now = chrono::Utc::now();
+    if state.valid_until <= now {
+        return Err(ResponseError::ExpiredToken);
+    }
+
+    let too_fast = state.last_polled_at.is_some_and(|polled_at| {
+        now.signed_duration_since(polled_at).num_seconds() < i64::from(state.interval)
+    });
+    let interval = if too_fast {
+        state.interval + SLOW_DOWN_INCREMENT
+    } else {
+        state.interval
+    };
+    crate::entity::device_authorization::Poll::new(&state.device_code, interval)
+        .execute(&mut *tx)
+        .await?;
+
+    if too_fast {
+        tx.commit().await?;
+        return Err(ResponseError::SlowDown);
+    }
+
+    let Some(user_id) = state.user_id else {
+        tx.commit().await?;
+        return Err(ResponseError::AuthorizationPending);
+    };
+
+    if !crate::entity::device_authorization::Consume::new(&state.device_code)
+        .execute(&mut *tx)
+        .await?
+    {
+        return Err(ResponseError::DeviceCodeNotFound);
+    }
+
+    let family = crate::helper::generate_token(24);
+    let response = Issue {
+        client_id: state.client_id,
+        user_id: Some(user_id),
+        scope: state.scope.as_deref(),
+        authorization_code: None,
+        refresh_family: Some(family.as_str()),
+        dpop_jkt,
+        audience,
+        actor: None,
+        time_to_live: ACCESS_TOKEN_TTL,
+        signer,
+    }
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
+
+    use crate::router::api::access_token::{GrantPayload, RequestPayload, ResponsePayload};
+    use crate::router::api::prelude::{basic_authorization, ClientCredentials};
+    use crate::service::dataset::{ALICE_ID, CLIENT_ID, CLIENT_SECRET};
+
+    fn token_request(device_code: &str) -> Request<Body> {
+        Request::builder()
+            .uri("/api/access-token")
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .method("POST")
+            .body(Body::from(
+                serde_urlencoded::to_string(RequestPayload {
+                    client: ClientCredentials::default(),
+                    grant: GrantPayload::DeviceCode(super::RequestPayload {
+                        device_code: device_code.to_string(),
+                        resource: None,
+                    }),
+                })
+                .unwrap(),
+            ))
+            .unwrap()
+    }
+
+    async fn reset_poll(app: &crate::app::Application, device_code: &str) {
+        sqlx::query(
+            "update device_authorizations set last_polled_at = null where device_code = $1",
+        )
+        .bind(device_code)
+        .execute(app.database())
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_poll_until_approved() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+
+        let req = Request::builder()
+            .uri("/api/device-authorization")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .method("POST")
+            .body(Body::from("scope=profile"))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let device_code = body["device_code"].as_str().unwrap();
+        let user_code = crate::helper::normalize_user_code(body["user_code"].as_str().unwrap());
+
+        // not approved yet
+        let res = app.handle(token_request(device_code)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let err = res.into_body().collect().await.unwrap().to_bytes();
+        let err: serde_json::Value = serde_json::from_slice(&err).unwrap();
+        assert_eq!(err["error"], "authorization_pending");
+
+        // polling too fast
+        let res = app.handle(token_request(device_code)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let err = res.into_body().collect().await.unwrap().to_bytes();
+        let err: serde_json::Value = serde_json::from_slice(&err).unwrap();
+        assert_eq!(err["error"], "slow_down");
+
+        assert!(
+            crate::entity::device_authorization::Approve::new(&user_code, ALICE_ID)
+                .execute(app.database())
+                .await
+                .unwrap()
+        );
+
+        reset_poll(&app, device_code).await;
+        let res = app.handle(token_request(device_code)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let token = res.into_body().collect().await.unwrap().to_bytes();
+        let token: ResponsePayload = serde_json::from_slice(&token).unwrap();
+        assert_eq!(token.scope.as_deref(), Some("profile"));
+        assert!(token.refresh_token.is_some());
+
+        let session = crate::entity::session::FindByAccessToken::new(&token.access_token)
+            .execute(app.database())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.user_id, Some(ALICE_ID));
+
+        // the device code can only be exchanged once
+        reset_poll(&app, device_code).await;
+        let res = app.handle(token_request(device_code)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn should_report_denied_request() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+
+        let req = Request::builder()
+            .uri("/api/device-authorization")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .method("POST")
+            .body(Body::from("scope=profile"))
+            .unwrap();
+        let res = app.handle(req).await;
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let device_code = body["device_code"].as_str().unwrap();
+        let user_code = body["user_code"].as_str().unwrap();
+
+        let req = Request::builder()
+            .uri(format!("/authorize/cancel?user_code={user_code}"))
+            .method("GET")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let page = res.into_body().collect().await.unwrap().to_bytes();
+        assert!(String::from_utf8_lossy(&page).contains("Device denied"));
+
+        let res = app.handle(token_request(device_code)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let err = res.into_body().collect().await.unwrap().to_bytes();
+        let err: serde_json::Value = serde_json::from_slice(&err).unwrap();
+        assert_eq!(err["error"], "access_denied");
+
+        // the denied request can't be approved anymore
+        let user_code = crate::helper::normalize_user_code(user_code);
+        assert!(
+            !crate::entity::device_authorization::Approve::new(&user_code, ALICE_ID)
+                .execute(app.database())
+                .await
+                .unwrap()
+        );
+    }
+}
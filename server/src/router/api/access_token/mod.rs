@@ -0,0 +1,1025 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::time::Duration;
+
+use axum::extract::rejection::{JsonRejection, RawFormRejection};
+use axum::extract::RawForm;
+use axum::http::header::{ACCEPT, CONTENT_TYPE};
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::{Extension, Form, Json};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use sha2::Digest;
+use uuid::Uuid;
+
+use super::dpop::{DPoPError, DPoPHeader};
+use super::prelude::{
+    authenticate_client, ClientAuthenticationError, ClientAuthorization, ClientCredentials,
+};
+use crate::entity::session::Actor;
+use crate::jose::Algorithm;
+use crate::service::base_url::BaseUrl;
+use crate::service::keys::Keys;
+
+mod authorization_code;
+mod client_credentials;
+mod device_code;
+mod password;
+mod refresh_token;
+mod token_exchange;
+
+// 1 day
+pub(crate) const ACCESS_TOKEN_TTL: Duration = Duration::new(60 * 60 * 24, 0);
+// 30 days
+const REFRESH_TOKEN_TTL: Duration = Duration::new(60 * 60 * 24 * 30, 0);
+// RFC 9068 §2.1
+const JWT_ACCESS_TOKEN_TYPE: &str = "at+jwt";
+const ID_TOKEN_TYPE: &str = "JWT";
+// 1 hour
+const ID_TOKEN_TTL: Duration = Duration::new(60 * 60, 0);
+// OpenID Connect Core §3.1.2.1
+const OPENID_SCOPE: &str = "openid";
+
+// RFC 6749 §6: the requested scope must not include any scope not originally granted
+fn is_subset(requested: &str, granted: Option<&str>) -> bool {
+    let granted: HashSet<&str> = granted.unwrap_or_default().split_whitespace().collect();
+    requested
+        .split_whitespace()
+        .all(|item| granted.contains(item))
+}
+
+// RFC 8707 §2.2: once granted, the token can only target the same resource
+fn grant_resource<'a>(
+    client: &crate::entity::application::Entity,
+    requested: Option<&'a str>,
+    granted: Option<&'a str>,
+) -> Result<Option<&'a str>, ResponseError> {
+    match (requested, granted) {
+        (Some(requested), Some(granted)) if requested != granted => {
+            Err(ResponseError::InvalidTarget)
+        }
+        (_, Some(granted)) => Ok(Some(granted)),
+        (requested, None) => client.grant_resource(requested).map_err(|err| {
+            tracing::debug!(message = "invalid resource", source = %err);
+            ResponseError::InvalidTarget
+        }),
+    }
+}
+
+pub(crate) struct AnyContentType<T>(pub T);
+
+pub(crate) enum AnyContentTypeRejection {
+    ContentTypeHeaderMissing,
+    ContentTypeHeaderInvalid,
+    ContentTypeNotSupported,
+    JsonRejection(JsonRejection),
+    FormRejection(RawFormRejection),
+    FormDecoding(serde_urlencoded::de::Error),
+    RepeatedResource,
+}
+
+impl AnyContentTypeRejection {
+    fn status_and_description(&self) -> (StatusCode, &'static str) {
+        match self {
+            Self::ContentTypeHeaderMissing => {
+                (StatusCode::BAD_REQUEST, "no 'Content-Type' header provided")
+            }
+            Self::ContentTypeHeaderInvalid => (
+                StatusCode::BAD_REQUEST,
+                "invalid 'Content-Type' header provided",
+            ),
+            Self::ContentTypeNotSupported => (
+                StatusCode::BAD_REQUEST,
+                "provided 'Content-Type' not supported",
+            ),
+            Self::JsonRejection(err) => {
+                let cause = err.source();
+                tracing::debug!(message = "failed decoding json payload", cause = cause);
+                (StatusCode::BAD_REQUEST, "unable to decode json payload")
+            }
+            Self::FormRejection(err) => {
+                let cause = err.source();
+                tracing::debug!(message = "failed decoding form payload", cause = cause);
+                (StatusCode::BAD_REQUEST, "unable to decode form payload")
+            }
+            Self::FormDecoding(err) => {
+                tracing::debug!(message = "failed decoding form payload", cause = %err);
+                (StatusCode::BAD_REQUEST, "unable to decode form payload")
+            }
+            Self::RepeatedResource => (
+                StatusCode::BAD_REQUEST,
+                "only one resource can be requested",
+            ),
+        }
+    }
+}
+
+impl IntoResponse for AnyContentTypeRejection {
+    fn into_response(self) -> axum::response::Response {
+        let code = match self {
+            Self::RepeatedResource => "invalid_target",
+            _ => "invalid_request",
+        };
+        let (status, description) = self.status_and_description();
+        super::error::Error::new(status, code)
+            .with_description(description)
+            .into_response()
+    }
+}
+
+#[axum::async_trait]
+impl<T, S> axum::extract::FromRequest<S> for AnyContentType<T>
+where
+    T: serde::de::DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AnyContentTypeRejection;
+
+    async fn from_request(req: axum::extract::Request, state: &S) -> Result<Self, Self::Rejection> {
+        let content_type = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .ok_or(AnyContentTypeRejection::ContentTypeHeaderMissing)?;
+        let content_type = content_type
+            .to_str()
+            .map_err(|_| AnyContentTypeRejection::ContentTypeHeaderInvalid)?;
+        if content_type.starts_with("application/json") {
+            Json::from_request(req, state)
+                .await
+                .map(|Json(inner)| AnyContentType(inner))
+                .map_err(AnyContentTypeRejection::JsonRejection)
+        } else if content_type.starts_with("application/x-www-form-urlencoded") {
+            let RawForm(body) = RawForm::from_request(req, state)
+                .await
+                .map_err(AnyContentTypeRejection::FormRejection)?;
+            if crate::helper::has_repeated_resource(&body) {
+                return Err(AnyContentTypeRejection::RepeatedResource);
+            }
+            serde_urlencoded::from_bytes(&body)
+                .map(AnyContentType)
+                .map_err(AnyContentTypeRejection::FormDecoding)
+        } else {
+            Err(AnyContentTypeRejection::ContentTypeNotSupported)
+        }
+    }
+}
+
+pub enum ResponseError {
+    UnsupportedGrantType,
+    UnauthorizedClient,
+    ClientAuthentication(ClientAuthenticationError),
+    DPoP(DPoPError),
+    DPoPKeyMismatch,
+    CodeNotFound,
+    CodeAlreadyUsed,
+    CodeClientMismatch,
+    InvalidCodeVerifier,
+    InvalidRedirectUri,
+    RefreshTokenNotFound,
+    RefreshTokenAlreadyUsed,
+    RefreshTokenClientMismatch,
+    UnsupportedTokenType,
+    InvalidSubjectToken,
+    InvalidActorToken,
+    TokenKeyMismatch,
+    InvalidScope,
+    InvalidTarget,
+    InvalidCredentials,
+    DeviceCodeNotFound,
+    AuthorizationPending,
+    AccessDenied,
+    SlowDown,
+    ExpiredToken,
+    Signing,
+    Database,
+}
+
+impl From<sqlx::Error> for ResponseError {
+    fn from(value: sqlx::Error) -> Self {
+        tracing::error!(message = "database interaction failed", error = %value);
+        Self::Database
+    }
+}
+
+impl From<ClientAuthenticationError> for ResponseError {
+    fn from(value: ClientAuthenticationError) -> Self {
+        Self::ClientAuthentication(value)
+    }
+}
+
+impl From<DPoPError> for ResponseError {
+    fn from(value: DPoPError) -> Self {
+        Self::DPoP(value)
+    }
+}
+
+impl IntoResponse for ResponseError {
+    fn into_response(self) -> axum::response::Response {
+        // RFC 6749 §5.2
+        let (code, description) = match self {
+            Self::ClientAuthentication(inner) => return inner.into_response(),
+            Self::DPoP(inner) => return inner.token_error().into_response(),
+            Self::Signing | Self::Database => {
+                return super::error::Error::internal().into_response()
+            }
+            Self::UnsupportedGrantType => ("unsupported_grant_type", "grant type not supported"),
+            Self::UnauthorizedClient => (
+                "unauthorized_client",
+                "client not allowed to use this grant type",
+            ),
+            Self::CodeNotFound => ("invalid_grant", "provided code doesn't exist"),
+            Self::CodeAlreadyUsed => ("invalid_grant", "provided code has already been used"),
+            Self::CodeClientMismatch => (
+                "invalid_grant",
+                "provided code was issued to another client",
+            ),
+            Self::InvalidRedirectUri => ("invalid_grant", "invalid redirect uri"),
+            Self::InvalidCodeVerifier => ("invalid_grant", "invalid code verifier"),
+            Self::RefreshTokenNotFound => ("invalid_grant", "provided refresh token doesn't exist"),
+            Self::RefreshTokenAlreadyUsed => (
+                "invalid_grant",
+                "provided refresh token has already been used",
+            ),
+            Self::RefreshTokenClientMismatch => (
+                "invalid_grant",
+                "provided refresh token was issued to another client",
+            ),
+            Self::DPoPKeyMismatch => (
+                "invalid_grant",
+                "provided refresh token is bound to another key",
+            ),
+            // RFC 8693 §2.2.2
+            Self::UnsupportedTokenType => ("invalid_request", "token type not supported"),
+            Self::InvalidSubjectToken => (
+                "invalid_request",
+                "provided subject token is invalid or expired",
+            ),
+            Self::InvalidActorToken => (
+                "invalid_request",
+                "provided actor token is invalid or expired",
+            ),
+            Self::TokenKeyMismatch => ("invalid_request", "provided token is bound to another key"),
+            Self::InvalidScope => ("invalid_scope", "requested scope is not allowed"),
+            // RFC 8707 §2
+            Self::InvalidTarget => ("invalid_target", "requested resource is not allowed"),
+            Self::InvalidCredentials => ("invalid_grant", "invalid resource owner credentials"),
+            Self::DeviceCodeNotFound => ("invalid_grant", "provided device code doesn't exist"),
+            // RFC 8628 §3.5
+            Self::AuthorizationPending => (
+                "authorization_pending",
+                "the user hasn't completed the authorization yet",
+            ),
+            Self::AccessDenied => ("access_denied", "the user denied the authorization"),
+            Self::SlowDown => ("slow_down", "polling too frequently"),
+            Self::ExpiredToken => ("expired_token", "provided device code has expired"),
+        };
+        super::error::Error::bad_request(code)
+            .with_description(description)
+            .into_response()
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[cfg_attr(test, derive(Debug, serde::Serialize))]
+#[serde(tag = "grant_type", rename_all = "snake_case")]
+pub(crate) enum GrantPayload {
+    AuthorizationCode(authorization_code::RequestPayload),
+    RefreshToken(refresh_token::RequestPayload),
+    ClientCredentials(client_credentials::RequestPayload),
+    Password(password::RequestPayload),
+    #[serde(rename = "urn:ietf:params:oauth:grant-type:device_code")]
+    DeviceCode(device_code::RequestPayload),
+    #[serde(rename = "urn:ietf:params:oauth:grant-type:token-exchange")]
+    TokenExchange(token_exchange::RequestPayload),
+    #[serde(other)]
+    Unsupported,
+}
+
+impl GrantPayload {
+    // the identifier of the grant type, as declared by the registered applications
+    fn grant_type(&self) -> Option<&'static str> {
+        match self {
+            Self::AuthorizationCode(_) => Some("authorization_code"),
+            Self::RefreshToken(_) => Some("refresh_token"),
+            Self::ClientCredentials(_) => Some("client_credentials"),
+            Self::Password(_) => Some("password"),
+            Self::DeviceCode(_) => Some("urn:ietf:params:oauth:grant-type:device_code"),
+            Self::TokenExchange(_) => Some("urn:ietf:params:oauth:grant-type:token-exchange"),
+            Self::Unsupported => None,
+        }
+    }
+}
+
+// RFC 8414 §2: the grant types handled above, the password grant being enabled per application
+pub(crate) fn supported_grant_types(
+    allow_password: bool,
+    allow_token_exchange: bool,
+) -> Vec<&'static str> {
+    let mut result = vec!["authorization_code", "refresh_token", "client_credentials"];
+    if allow_password {
+        result.push("password");
+    }
+    result.push("urn:ietf:params:oauth:grant-type:device_code");
+    if allow_token_exchange {
+        result.push("urn:ietf:params:oauth:grant-type:token-exchange");
+    }
+    result
+}
+
+#[derive(serde::Deserialize)]
+#[cfg_attr(test, derive(Debug, serde::Serialize))]
+pub(crate) struct RequestPayload {
+    #[serde(flatten)]
+    pub client: ClientCredentials,
+    #[serde(flatten)]
+    pub grant: GrantPayload,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) enum AcceptHeader {
+    Json,
+    #[default]
+    Form,
+}
+
+#[axum::async_trait]
+impl<S> axum::extract::FromRequestParts<S> for AcceptHeader
+where
+    S: Send + Sync,
+{
+    type Rejection = axum::response::Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts
+            .headers
+            .get(ACCEPT)
+            .and_then(|value| value.to_str().ok())
+        {
+            Some("application/json") => Ok(AcceptHeader::Json),
+            Some("application/x-www-form-urlencoded") | None => Ok(AcceptHeader::Form),
+            Some(other) => {
+                tracing::warn!("received a request for accept header of type {other}");
+                Err(
+                    super::error::Error::new(StatusCode::NOT_ACCEPTABLE, "invalid_request")
+                        .with_description("`Accept` header is requesting an incompatible type")
+                        .into_response(),
+                )
+            }
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+#[cfg_attr(test, derive(serde::Deserialize))]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TokenType {
+    Bearer,
+    // RFC 9449 §5
+    #[serde(rename = "DPoP")]
+    DPoP,
+}
+
+// RFC 8693 §3: only access tokens can be exchanged or issued
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub(crate) enum TokenTypeIdentifier {
+    #[serde(rename = "urn:ietf:params:oauth:token-type:access_token")]
+    AccessToken,
+    #[serde(other)]
+    Unsupported,
+}
+
+// RFC 9449 §6: the thumbprint of the key the token is bound to
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct Confirmation {
+    pub jkt: String,
+}
+
+// RFC 9068 §2.2
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct AccessTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: i64,
+    pub iat: i64,
+    pub jti: String,
+    pub client_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cnf: Option<Confirmation>,
+}
+
+impl AccessTokenClaims {
+    fn new(base_url: &BaseUrl, jti: String, session: crate::entity::session::Entity) -> Self {
+        Self {
+            iss: base_url.as_ref().to_string(),
+            // without user, the subject is the client itself
+            sub: session.user_id.unwrap_or(session.client_id).to_string(),
+            // without requested resource, the token targets the server own apis
+            aud: session
+                .audience
+                .unwrap_or_else(|| base_url.as_ref().to_string()),
+            exp: session.valid_until.timestamp(),
+            iat: session.created_at.timestamp(),
+            jti,
+            client_id: session.client_id,
+            scope: session.scope,
+            act: session.actor,
+            cnf: session.dpop_jkt.map(|jkt| Confirmation { jkt }),
+        }
+    }
+}
+
+// The user authentication an id token is issued for
+struct Authentication {
+    user_id: Uuid,
+    auth_time: chrono::DateTime<chrono::Utc>,
+    nonce: Option<String>,
+}
+
+// OpenID Connect Core §3.1.3.6: the left-most half of the hash of the access token,
+// using the hash function of the signing algorithm
+fn access_token_hash(alg: Algorithm, access_token: &str) -> String {
+    let hash = match alg {
+        Algorithm::RS256 | Algorithm::PS256 | Algorithm::ES256 => {
+            sha2::Sha256::digest(access_token.as_bytes()).to_vec()
+        }
+        Algorithm::ES384 => sha2::Sha384::digest(access_token.as_bytes()).to_vec(),
+        // Ed25519 is built on SHA-512
+        Algorithm::EdDSA => sha2::Sha512::digest(access_token.as_bytes()).to_vec(),
+    };
+    URL_SAFE_NO_PAD.encode(&hash[..hash.len() / 2])
+}
+
+// OpenID Connect Core §2
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: i64,
+    pub iat: i64,
+    pub auth_time: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at_hash: Option<String>,
+}
+
+impl IdTokenClaims {
+    fn new(
+        base_url: &BaseUrl,
+        client_id: Uuid,
+        authentication: Authentication,
+        at_hash: Option<String>,
+    ) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            iss: base_url.as_ref().to_string(),
+            sub: authentication.user_id.to_string(),
+            aud: client_id.to_string(),
+            exp: (now + ID_TOKEN_TTL).timestamp(),
+            iat: now.timestamp(),
+            auth_time: authentication.auth_time.timestamp(),
+            nonce: authentication.nonce,
+            at_hash,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct AccessTokenIdentifier {
+    jti: String,
+}
+
+// RFC 9068 §4: the sessions of the JWT access tokens are recorded by their identifier,
+// which is only trusted once the signature is verified
+pub(crate) enum SessionToken<'t> {
+    Opaque(&'t str),
+    Jwt(String),
+}
+
+impl SessionToken<'_> {
+    pub fn find(&self) -> crate::entity::session::FindByAccessToken<'_> {
+        match self {
+            Self::Opaque(token) => crate::entity::session::FindByAccessToken::new(token),
+            Self::Jwt(jti) => crate::entity::session::FindByAccessToken::jwt(jti),
+        }
+    }
+
+    pub fn revoke(&self, client_id: Uuid) -> crate::entity::session::RevokeByAccessToken<'_> {
+        match self {
+            Self::Opaque(token) => {
+                crate::entity::session::RevokeByAccessToken::new(token, client_id)
+            }
+            Self::Jwt(jti) => crate::entity::session::RevokeByAccessToken::jwt(jti, client_id),
+        }
+    }
+}
+
+pub(crate) fn session_token<'t>(keys: &Keys, token: &'t str) -> SessionToken<'t> {
+    match keys.verify::<AccessTokenIdentifier>(token) {
+        Ok((header, claims)) if header.typ.as_deref() == Some(JWT_ACCESS_TOKEN_TYPE) => {
+            SessionToken::Jwt(claims.jti)
+        }
+        _ => SessionToken::Opaque(token),
+    }
+}
+
+#[derive(serde::Serialize)]
+#[cfg_attr(test, derive(serde::Deserialize))]
+pub(crate) struct ResponsePayload {
+    #[serde(skip)]
+    accept: AcceptHeader,
+    #[serde(skip)]
+    authentication: Option<Authentication>,
+    access_token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issued_token_type: Option<TokenTypeIdentifier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    token_type: TokenType,
+    expires_in: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id_token: Option<String>,
+}
+
+impl IntoResponse for ResponsePayload {
+    fn into_response(self) -> axum::response::Response {
+        match self.accept {
+            AcceptHeader::Json => Json(self).into_response(),
+            AcceptHeader::Form => Form(self).into_response(),
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub(crate) struct SessionState {
+    pub client_id: String,
+    pub user: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+// RFC 9068: the access tokens of the clients using JWT are signed before the session is committed
+#[derive(Clone, Copy)]
+struct AccessTokenSigner<'a> {
+    keys: &'a Keys,
+    base_url: &'a BaseUrl,
+}
+
+impl AccessTokenSigner<'_> {
+    fn sign(
+        &self,
+        jti: String,
+        session: crate::entity::session::Entity,
+    ) -> Result<String, ResponseError> {
+        self.keys
+            .sign(
+                JWT_ACCESS_TOKEN_TYPE,
+                &AccessTokenClaims::new(self.base_url, jti, session),
+            )
+            .map_err(|err| {
+                tracing::error!(message = "unable to sign access token", source = %err);
+                ResponseError::Signing
+            })
+    }
+}
+
+struct Issue<'a> {
+    client_id: Uuid,
+    user_id: Option<Uuid>,
+    scope: Option<&'a str>,
+    authorization_code: Option<&'a str>,
+    refresh_family: Option<&'a str>,
+    dpop_jkt: Option<&'a str>,
+    audience: Option<&'a str>,
+    actor: Option<&'a crate::entity::session::Actor>,
+    time_to_live: Duration,
+    signer: Option<AccessTokenSigner<'a>>,
+}
+
+impl Issue<'_> {
+    async fn execute(
+        &self,
+        conn: &mut sqlx::SqliteConnection,
+    ) -> Result<ResponsePayload, ResponseError> {
+        let key = crate::helper::generate_token(42);
+        // the opaque key of the session is not exposed in the token
+        let jti = self.signer.map(|_| crate::helper::generate_token(32));
+        let session = crate::entity::session::Create {
+            access_token: key.as_str(),
+            jti: jti.as_deref(),
+            client_id: self.client_id,
+            user_id: self.user_id,
+            scope: self.scope,
+            authorization_code: self.authorization_code,
+            dpop_jkt: self.dpop_jkt,
+            audience: self.audience,
+            actor: self.actor,
+            time_to_live: self.time_to_live,
+        }
+        .execute(&mut *conn)
+        .await?;
+
+        let refresh_token = match self.refresh_family {
+            Some(family) => {
+                let token = crate::helper::generate_token(42);
+                crate::entity::refresh_token::Create {
+                    token: token.as_str(),
+                    family,
+                    access_token: key.as_str(),
+                    time_to_live: REFRESH_TOKEN_TTL,
+                }
+                .execute(&mut *conn)
+                .await?;
+                Some(token)
+            }
+            None => None,
+        };
+
+        let access_token = match (self.signer, jti) {
+            (Some(signer), Some(jti)) => signer.sign(jti, session)?,
+            _ => key,
+        };
+
+        Ok(ResponsePayload {
+            accept: AcceptHeader::default(),
+            authentication: None,
+            access_token,
+            issued_token_type: None,
+            refresh_token,
+            scope: self.scope.map(String::from),
+            token_type: match self.dpop_jkt {
+                Some(_) => TokenType::DPoP,
+                None => TokenType::Bearer,
+            },
+            expires_in: self.time_to_live.as_secs(),
+            id_token: None,
+        })
+    }
+}
+
+pub(super) async fn handle(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Extension(base_url): Extension<BaseUrl>,
+    Extension(keys): Extension<Keys>,
+    accept: AcceptHeader,
+    ClientAuthorization(basic): ClientAuthorization,
+    DPoPHeader(proof): DPoPHeader,
+    AnyContentType(payload): AnyContentType<RequestPayload>,
+) -> Result<ResponsePayload, ResponseError> {
+    let client = authenticate_client(database.as_ref(), basic, payload.client).await?;
+    if let Some(grant_type) = payload.grant.grant_type() {
+        if !client.allows_grant_type(grant_type) {
+            tracing::warn!(message = "grant type not registered", client_id = %client.id, grant_type);
+            return Err(ResponseError::UnauthorizedClient);
+        }
+    }
+    let dpop_jkt = match proof {
+        Some(proof) => Some(proof.verify(&database, &base_url, None).await?),
+        None => None,
+    };
+    let dpop_jkt = dpop_jkt.as_deref();
+    let signer = client.jwt_access_token.then_some(AccessTokenSigner {
+        keys: &keys,
+        base_url: &base_url,
+    });
+    let mut response = match payload.grant {
+        GrantPayload::AuthorizationCode(inner) => {
+            authorization_code::handle(&database, &client, dpop_jkt, signer, inner).await?
+        }
+        GrantPayload::RefreshToken(inner) => {
+            refresh_token::handle(&database, &client, dpop_jkt, signer, inner).await?
+        }
+        GrantPayload::ClientCredentials(inner) => {
+            client_credentials::handle(&database, &client, dpop_jkt, signer, inner).await?
+        }
+        GrantPayload::Password(inner) => {
+            password::handle(&database, &client, dpop_jkt, signer, inner).await?
+        }
+        GrantPayload::DeviceCode(inner) => {
+            device_code::handle(&database, &client, dpop_jkt, signer, inner).await?
+        }
+        GrantPayload::TokenExchange(inner) => {
+            token_exchange::handle(&database, &keys, &client, dpop_jkt, signer, inner).await?
+        }
+        GrantPayload::Unsupported => return Err(ResponseError::UnsupportedGrantType),
+    };
+    response.accept = accept;
+    // OpenID Connect Core §3.1.3.3: the hash is computed on the access token as returned
+    if let Some(authentication) = response.authentication.take() {
+        let at_hash = keys
+            .algorithm()
+            .map(|alg| access_token_hash(alg, &response.access_token));
+        let claims = IdTokenClaims::new(&base_url, client.id, authentication, at_hash);
+        let id_token = keys.sign(ID_TOKEN_TYPE, &claims).map_err(|err| {
+            tracing::error!(message = "unable to sign id token", source = %err);
+            ResponseError::Signing
+        })?;
+        response.id_token = Some(id_token);
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
+
+    use crate::router::api::dpop::tests::proof;
+    use crate::router::api::prelude::basic_authorization;
+    use crate::service::dataset::{CLIENT_ID, CLIENT_SECRET, RESOURCE};
+
+    fn dpop_request(proof: String) -> Request<Body> {
+        Request::builder()
+            .uri("/api/access-token")
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .header("DPoP", proof)
+            .method("POST")
+            .body(Body::from("grant_type=client_credentials&scope=service"))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_reject_unsupported_media_types() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        let build_request = |accept: &str, content_type: &str| {
+            Request::builder()
+                .uri("/api/access-token")
+                .header("Accept", accept)
+                .header("Content-Type", content_type)
+                .header(
+                    "Authorization",
+                    basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+                )
+                .method("POST")
+                .body(Body::from("grant_type=client_credentials"))
+                .unwrap()
+        };
+
+        // RFC 6749 §5.2: an unsupported payload is an invalid request
+        let res = app
+            .handle(build_request("application/json", "text/plain"))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_request");
+
+        let res = app
+            .handle(build_request(
+                "text/html",
+                "application/x-www-form-urlencoded",
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_ACCEPTABLE);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_request");
+        assert!(body["error_description"].is_string());
+    }
+
+    #[tokio::test]
+    async fn should_reject_unsupported_grant_type() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+
+        let req = Request::builder()
+            .uri("/api/access-token")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .method("POST")
+            .body(Body::from(
+                "grant_type=authorisation_code&code=aaaaaaaaaaaaaaaaaaa",
+            ))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "unsupported_grant_type");
+    }
+
+    #[tokio::test]
+    async fn should_bind_session_to_dpop_key() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        let (pair, jwk) = crate::jose::tests::ed25519_key("client");
+        let past = chrono::Utc::now() - chrono::Duration::minutes(10);
+        for table in ["dpop_nonces (nonce", "dpop_proofs (jti"] {
+            sqlx::query(&format!(
+                "insert into {table}, created_at, valid_until) values ('expired', $1, $1)"
+            ))
+            .bind(past)
+            .execute(app.database())
+            .await
+            .unwrap();
+        }
+
+        // RFC 9449 §8: the first proof is rejected with a nonce to use
+        let res = app
+            .handle(dpop_request(proof(
+                &pair,
+                &jwk,
+                "POST",
+                "/api/access-token",
+                None,
+                None,
+            )))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let nonce = res.headers().get("DPoP-Nonce").unwrap().to_str().unwrap();
+        let nonce = nonce.to_string();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "use_dpop_nonce");
+
+        // proof issued for another endpoint
+        let res = app
+            .handle(dpop_request(proof(
+                &pair,
+                &jwk,
+                "POST",
+                "/api/revoke",
+                Some(&nonce),
+                None,
+            )))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_dpop_proof");
+
+        let valid = proof(&pair, &jwk, "POST", "/api/access-token", Some(&nonce), None);
+        let res = app.handle(dpop_request(valid.clone())).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["token_type"], "DPoP");
+
+        let session =
+            crate::entity::session::FindByAccessToken::new(body["access_token"].as_str().unwrap())
+                .execute(app.database())
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(session.dpop_jkt, Some(jwk.thumbprint()));
+
+        // the expired nonces and proofs have been dropped
+        let count: (i64,) = sqlx::query_as(
+            "select (select count(*) from dpop_nonces where nonce = 'expired') + (select count(*) from dpop_proofs where jti = 'expired')",
+        )
+        .fetch_one(app.database())
+        .await
+        .unwrap();
+        assert_eq!(count.0, 0);
+
+        // the same proof can't be replayed
+        let res = app.handle(dpop_request(valid)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_dpop_proof");
+    }
+
+    #[tokio::test]
+    async fn should_issue_jwt_access_token() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        sqlx::query("update applications set jwt_access_token = true where id = $1")
+            .bind(CLIENT_ID)
+            .execute(app.database())
+            .await
+            .unwrap();
+        let authorization = basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET);
+
+        let req = Request::builder()
+            .uri("/api/access-token")
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Authorization", authorization.as_str())
+            .method("POST")
+            .body(Body::from(format!(
+                "grant_type=client_credentials&scope=service&resource={RESOURCE}"
+            )))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let access_token = body["access_token"].as_str().unwrap().to_string();
+
+        let (header, claims): (_, super::AccessTokenClaims) =
+            app.keys().verify(&access_token).unwrap();
+        assert_eq!(header.typ.as_deref(), Some("at+jwt"));
+        assert_eq!(claims.iss, "http://localhost:8080");
+        assert_eq!(claims.sub, CLIENT_ID.to_string());
+        assert_eq!(claims.aud, RESOURCE);
+        assert_eq!(claims.client_id, CLIENT_ID);
+        assert_eq!(claims.scope.as_deref(), Some("service"));
+        assert_eq!(claims.exp - claims.iat, 60 * 60 * 24);
+
+        // the session is recorded by the token identifier
+        let session = crate::entity::session::FindByAccessToken::jwt(&claims.jti)
+            .execute(app.database())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.client_id, CLIENT_ID);
+        assert_ne!(session.access_token, claims.jti);
+
+        let introspect = |token: &str| {
+            Request::builder()
+                .uri("/api/introspect")
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .header("Authorization", authorization.as_str())
+                .method("POST")
+                .body(Body::from(format!("token={token}")))
+                .unwrap()
+        };
+        let res = app.handle(introspect(&access_token)).await;
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["active"], true);
+        assert_eq!(body["aud"], RESOURCE);
+
+        // neither the bare identifier nor the session key can be used without the signed token
+        for token in [claims.jti.as_str(), session.access_token.as_str()] {
+            let res = app.handle(introspect(token)).await;
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["active"], false);
+        }
+
+        let req = Request::builder()
+            .uri("/api/revoke")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Authorization", authorization.as_str())
+            .method("POST")
+            .body(Body::from(format!("token={access_token}")))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = app.handle(introspect(&access_token)).await;
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["active"], false);
+    }
+
+    #[tokio::test]
+    async fn should_not_keep_session_when_signing_fails() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await.without_signing_keys();
+        sqlx::query("update applications set jwt_access_token = true where id = $1")
+            .bind(CLIENT_ID)
+            .execute(app.database())
+            .await
+            .unwrap();
+
+        let req = Request::builder()
+            .uri("/api/access-token")
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .method("POST")
+            .body(Body::from("grant_type=client_credentials&scope=service"))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let count: i64 = sqlx::query_scalar("select count(*) from sessions")
+            .fetch_one(app.database())
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+}
@@ -0,0 +1,153 @@
+use super::{
+    grant_resource, AccessTokenSigner, Issue, ResponseError, ResponsePayload, ACCESS_TOKEN_TTL,
+};
+use crate::entity::user::FindForCredentials;
+
+#[derive(serde::Deserialize)]
+#[cfg_attr(test, derive(Debug, serde::Serialize))]
+pub(crate) struct RequestPayload {
+    pub username: String,
+    pub password: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+}
+
+pub(super) async fn handle(
+    database: &crate::service::database::Pool,
+    client: &crate::entity::application::Entity,
+    dpop_jkt: Option<&str>,
+    signer: Option<AccessTokenSigner<'_>>,
+    payload: RequestPayload,
+) -> Result<ResponsePayload, ResponseError> {
+    if !client.allow_password_grant {
+        tracing::warn!(message = "password grant not enabled for client", client_id = %client.id);
+        return Err(ResponseError::UnauthorizedClient);
+    }
+
+    let scope = client
+        .grant_scope(payload.scope.as_deref())
+        .map_err(|_| ResponseError::InvalidScope)?;
+    let audience = grant_resource(client, payload.resource.as_deref(), None)?;
+
+    let mut tx = database.as_ref().begin().await?;
+    let user = FindForCredentials::new(client.id, payload.username.as_str())
+        .execute(&mut *tx)
+        .await?;
+    let Some(user) = user else {
+        tracing::warn!(message = "user not found with provided email", email = %payload.username);
+        return Err(ResponseError::InvalidCredentials);
+    };
+    if !user.check_password(payload.password.as_str()) {
+        tracing::warn!(message = "invalid password", email = %payload.username);
+        return Err(ResponseError::InvalidCredentials);
+    }
+
+    let refresh_family = crate::helper::generate_token(24);
+    let response = Issue {
+        client_id: client.id,
+        user_id: Some(user.id),
+        scope: scope.as_deref(),
+        authorization_code: None,
+        refresh_family: Some(refresh_family.as_str()),
+        dpop_jkt,
+        audience,
+        actor: None,
+        time_to_live: ACCESS_TOKEN_TTL,
+        signer,
+    }
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::collections::HashSet;
+
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
+
+    use crate::router::api::access_token::{GrantPayload, RequestPayload, ResponsePayload};
+    use crate::router::api::prelude::{basic_authorization, ClientCredentials};
+    use crate::service::dataset::{CLIENT_ID, CLIENT_SECRET, REDIRECT_URI};
+
+    fn request(password: &str) -> Request<Body> {
+        Request::builder()
+            .uri("/api/access-token")
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .method("POST")
+            .body(Body::from(
+                serde_urlencoded::to_string(RequestPayload {
+                    client: ClientCredentials::default(),
+                    grant: GrantPayload::Password(super::RequestPayload {
+                        username: "charles@example.com".into(),
+                        password: password.into(),
+                        scope: None,
+                        resource: None,
+                    }),
+                })
+                .unwrap(),
+            ))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_create_access_token_for_user() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+
+        let res = app.handle(request("this-is-a-password")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: ResponsePayload = serde_json::from_slice(&body).unwrap();
+        assert!(body.refresh_token.is_some());
+
+        let req = Request::builder()
+            .uri("/api/user-info")
+            .header("Authorization", format!("Bearer {}", body.access_token))
+            .method("GET")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_password() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+
+        let res = app.handle(request("not-the-password")).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn should_reject_when_not_enabled() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        crate::entity::application::Upsert::new(
+            CLIENT_ID,
+            &HashSet::from_iter([CLIENT_SECRET.to_string()]),
+            &[REDIRECT_URI.into()],
+        )
+        .execute(app.database())
+        .await
+        .unwrap();
+
+        let res = app.handle(request("this-is-a-password")).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
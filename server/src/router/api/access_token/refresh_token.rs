@@ -0,0 +1,364 @@
+use super::{
+    grant_resource, is_subset, AccessTokenSigner, Issue, ResponseError, ResponsePayload,
+    ACCESS_TOKEN_TTL,
+};
+
+#[derive(serde::Deserialize)]
+#[cfg_attr(test, derive(Debug, serde::Serialize))]
+pub(crate) struct RequestPayload {
+    pub refresh_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+}
+
+// a rotated refresh token presented again means it leaked, the whole family is revoked
+async fn revoke_reused_family(
+    mut tx: sqlx::Transaction<'_, sqlx::Sqlite>,
+    family: &str,
+) -> Result<ResponsePayload, ResponseError> {
+    let revoked = crate::entity::session::RevokeByRefreshFamily::new(family)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    tracing::warn!(message = "refresh token reused", revoked_sessions = revoked);
+    Err(ResponseError::RefreshTokenAlreadyUsed)
+}
+
+pub(super) async fn handle(
+    database: &crate::service::database::Pool,
+    client: &crate::entity::application::Entity,
+    dpop_jkt: Option<&str>,
+    signer: Option<AccessTokenSigner<'_>>,
+    payload: RequestPayload,
+) -> Result<ResponsePayload, ResponseError> {
+    let mut tx = database.as_ref().begin().await?;
+    let state = crate::entity::refresh_token::FindByToken::new(payload.refresh_token.as_str())
+        .execute(&mut *tx)
+        .await?
+        .ok_or(ResponseError::RefreshTokenNotFound)?;
+
+    if state.client_id != client.id {
+        tracing::warn!(message = "refresh token used by another client", client_id = %client.id);
+        return Err(ResponseError::RefreshTokenClientMismatch);
+    }
+
+    if state.consumed_at.is_some() {
+        return revoke_reused_family(tx, &state.family).await;
+    }
+
+    // RFC 9449 §5: the refresh tokens of public clients are bound to the key of the first proof
+    if client.kind == crate::entity::application::ApplicationKind::Public
+        && state.dpop_jkt.is_some()
+        && state.dpop_jkt.as_deref() != dpop_jkt
+    {
+        tracing::warn!(message = "refresh token used with another key", client_id = %client.id);
+        return Err(ResponseError::DPoPKeyMismatch);
+    }
+
+    // the scopes removed from the application since the first grant aren't granted anymore
+    let scope = match payload.scope {
+        Some(ref requested) if !is_subset(requested, state.scope.as_deref()) => {
+            return Err(ResponseError::InvalidScope);
+        }
+        Some(requested) => client.grant_scope(Some(&requested)).map_err(|err| {
+            tracing::debug!(message = "invalid scope", source = %err);
+            ResponseError::InvalidScope
+        })?,
+        None => {
+            let registered = state
+                .scope
+                .iter()
+                .flat_map(|value| value.split_whitespace())
+                .filter(|item| client.scopes.iter().any(|scope| scope == item))
+                .collect::<Vec<_>>();
+            (!registered.is_empty()).then(|| registered.join(" "))
+        }
+    };
+    let audience = grant_resource(
+        client,
+        payload.resource.as_deref(),
+        state.audience.as_deref(),
+    )?;
+
+    if !crate::entity::refresh_token::Consume::new(&state.token)
+        .execute(&mut *tx)
+        .await?
+    {
+        return revoke_reused_family(tx, &state.family).await;
+    }
+
+    let response = Issue {
+        client_id: state.client_id,
+        user_id: state.user_id,
+        scope: scope.as_deref(),
+        authorization_code: state.authorization_code.as_deref(),
+        refresh_family: Some(state.family.as_str()),
+        dpop_jkt,
+        audience,
+        actor: None,
+        time_to_live: ACCESS_TOKEN_TTL,
+        signer,
+    }
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::time::Duration;
+
+    use axum::body::Body;
+    use axum::http::{HeaderValue, Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
+
+    use crate::router::api::access_token::{GrantPayload, RequestPayload, ResponsePayload};
+    use crate::router::api::dpop::tests::proof;
+    use crate::router::api::prelude::{basic_authorization, ClientCredentials};
+    use crate::service::dataset::{ALICE_ID, CLIENT_ID, CLIENT_SECRET};
+
+    const SHORT_TTL: Duration = Duration::new(5, 0);
+
+    async fn create_session(app: &crate::app::Application) {
+        crate::entity::session::Create {
+            access_token: "aaaaaaaaaaaaaaaaaaa",
+            jti: None,
+            client_id: CLIENT_ID,
+            user_id: Some(ALICE_ID),
+            scope: Some("profile email"),
+            authorization_code: None,
+            dpop_jkt: None,
+            audience: None,
+            actor: None,
+            time_to_live: SHORT_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+        crate::entity::refresh_token::Create {
+            token: "bbbbbbbbbbbbbbbbbbb",
+            family: "family",
+            access_token: "aaaaaaaaaaaaaaaaaaa",
+            time_to_live: SHORT_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+    }
+
+    fn refresh_request(refresh_token: &str, scope: Option<&str>) -> Request<Body> {
+        Request::builder()
+            .uri("/api/access-token")
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .method("POST")
+            .body(Body::from(
+                serde_urlencoded::to_string(RequestPayload {
+                    client: ClientCredentials::default(),
+                    grant: GrantPayload::RefreshToken(super::RequestPayload {
+                        refresh_token: refresh_token.into(),
+                        scope: scope.map(String::from),
+                        resource: None,
+                    }),
+                })
+                .unwrap(),
+            ))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_rotate_refresh_token() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        create_session(&app).await;
+
+        let res = app
+            .handle(refresh_request("bbbbbbbbbbbbbbbbbbb", Some("profile")))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: ResponsePayload = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.scope.as_deref(), Some("profile"));
+        let rotated = body.refresh_token.unwrap();
+        assert_ne!(rotated, "bbbbbbbbbbbbbbbbbbb");
+
+        let res = app.handle(refresh_request(&rotated, None)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_reject_wider_scope() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        create_session(&app).await;
+
+        let res = app
+            .handle(refresh_request(
+                "bbbbbbbbbbbbbbbbbbb",
+                Some("profile service"),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn should_drop_scope_removed_from_application() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        create_session(&app).await;
+        sqlx::query("update applications set scopes = 'openid profile service' where id = $1")
+            .bind(CLIENT_ID)
+            .execute(app.database())
+            .await
+            .unwrap();
+
+        let res = app
+            .handle(refresh_request("bbbbbbbbbbbbbbbbbbb", Some("email")))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_scope");
+
+        let res = app
+            .handle(refresh_request("bbbbbbbbbbbbbbbbbbb", None))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: ResponsePayload = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.scope.as_deref(), Some("profile"));
+    }
+
+    #[tokio::test]
+    async fn should_require_bound_key_for_public_client() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        create_session(&app).await;
+        let (pair, jwk) = crate::jose::tests::ed25519_key("client");
+        let (other_pair, other_jwk) = crate::jose::tests::ed25519_key("other");
+        sqlx::query("update applications set kind = 0 where id = $1")
+            .bind(CLIENT_ID)
+            .execute(app.database())
+            .await
+            .unwrap();
+        sqlx::query("update sessions set dpop_jkt = $1")
+            .bind(jwk.thumbprint())
+            .execute(app.database())
+            .await
+            .unwrap();
+        let with_proof = |proof: String| {
+            let mut req = refresh_request("bbbbbbbbbbbbbbbbbbb", None);
+            req.headers_mut()
+                .insert("DPoP", HeaderValue::try_from(proof).unwrap());
+            req
+        };
+
+        // RFC 9449 §5: without a proof, the refresh token is refused
+        let res = app
+            .handle(refresh_request("bbbbbbbbbbbbbbbbbbb", None))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_grant");
+
+        let res = app
+            .handle(with_proof(proof(
+                &pair,
+                &jwk,
+                "POST",
+                "/api/access-token",
+                None,
+                None,
+            )))
+            .await;
+        let nonce = res.headers().get("DPoP-Nonce").unwrap().to_str().unwrap();
+        let nonce = nonce.to_string();
+
+        // a proof signed with another key is refused
+        let res = app
+            .handle(with_proof(proof(
+                &other_pair,
+                &other_jwk,
+                "POST",
+                "/api/access-token",
+                Some(&nonce),
+                None,
+            )))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_grant");
+
+        let res = app
+            .handle(with_proof(proof(
+                &pair,
+                &jwk,
+                "POST",
+                "/api/access-token",
+                Some(&nonce),
+                None,
+            )))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: ResponsePayload = serde_json::from_slice(&body).unwrap();
+        let session = crate::entity::session::FindByAccessToken::new(&body.access_token)
+            .execute(app.database())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.dpop_jkt, Some(jwk.thumbprint()));
+    }
+
+    #[tokio::test]
+    async fn should_revoke_family_when_refresh_token_is_reused() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+        create_session(&app).await;
+
+        let res = app
+            .handle(refresh_request("bbbbbbbbbbbbbbbbbbb", None))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: ResponsePayload = serde_json::from_slice(&body).unwrap();
+
+        let res = app
+            .handle(refresh_request("bbbbbbbbbbbbbbbbbbb", None))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        // the rotated refresh token and its access token are no longer usable
+        let res = app
+            .handle(refresh_request(
+                body.refresh_token.as_deref().unwrap(),
+                None,
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let req = Request::builder()
+            .uri("/api/user-info")
+            .header("Authorization", format!("Bearer {}", body.access_token))
+            .method("GET")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,435 @@
+use super::{
+    grant_resource, is_subset, session_token, AccessTokenSigner, Issue, ResponseError,
+    ResponsePayload, TokenTypeIdentifier, ACCESS_TOKEN_TTL,
+};
+use crate::entity::session::Actor;
+use crate::service::keys::Keys;
+
+#[derive(serde::Deserialize)]
+#[cfg_attr(test, derive(Debug, serde::Serialize))]
+pub(crate) struct RequestPayload {
+    pub subject_token: String,
+    pub subject_token_type: TokenTypeIdentifier,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor_token_type: Option<TokenTypeIdentifier>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requested_token_type: Option<TokenTypeIdentifier>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audience: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+// RFC 9449 §5: a token bound to a key can only be exchanged with a proof signed by that key
+fn check_binding(bound: Option<&str>, dpop_jkt: Option<&str>) -> Result<(), ResponseError> {
+    match bound {
+        Some(bound) if Some(bound) != dpop_jkt => Err(ResponseError::TokenKeyMismatch),
+        _ => Ok(()),
+    }
+}
+
+pub(super) async fn handle(
+    database: &crate::service::database::Pool,
+    keys: &Keys,
+    client: &crate::entity::application::Entity,
+    dpop_jkt: Option<&str>,
+    signer: Option<AccessTokenSigner<'_>>,
+    payload: RequestPayload,
+) -> Result<ResponsePayload, ResponseError> {
+    if !client.allow_token_exchange {
+        tracing::warn!(message = "token exchange not enabled for client", client_id = %client.id);
+        return Err(ResponseError::UnauthorizedClient);
+    }
+    if payload.subject_token_type != TokenTypeIdentifier::AccessToken
+        || payload
+            .requested_token_type
+            .is_some_and(|value| value != TokenTypeIdentifier::AccessToken)
+    {
+        return Err(ResponseError::UnsupportedTokenType);
+    }
+
+    let mut tx = database.as_ref().begin().await?;
+    let subject = session_token(keys, &payload.subject_token)
+        .find()
+        .execute(&mut *tx)
+        .await?
+        .ok_or(ResponseError::InvalidSubjectToken)?;
+    check_binding(subject.dpop_jkt.as_deref(), dpop_jkt)?;
+
+    // RFC 8693 §2.1: the actor token type is required with the actor token, and only then
+    let actor = match (payload.actor_token.as_deref(), payload.actor_token_type) {
+        (None, None) => None,
+        (Some(token), Some(TokenTypeIdentifier::AccessToken)) => {
+            let found = session_token(keys, token)
+                .find()
+                .execute(&mut *tx)
+                .await?
+                .ok_or(ResponseError::InvalidActorToken)?;
+            check_binding(found.dpop_jkt.as_deref(), dpop_jkt)?;
+            Some(found)
+        }
+        (Some(_), Some(_)) => return Err(ResponseError::UnsupportedTokenType),
+        _ => return Err(ResponseError::InvalidActorToken),
+    };
+
+    // the exchanged token can only be downscoped
+    let scope = match payload.scope {
+        Some(ref requested) if !is_subset(requested, subject.scope.as_deref()) => {
+            return Err(ResponseError::InvalidScope);
+        }
+        Some(requested) => Some(requested),
+        None => subject.scope,
+    };
+
+    // RFC 8693 §2.1: the resource has to be allowed for the client, the audience is a logical name
+    let requested = match (payload.resource.as_deref(), payload.audience.as_deref()) {
+        (Some(resource), Some(audience)) if resource != audience => {
+            return Err(ResponseError::InvalidTarget);
+        }
+        (Some(resource), _) => Some(resource),
+        (None, audience) => audience,
+    };
+    // the audience of the subject token can't be widened by the exchange
+    let audience = grant_resource(client, requested, subject.audience.as_deref())?;
+
+    // RFC 8693 §4.1: the new actor comes first, followed by the ones of the subject token
+    let actor = match actor {
+        Some(found) => Some(Actor {
+            sub: found.user_id.unwrap_or(found.client_id).to_string(),
+            act: subject.actor.map(Box::new),
+        }),
+        None => subject.actor,
+    };
+
+    // the exchanged token doesn't outlive the subject token, so that chains can't extend it
+    let remaining = (subject.valid_until - chrono::Utc::now())
+        .to_std()
+        .map_err(|_| ResponseError::InvalidSubjectToken)?;
+
+    let mut response = Issue {
+        client_id: client.id,
+        user_id: subject.user_id,
+        scope: scope.as_deref(),
+        authorization_code: None,
+        refresh_family: None,
+        dpop_jkt,
+        audience,
+        actor: actor.as_ref(),
+        time_to_live: remaining.min(ACCESS_TOKEN_TTL),
+        signer,
+    }
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    response.issued_token_type = Some(TokenTypeIdentifier::AccessToken);
+    Ok(response)
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::time::Duration;
+
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
+
+    use crate::entity::session::Actor;
+    use crate::router::api::prelude::basic_authorization;
+    use crate::service::dataset::{ALICE_ID, CLIENT_ID, CLIENT_SECRET, RESOURCE};
+
+    const SHORT_TTL: Duration = Duration::new(60, 0);
+    const ACCESS_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:access_token";
+
+    async fn create_session(app: &crate::app::Application, access_token: &str, user: bool) {
+        crate::entity::session::Create {
+            access_token,
+            jti: None,
+            client_id: CLIENT_ID,
+            user_id: user.then_some(ALICE_ID),
+            scope: Some("read write"),
+            authorization_code: None,
+            dpop_jkt: None,
+            audience: None,
+            actor: None,
+            time_to_live: SHORT_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+    }
+
+    fn exchange_request(params: &[(&str, &str)]) -> Request<Body> {
+        let mut form = vec![(
+            "grant_type",
+            "urn:ietf:params:oauth:grant-type:token-exchange",
+        )];
+        form.extend_from_slice(params);
+        Request::builder()
+            .uri("/api/access-token")
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .method("POST")
+            .body(Body::from(serde_urlencoded::to_string(form).unwrap()))
+            .unwrap()
+    }
+
+    async fn json_body(res: axum::response::Response) -> serde_json::Value {
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_exchange_for_downscoped_token() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        create_session(&app, "aaaaaaaaaaaaaaaaaaa", true).await;
+
+        let res = app
+            .handle(exchange_request(&[
+                ("subject_token", "aaaaaaaaaaaaaaaaaaa"),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("audience", RESOURCE),
+                ("scope", "read"),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = json_body(res).await;
+        assert_eq!(body["issued_token_type"], ACCESS_TOKEN_TYPE);
+        assert_eq!(body["token_type"], "bearer");
+        assert_eq!(body["scope"], "read");
+        assert!(body.get("refresh_token").is_none());
+
+        let session =
+            crate::entity::session::FindByAccessToken::new(body["access_token"].as_str().unwrap())
+                .execute(app.database())
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(session.user_id, Some(ALICE_ID));
+        assert_eq!(session.audience.as_deref(), Some(RESOURCE));
+        // the exchanged token doesn't outlive the subject token
+        assert!(body["expires_in"].as_u64().unwrap() <= SHORT_TTL.as_secs());
+        assert!(session.actor.is_none());
+
+        // the scope can't be extended
+        let res = app
+            .handle(exchange_request(&[
+                ("subject_token", "aaaaaaaaaaaaaaaaaaa"),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("scope", "read admin"),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json_body(res).await["error"], "invalid_scope");
+
+        let res = app
+            .handle(exchange_request(&[
+                ("subject_token", "unknown"),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json_body(res).await["error"], "invalid_request");
+
+        let res = app
+            .handle(exchange_request(&[
+                ("subject_token", "aaaaaaaaaaaaaaaaaaa"),
+                (
+                    "subject_token_type",
+                    "urn:ietf:params:oauth:token-type:saml2",
+                ),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json_body(res).await["error"], "invalid_request");
+    }
+
+    #[tokio::test]
+    async fn should_chain_delegation_actors() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        create_session(&app, "aaaaaaaaaaaaaaaaaaa", true).await;
+        create_session(&app, "bbbbbbbbbbbbbbbbbbb", false).await;
+
+        let res = app
+            .handle(exchange_request(&[
+                ("subject_token", "aaaaaaaaaaaaaaaaaaa"),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("actor_token", "bbbbbbbbbbbbbbbbbbb"),
+                ("actor_token_type", ACCESS_TOKEN_TYPE),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let first = json_body(res).await;
+        let first = first["access_token"].as_str().unwrap().to_string();
+
+        // the delegated token is exchanged again by another service
+        let res = app
+            .handle(exchange_request(&[
+                ("subject_token", first.as_str()),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("actor_token", "aaaaaaaaaaaaaaaaaaa"),
+                ("actor_token_type", ACCESS_TOKEN_TYPE),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let second = json_body(res).await;
+
+        let session = crate::entity::session::FindByAccessToken::new(
+            second["access_token"].as_str().unwrap(),
+        )
+        .execute(app.database())
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(session.user_id, Some(ALICE_ID));
+        assert_eq!(
+            session.actor,
+            Some(Actor {
+                sub: ALICE_ID.to_string(),
+                act: Some(Box::new(Actor {
+                    sub: CLIENT_ID.to_string(),
+                    act: None,
+                })),
+            })
+        );
+
+        // the actor token type is required with the actor token
+        let res = app
+            .handle(exchange_request(&[
+                ("subject_token", "aaaaaaaaaaaaaaaaaaa"),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("actor_token", "bbbbbbbbbbbbbbbbbbb"),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json_body(res).await["error"], "invalid_request");
+    }
+
+    #[tokio::test]
+    async fn should_require_enabled_client() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        create_session(&app, "aaaaaaaaaaaaaaaaaaa", true).await;
+        sqlx::query("update applications set allow_token_exchange = false where id = $1")
+            .bind(CLIENT_ID)
+            .execute(app.database())
+            .await
+            .unwrap();
+
+        let res = app
+            .handle(exchange_request(&[
+                ("subject_token", "aaaaaaaaaaaaaaaaaaa"),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json_body(res).await["error"], "unauthorized_client");
+    }
+
+    #[tokio::test]
+    async fn should_restrict_audience_to_client_resources() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        create_session(&app, "aaaaaaaaaaaaaaaaaaa", true).await;
+
+        // the audience is checked against the resources allowed for the client
+        let res = app
+            .handle(exchange_request(&[
+                ("subject_token", "aaaaaaaaaaaaaaaaaaa"),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("audience", "http://other/api"),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json_body(res).await["error"], "invalid_target");
+
+        // RFC 8707 §2: a single resource is supported
+        let res = app
+            .handle(exchange_request(&[
+                ("subject_token", "aaaaaaaaaaaaaaaaaaa"),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("resource", RESOURCE),
+                ("resource", "http://other/api"),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json_body(res).await["error"], "invalid_target");
+    }
+
+    #[tokio::test]
+    async fn should_keep_subject_restrictions() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        create_session(&app, "aaaaaaaaaaaaaaaaaaa", true).await;
+        create_session(&app, "bbbbbbbbbbbbbbbbbbb", true).await;
+        sqlx::query("update sessions set audience = $1 where access_token = 'aaaaaaaaaaaaaaaaaaa'")
+            .bind(RESOURCE)
+            .execute(app.database())
+            .await
+            .unwrap();
+        sqlx::query(
+            "update sessions set dpop_jkt = 'thumbprint' where access_token = 'bbbbbbbbbbbbbbbbbbb'",
+        )
+        .execute(app.database())
+        .await
+        .unwrap();
+
+        // the audience can't be widened, nor replaced
+        let res = app
+            .handle(exchange_request(&[
+                ("subject_token", "aaaaaaaaaaaaaaaaaaa"),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("audience", "http://other/api"),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json_body(res).await["error"], "invalid_target");
+
+        let res = app
+            .handle(exchange_request(&[
+                ("subject_token", "aaaaaaaaaaaaaaaaaaa"),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = json_body(res).await;
+        let session =
+            crate::entity::session::FindByAccessToken::new(body["access_token"].as_str().unwrap())
+                .execute(app.database())
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(session.audience.as_deref(), Some(RESOURCE));
+
+        // a token bound to a key can't become a bearer token
+        let res = app
+            .handle(exchange_request(&[
+                ("subject_token", "bbbbbbbbbbbbbbbbbbb"),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json_body(res).await["error"], "invalid_request");
+
+        let res = app
+            .handle(exchange_request(&[
+                ("subject_token", "aaaaaaaaaaaaaaaaaaa"),
+                ("subject_token_type", ACCESS_TOKEN_TYPE),
+                ("actor_token", "bbbbbbbbbbbbbbbbbbb"),
+                ("actor_token_type", ACCESS_TOKEN_TYPE),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json_body(res).await["error"], "invalid_request");
+    }
+}
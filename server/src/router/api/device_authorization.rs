@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+
+use super::access_token::AnyContentType;
+use super::prelude::{
+    authenticate_client, ClientAuthenticationError, ClientAuthorization, ClientCredentials,
+};
+use crate::service::base_url::BaseUrl;
+
+// 10 mins
+pub(super) const DEVICE_CODE_TTL: Duration = Duration::new(600, 0);
+// RFC 8628 §3.2: default polling interval in seconds
+pub(super) const DEFAULT_INTERVAL: u32 = 5;
+// the generation of a user code is retried on collision
+const USER_CODE_ATTEMPTS: u32 = 5;
+
+pub(crate) enum ResponseError {
+    ClientAuthentication(ClientAuthenticationError),
+    InvalidScope,
+    Database,
+}
+
+impl From<sqlx::Error> for ResponseError {
+    fn from(value: sqlx::Error) -> Self {
+        tracing::error!(message = "database interaction failed", error = %value);
+        Self::Database
+    }
+}
+
+impl From<ClientAuthenticationError> for ResponseError {
+    fn from(value: ClientAuthenticationError) -> Self {
+        Self::ClientAuthentication(value)
+    }
+}
+
+impl IntoResponse for ResponseError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::ClientAuthentication(inner) => inner.into_response(),
+            Self::InvalidScope => super::error::Error::bad_request("invalid_scope")
+                .with_description("requested scope is not allowed")
+                .into_response(),
+            Self::Database => super::error::Error::internal().into_response(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[cfg_attr(test, derive(Debug, serde::Serialize))]
+pub(crate) struct RequestPayload {
+    #[serde(flatten)]
+    pub client: ClientCredentials,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+#[cfg_attr(test, derive(serde::Deserialize))]
+pub(crate) struct ResponsePayload {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    verification_uri_complete: String,
+    expires_in: u64,
+    interval: u32,
+}
+
+pub(super) async fn handle(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Extension(base_url): Extension<BaseUrl>,
+    ClientAuthorization(basic): ClientAuthorization,
+    AnyContentType(payload): AnyContentType<RequestPayload>,
+) -> Result<Json<ResponsePayload>, ResponseError> {
+    let client = authenticate_client(database.as_ref(), basic, payload.client).await?;
+
+    let scope = client
+        .grant_scope(payload.scope.as_deref())
+        .map_err(|_| ResponseError::InvalidScope)?;
+
+    let device_code = crate::helper::generate_token(42);
+    let mut attempt = 1;
+    // the user codes are short, a collision gets a new one
+    let user_code = loop {
+        let user_code = crate::helper::generate_user_code();
+        match (crate::entity::device_authorization::Create {
+            device_code: device_code.as_str(),
+            user_code: user_code.as_str(),
+            client_id: client.id,
+            scope: scope.as_deref(),
+            interval: DEFAULT_INTERVAL,
+            time_to_live: DEVICE_CODE_TTL,
+        })
+        .execute(database.as_ref())
+        .await
+        {
+            Ok(_) => break user_code,
+            Err(sqlx::Error::Database(err))
+                if err.is_unique_violation() && attempt < USER_CODE_ATTEMPTS =>
+            {
+                tracing::debug!(message = "user code collision", attempt);
+                attempt += 1;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    };
+
+    let user_code = crate::helper::format_user_code(&user_code);
+    let verification_uri = base_url.join("/device");
+    let verification_uri_complete = format!("{verification_uri}?user_code={user_code}");
+    Ok(Json(ResponsePayload {
+        device_code,
+        user_code,
+        verification_uri,
+        verification_uri_complete,
+        expires_in: DEVICE_CODE_TTL.as_secs(),
+        interval: DEFAULT_INTERVAL,
+    }))
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
+
+    use crate::router::api::prelude::basic_authorization;
+    use crate::service::dataset::{CLIENT_ID, CLIENT_SECRET};
+
+    #[tokio::test]
+    async fn should_create_device_authorization() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+
+        let req = Request::builder()
+            .uri("/api/device-authorization")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .method("POST")
+            .body(Body::from("scope=profile"))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: super::ResponsePayload = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.interval, 5);
+        assert_eq!(body.expires_in, 600);
+        assert_eq!(body.user_code.len(), 9);
+        assert!(body.verification_uri.ends_with("/device"));
+        assert!(body.verification_uri_complete.contains(&body.user_code));
+
+        let user_code = crate::helper::normalize_user_code(&body.user_code);
+        let found = crate::entity::device_authorization::FindPendingByUserCode::new(&user_code)
+            .execute(app.database())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.device_code, body.device_code);
+        assert_eq!(found.scope.as_deref(), Some("profile"));
+    }
+
+    #[tokio::test]
+    async fn should_reject_unknown_client() {
+        crate::enable_tracing();
+
+        let app = crate::app::Application::test().await;
+
+        let req = Request::builder()
+            .uri("/api/device-authorization")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .method("POST")
+            .body(Body::from(format!("client_id={}", uuid::Uuid::new_v4())))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,237 @@
+use std::time::Duration;
+
+use axum::extract::OriginalUri;
+use axum::http::{Method, StatusCode};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+use super::error::Error;
+use crate::service::base_url::BaseUrl;
+
+// 5 minutes
+const NONCE_TTL: Duration = Duration::new(60 * 5, 0);
+// the identifiers are kept longer than a proof can be accepted
+const PROOF_TTL: Duration = Duration::new(60 * 10, 0);
+// RFC 9449 §11.1: how old a proof can be, and how much clock skew is accepted
+const PROOF_MAX_AGE: i64 = 60 * 5;
+const PROOF_LEEWAY: i64 = 60;
+
+#[derive(serde::Deserialize)]
+struct Claims {
+    jti: String,
+    htm: String,
+    htu: String,
+    iat: i64,
+    #[serde(default)]
+    nonce: Option<String>,
+    #[serde(default)]
+    ath: Option<String>,
+}
+
+#[derive(Debug)]
+pub(crate) enum DPoPError {
+    Invalid(&'static str),
+    UseNonce(String),
+    Database,
+}
+
+impl From<sqlx::Error> for DPoPError {
+    fn from(value: sqlx::Error) -> Self {
+        tracing::error!(message = "database interaction failed", error = %value);
+        Self::Database
+    }
+}
+
+impl DPoPError {
+    // RFC 9449 §5: errors on the token endpoint
+    pub(super) fn token_error(self) -> Error {
+        match self {
+            Self::Invalid(description) => {
+                Error::bad_request("invalid_dpop_proof").with_description(description)
+            }
+            Self::UseNonce(nonce) => Error::bad_request("use_dpop_nonce")
+                .with_description("authorization server requires nonce in DPoP proof")
+                .with_dpop_nonce(nonce),
+            Self::Database => Error::internal(),
+        }
+    }
+
+    // RFC 9449 §7.1: errors on the protected resources
+    pub(super) fn resource_error(self) -> Error {
+        match self {
+            Self::Invalid(description) => {
+                Error::dpop(StatusCode::UNAUTHORIZED, "invalid_dpop_proof", description)
+            }
+            Self::UseNonce(nonce) => Error::dpop(
+                StatusCode::UNAUTHORIZED,
+                "use_dpop_nonce",
+                "resource server requires nonce in DPoP proof",
+            )
+            .with_dpop_nonce(nonce),
+            Self::Database => Error::internal(),
+        }
+    }
+}
+
+pub(crate) struct DPoPProof {
+    token: String,
+    method: Method,
+    path: String,
+}
+
+// RFC 9449 §4.1: the proof is given in the DPoP header
+pub(super) struct DPoPHeader(pub Option<DPoPProof>);
+
+#[axum::async_trait]
+impl<S> axum::extract::FromRequestParts<S> for DPoPHeader
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let mut values = parts.headers.get_all("DPoP").iter();
+        let Some(value) = values.next() else {
+            return Ok(Self(None));
+        };
+        if values.next().is_some() {
+            return Err(Error::bad_request("invalid_dpop_proof")
+                .with_description("more than one DPoP proof provided"));
+        }
+        let token = value.to_str().map_err(|_| {
+            Error::bad_request("invalid_dpop_proof").with_description("malformed DPoP proof")
+        })?;
+        // the routes are nested, the proof refers to the full path
+        let path = match parts.extensions.get::<OriginalUri>() {
+            Some(OriginalUri(uri)) => uri.path().to_string(),
+            None => parts.uri.path().to_string(),
+        };
+        Ok(Self(Some(DPoPProof {
+            token: token.to_string(),
+            method: parts.method.clone(),
+            path,
+        })))
+    }
+}
+
+pub(super) fn access_token_hash(access_token: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    URL_SAFE_NO_PAD.encode(Sha256::digest(access_token.as_bytes()))
+}
+
+impl DPoPProof {
+    // RFC 9449 §4.3: returns the thumbprint of the key that signed the proof
+    pub(super) async fn verify(
+        &self,
+        database: &crate::service::database::Pool,
+        base_url: &BaseUrl,
+        access_token: Option<&str>,
+    ) -> Result<String, DPoPError> {
+        let (header, claims) = crate::jose::verify_with_embedded_key::<Claims>(&self.token)
+            .map_err(|err| {
+                tracing::debug!(message = "unable to verify DPoP proof", source = %err);
+                DPoPError::Invalid("unable to verify DPoP proof")
+            })?;
+        if header.typ.as_deref() != Some("dpop+jwt") {
+            return Err(DPoPError::Invalid("invalid DPoP proof type"));
+        }
+        if claims.htm != self.method.as_str() {
+            return Err(DPoPError::Invalid("DPoP proof issued for another method"));
+        }
+        // the query and fragment parts are ignored
+        let htu = claims.htu.split(['?', '#']).next().unwrap_or_default();
+        if htu != base_url.join(&self.path) {
+            return Err(DPoPError::Invalid("DPoP proof issued for another uri"));
+        }
+        let now = chrono::Utc::now().timestamp();
+        if claims.iat < now - PROOF_MAX_AGE || claims.iat > now + PROOF_LEEWAY {
+            return Err(DPoPError::Invalid(
+                "DPoP proof expired or issued in the future",
+            ));
+        }
+        if let Some(access_token) = access_token {
+            if claims.ath.as_deref() != Some(access_token_hash(access_token).as_str()) {
+                return Err(DPoPError::Invalid(
+                    "DPoP proof issued for another access token",
+                ));
+            }
+        }
+
+        // RFC 9449 §8: a proof without a valid nonce is rejected with a fresh one
+        let nonce_valid = match claims.nonce.as_deref() {
+            Some(nonce) => {
+                crate::entity::dpop_nonce::IsValid::new(nonce)
+                    .execute(database.as_ref())
+                    .await?
+            }
+            None => false,
+        };
+        if !nonce_valid {
+            crate::entity::dpop_nonce::DeleteExpired
+                .execute(database.as_ref())
+                .await?;
+            let nonce = crate::helper::generate_token(32);
+            crate::entity::dpop_nonce::Create {
+                nonce: nonce.as_str(),
+                time_to_live: NONCE_TTL,
+            }
+            .execute(database.as_ref())
+            .await?;
+            return Err(DPoPError::UseNonce(nonce));
+        }
+
+        crate::entity::dpop_proof::DeleteExpired
+            .execute(database.as_ref())
+            .await?;
+        let first_use = crate::entity::dpop_proof::Create {
+            jti: claims.jti.as_str(),
+            time_to_live: PROOF_TTL,
+        }
+        .execute(database.as_ref())
+        .await?;
+        if !first_use {
+            return Err(DPoPError::Invalid("DPoP proof has already been used"));
+        }
+
+        header
+            .jwk
+            .map(|jwk| jwk.thumbprint())
+            .ok_or(DPoPError::Invalid("DPoP proof without key"))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use ring::signature::Ed25519KeyPair;
+
+    use crate::jose::Jwk;
+
+    // builds a proof for the test base url
+    pub(crate) fn proof(
+        pair: &Ed25519KeyPair,
+        jwk: &Jwk,
+        method: &str,
+        path: &str,
+        nonce: Option<&str>,
+        access_token: Option<&str>,
+    ) -> String {
+        let header = serde_json::json!({ "typ": "dpop+jwt", "alg": "EdDSA", "jwk": jwk });
+        let mut claims = serde_json::json!({
+            "jti": crate::helper::generate_token(16),
+            "htm": method,
+            "htu": format!("http://localhost:8080{path}"),
+            "iat": chrono::Utc::now().timestamp(),
+        });
+        if let Some(nonce) = nonce {
+            claims["nonce"] = serde_json::Value::from(nonce);
+        }
+        if let Some(access_token) = access_token {
+            claims["ath"] = serde_json::Value::from(super::access_token_hash(access_token));
+        }
+        crate::jose::tests::sign_with_header(pair, &header, &claims)
+    }
+}
@@ -1,37 +1,107 @@
 use std::borrow::Cow;
 
-use axum::http::StatusCode;
+use axum::http::header::WWW_AUTHENTICATE;
+use axum::http::{HeaderValue, StatusCode};
 use axum::response::IntoResponse;
 use axum::Json;
 
+// RFC 6749 §5.2: the error is a code meant for the client, the description is meant for the developer
 #[derive(Debug, serde::Serialize)]
 pub(super) struct Error {
     #[serde(skip)]
     status: StatusCode,
-    #[serde(rename = "error")]
-    message: Cow<'static, str>,
+    #[serde(skip)]
+    challenge: Option<String>,
+    #[serde(skip)]
+    dpop_nonce: Option<String>,
+    error: Cow<'static, str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_description: Option<Cow<'static, str>>,
 }
 
 impl Error {
     #[inline]
-    pub fn new(status: StatusCode, message: impl Into<Cow<'static, str>>) -> Self {
+    pub fn new(status: StatusCode, error: impl Into<Cow<'static, str>>) -> Self {
         Self {
             status,
-            message: message.into(),
+            challenge: None,
+            dpop_nonce: None,
+            error: error.into(),
+            error_description: None,
         }
     }
 
-    pub fn bad_request(message: impl Into<Cow<'static, str>>) -> Self {
-        Self::new(StatusCode::BAD_REQUEST, message)
+    pub fn bad_request(error: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, error)
     }
 
     pub fn internal() -> Self {
-        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "something went wrong")
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "server_error")
+            .with_description("something went wrong")
+    }
+
+    pub fn with_description(mut self, description: impl Into<Cow<'static, str>>) -> Self {
+        self.error_description = Some(description.into());
+        self
+    }
+
+    // the value of the WWW-Authenticate header
+    pub fn with_challenge(mut self, challenge: impl Into<String>) -> Self {
+        self.challenge = Some(challenge.into());
+        self
+    }
+
+    // RFC 9449 §8: the nonce the client should include in its next proof
+    pub fn with_dpop_nonce(mut self, nonce: impl Into<String>) -> Self {
+        self.dpop_nonce = Some(nonce.into());
+        self
+    }
+
+    // RFC 6750 §3: the error is only given when the request contained a token
+    pub fn bearer(
+        status: StatusCode,
+        error: Option<&'static str>,
+        description: &'static str,
+    ) -> Self {
+        let challenge = match error {
+            Some(code) => format!(
+                "Bearer realm=\"tekitoi\", error=\"{code}\", error_description=\"{description}\""
+            ),
+            None => String::from("Bearer realm=\"tekitoi\""),
+        };
+        Self::new(status, error.unwrap_or("invalid_request"))
+            .with_description(description)
+            .with_challenge(challenge)
+    }
+
+    // RFC 9449 §7.1: same as the bearer challenge, with the supported algorithms
+    pub fn dpop(status: StatusCode, error: &'static str, description: &'static str) -> Self {
+        let algs = crate::jose::SUPPORTED_ALGORITHMS
+            .iter()
+            .map(|alg| alg.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let challenge = format!(
+            "DPoP realm=\"tekitoi\", algs=\"{algs}\", error=\"{error}\", error_description=\"{description}\""
+        );
+        Self::new(status, error)
+            .with_description(description)
+            .with_challenge(challenge)
     }
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> axum::response::Response {
-        (self.status, Json(self)).into_response()
+        let challenge = self.challenge.clone();
+        let dpop_nonce = self.dpop_nonce.clone();
+        let mut res = (self.status, Json(self)).into_response();
+        let headers = res.headers_mut();
+        if let Some(value) = challenge.and_then(|value| HeaderValue::try_from(value).ok()) {
+            headers.insert(WWW_AUTHENTICATE, value);
+        }
+        if let Some(value) = dpop_nonce.and_then(|value| HeaderValue::try_from(value).ok()) {
+            headers.insert("DPoP-Nonce", value);
+        }
+        res
     }
 }
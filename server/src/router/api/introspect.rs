@@ -0,0 +1,226 @@
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use uuid::Uuid;
+
+use super::access_token::{session_token, AnyContentType, Confirmation, TokenType};
+use super::prelude::{
+    authenticate_client, ClientAuthenticationError, ClientAuthorization, ClientCredentials,
+};
+use crate::entity::application::ApplicationKind;
+use crate::service::keys::Keys;
+
+pub(crate) enum ResponseError {
+    ClientAuthentication(ClientAuthenticationError),
+    Database,
+}
+
+impl From<sqlx::Error> for ResponseError {
+    fn from(value: sqlx::Error) -> Self {
+        tracing::error!(message = "database interaction failed", error = %value);
+        Self::Database
+    }
+}
+
+impl From<ClientAuthenticationError> for ResponseError {
+    fn from(value: ClientAuthenticationError) -> Self {
+        Self::ClientAuthentication(value)
+    }
+}
+
+impl IntoResponse for ResponseError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::ClientAuthentication(inner) => inner.into_response(),
+            Self::Database => super::error::Error::internal().into_response(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[cfg_attr(test, derive(Debug, serde::Serialize))]
+pub(crate) struct RequestPayload {
+    #[serde(flatten)]
+    pub client: ClientCredentials,
+    // only access tokens can be introspected, so the token_type_hint is ignored
+    pub token: String,
+}
+
+#[derive(Default, serde::Serialize)]
+#[cfg_attr(test, derive(serde::Deserialize))]
+pub(crate) struct ResponsePayload {
+    active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aud: Option<String>,
+    // RFC 8693 §4.1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    act: Option<crate::entity::session::Actor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token_type: Option<TokenType>,
+    // RFC 9449 §6.2: the thumbprint of the key the token is bound to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cnf: Option<Confirmation>,
+}
+
+impl From<crate::entity::session::Entity> for ResponsePayload {
+    fn from(value: crate::entity::session::Entity) -> Self {
+        Self {
+            active: true,
+            scope: value.scope,
+            client_id: Some(value.client_id),
+            sub: value.user_id,
+            aud: value.audience,
+            act: value.actor,
+            exp: Some(value.valid_until.timestamp()),
+            iat: Some(value.created_at.timestamp()),
+            token_type: Some(match value.dpop_jkt {
+                Some(_) => TokenType::DPoP,
+                None => TokenType::Bearer,
+            }),
+            cnf: value.dpop_jkt.map(|jkt| Confirmation { jkt }),
+        }
+    }
+}
+
+pub(super) async fn handle(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Extension(keys): Extension<Keys>,
+    ClientAuthorization(basic): ClientAuthorization,
+    AnyContentType(payload): AnyContentType<RequestPayload>,
+) -> Result<Json<ResponsePayload>, ResponseError> {
+    let client = authenticate_client(database.as_ref(), basic, payload.client).await?;
+    // RFC 7662 §2.1: the caller must be authenticated, identifying as a public client isn't enough
+    if client.kind != ApplicationKind::Confidential {
+        tracing::warn!(message = "introspection requested by a public client", client_id = %client.id);
+        return Err(ClientAuthenticationError::InvalidClient.into());
+    }
+
+    let session = session_token(&keys, &payload.token)
+        .find()
+        .execute(database.as_ref())
+        .await?;
+
+    Ok(Json(session.map(ResponsePayload::from).unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
+    use uuid::Uuid;
+
+    use crate::router::api::prelude::basic_authorization;
+    use crate::service::dataset::{ALICE_ID, CLIENT_ID, CLIENT_SECRET, REDIRECT_URI};
+
+    const LOCAL_TTL: Duration = Duration::new(60, 0);
+
+    fn introspect_request(token: &str) -> Request<Body> {
+        Request::builder()
+            .uri("/api/introspect")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header(
+                "Authorization",
+                basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET),
+            )
+            .method("POST")
+            .body(Body::from(format!("token={token}")))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_describe_active_token() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let session = crate::entity::session::Create {
+            access_token: "aaaaaaaaaaaaaaaaaaa",
+            jti: None,
+            client_id: CLIENT_ID,
+            user_id: Some(ALICE_ID),
+            scope: Some("profile email"),
+            authorization_code: None,
+            dpop_jkt: None,
+            audience: None,
+            actor: None,
+            time_to_live: LOCAL_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+
+        let res = app.handle(introspect_request("aaaaaaaaaaaaaaaaaaa")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["active"], true);
+        assert_eq!(body["scope"], "profile email");
+        assert_eq!(body["client_id"], CLIENT_ID.to_string());
+        assert_eq!(body["sub"], ALICE_ID.to_string());
+        assert_eq!(body["exp"], session.valid_until.timestamp());
+        assert_eq!(body["iat"], session.created_at.timestamp());
+        assert_eq!(body["token_type"], "bearer");
+    }
+
+    #[tokio::test]
+    async fn should_describe_inactive_token() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        crate::entity::session::Create {
+            access_token: "expired",
+            jti: None,
+            client_id: CLIENT_ID,
+            user_id: Some(ALICE_ID),
+            scope: None,
+            authorization_code: None,
+            dpop_jkt: None,
+            audience: None,
+            actor: None,
+            time_to_live: Duration::ZERO,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+
+        for token in ["expired", "unknown"] {
+            let res = app.handle(introspect_request(token)).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body, serde_json::json!({ "active": false }));
+        }
+    }
+
+    #[tokio::test]
+    async fn should_reject_public_client() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let public_id = Uuid::new_v4();
+        crate::entity::application::Upsert::new(public_id, &HashSet::new(), &[REDIRECT_URI.into()])
+            .execute(app.database())
+            .await
+            .unwrap();
+
+        let req = Request::builder()
+            .uri("/api/introspect")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .method("POST")
+            .body(Body::from(format!(
+                "token=aaaaaaaaaa&client_id={public_id}"
+            )))
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
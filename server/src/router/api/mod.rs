@@ -1,14 +1,53 @@
 use axum::routing::{get, post};
 
 mod access_token;
+mod device_authorization;
+mod dpop;
 mod error;
+mod introspect;
 mod prelude;
+mod pushed_authorization;
+mod register;
+mod request_object;
+mod revoke;
 mod status;
 mod user_info;
 
+pub(super) const ACCESS_TOKEN_PATH: &str = "/access-token";
+pub(super) const DEVICE_AUTHORIZATION_PATH: &str = "/device-authorization";
+pub(super) const INTROSPECT_PATH: &str = "/introspect";
+pub(super) const PUSHED_AUTHORIZATION_PATH: &str = "/par";
+pub(super) const REGISTER_PATH: &str = "/register";
+pub(super) const REQUEST_OBJECT_PATH: &str = "/request-objects";
+pub(super) const REVOKE_PATH: &str = "/revoke";
+pub(super) const USER_INFO_PATH: &str = "/user-info";
+
+pub(super) use access_token::supported_grant_types;
+pub(crate) use access_token::ACCESS_TOKEN_TTL;
+pub(super) use pushed_authorization::REQUEST_URI_PREFIX;
+
 pub(super) fn router() -> axum::Router {
     axum::Router::new()
-        .route("/access-token", post(access_token::handle))
+        .route(ACCESS_TOKEN_PATH, post(access_token::handle))
+        .route(
+            DEVICE_AUTHORIZATION_PATH,
+            post(device_authorization::handle),
+        )
+        .route(INTROSPECT_PATH, post(introspect::handle))
+        .route(
+            PUSHED_AUTHORIZATION_PATH,
+            post(pushed_authorization::handle),
+        )
+        .route(REGISTER_PATH, post(register::create))
+        .route(
+            "/register/:client_id",
+            get(register::read)
+                .put(register::update)
+                .delete(register::delete),
+        )
+        .route(REQUEST_OBJECT_PATH, post(request_object::create))
+        .route("/request-objects/:id", get(request_object::read))
+        .route(REVOKE_PATH, post(revoke::handle))
         .route("/status", get(status::handle))
-        .route("/user-info", get(user_info::handle))
+        .route(USER_INFO_PATH, get(user_info::handle))
 }
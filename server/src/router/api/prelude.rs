@@ -1,11 +1,26 @@
+use std::collections::HashSet;
+
 use axum::http::StatusCode;
-use axum_extra::headers::authorization::Bearer;
+use axum::response::IntoResponse;
+use axum_extra::headers::authorization::Basic;
 use axum_extra::headers::Authorization;
 use axum_extra::TypedHeader;
+use uuid::Uuid;
 
 use super::error::Error;
+use crate::entity::application::{ApplicationKind, AuthMethod};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum TokenScheme {
+    Bearer,
+    // RFC 9449 §7.1
+    DPoP,
+}
 
-pub(super) struct AuthorizationToken(pub Bearer);
+pub(super) struct AuthorizationToken {
+    pub scheme: TokenScheme,
+    pub token: String,
+}
 
 #[axum::async_trait]
 impl<S> axum::extract::FromRequestParts<S> for AuthorizationToken
@@ -16,17 +31,182 @@ where
 
     async fn from_request_parts(
         parts: &mut axum::http::request::Parts,
-        state: &S,
+        _state: &S,
     ) -> Result<Self, Self::Rejection> {
-        if let Ok(TypedHeader(Authorization(inner))) =
-            TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state).await
-        {
-            Ok(AuthorizationToken(inner))
-        } else {
-            Err(Error::new(
+        let found = parts
+            .headers
+            .get(axum::http::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once(' '))
+            .and_then(|(scheme, token)| {
+                let token = token.trim();
+                if token.is_empty() {
+                    None
+                } else if scheme.eq_ignore_ascii_case("bearer") {
+                    Some((TokenScheme::Bearer, token))
+                } else if scheme.eq_ignore_ascii_case("dpop") {
+                    Some((TokenScheme::DPoP, token))
+                } else {
+                    None
+                }
+            });
+        match found {
+            Some((scheme, token)) => Ok(AuthorizationToken {
+                scheme,
+                token: token.to_string(),
+            }),
+            None => Err(Error::bearer(
                 StatusCode::UNAUTHORIZED,
+                None,
                 "unable to get authorization token",
-            ))
+            )),
         }
     }
 }
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+pub(crate) struct ClientCredentials {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+}
+
+pub(super) struct ClientAuthorization(pub Option<ClientCredentials>);
+
+fn decode_basic_value(value: &str) -> Option<String> {
+    // RFC 6749 §2.3.1: the values are form encoded before being placed in the header
+    let value = value.replace('+', " ");
+    percent_encoding::percent_decode_str(&value)
+        .decode_utf8()
+        .ok()
+        .map(String::from)
+}
+
+#[axum::async_trait]
+impl<S> axum::extract::FromRequestParts<S> for ClientAuthorization
+where
+    S: Send + Sync,
+{
+    type Rejection = ClientAuthenticationError;
+
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        if !parts
+            .headers
+            .contains_key(axum::http::header::AUTHORIZATION)
+        {
+            return Ok(Self(None));
+        }
+        let TypedHeader(Authorization(inner)) =
+            TypedHeader::<Authorization<Basic>>::from_request_parts(parts, state)
+                .await
+                .map_err(|_| ClientAuthenticationError::InvalidClient)?;
+        let client_id =
+            decode_basic_value(inner.username()).ok_or(ClientAuthenticationError::InvalidClient)?;
+        let client_secret =
+            decode_basic_value(inner.password()).ok_or(ClientAuthenticationError::InvalidClient)?;
+        Ok(Self(Some(ClientCredentials {
+            client_id: Some(client_id),
+            client_secret: Some(client_secret),
+        })))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum ClientAuthenticationError {
+    InvalidClient,
+    Database,
+}
+
+impl From<sqlx::Error> for ClientAuthenticationError {
+    fn from(value: sqlx::Error) -> Self {
+        tracing::error!(message = "database interaction failed", error = %value);
+        Self::Database
+    }
+}
+
+impl IntoResponse for ClientAuthenticationError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::InvalidClient => Error::new(StatusCode::UNAUTHORIZED, "invalid_client")
+                .with_description("client authentication failed")
+                .with_challenge("Basic realm=\"tekitoi\"")
+                .into_response(),
+            Self::Database => Error::internal().into_response(),
+        }
+    }
+}
+
+fn check_secret(kind: ApplicationKind, secrets: &HashSet<String>, provided: Option<&str>) -> bool {
+    match (kind, provided) {
+        (_, Some(secret)) => secrets.contains(secret),
+        // public applications only need to identify themselves
+        (ApplicationKind::Public, None) => true,
+        (ApplicationKind::Confidential, None) => false,
+    }
+}
+
+pub(super) async fn authenticate_client<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+    executor: E,
+    header: Option<ClientCredentials>,
+    body: ClientCredentials,
+) -> Result<crate::entity::application::Entity, ClientAuthenticationError> {
+    let method = match (&header, &body.client_secret) {
+        (Some(_), _) => AuthMethod::ClientSecretBasic,
+        (None, Some(_)) => AuthMethod::ClientSecretPost,
+        (None, None) => AuthMethod::None,
+    };
+    let credentials = match header {
+        Some(header) => {
+            // RFC 6749 §2.3: the client must not use more than one authentication method
+            if body.client_secret.is_some()
+                || body
+                    .client_id
+                    .as_ref()
+                    .is_some_and(|id| header.client_id.as_ref() != Some(id))
+            {
+                tracing::warn!("client provided conflicting authentication methods");
+                return Err(ClientAuthenticationError::InvalidClient);
+            }
+            header
+        }
+        None => body,
+    };
+    let client_id = credentials
+        .client_id
+        .as_deref()
+        .and_then(|value| Uuid::parse_str(value).ok())
+        .ok_or(ClientAuthenticationError::InvalidClient)?;
+    let app = crate::entity::application::FindById::new(client_id)
+        .execute(executor)
+        .await?;
+    let Some(app) = app else {
+        tracing::warn!(message = "client not found", client_id = %client_id);
+        return Err(ClientAuthenticationError::InvalidClient);
+    };
+    if !check_secret(app.kind, &app.secrets, credentials.client_secret.as_deref()) {
+        tracing::warn!(message = "invalid client secret", client_id = %client_id);
+        return Err(ClientAuthenticationError::InvalidClient);
+    }
+    // RFC 7591 §2: the registered applications only authenticate with the declared method
+    if !app.allows_auth_method(method) {
+        tracing::warn!(message = "unexpected authentication method", client_id = %client_id, method = method.as_str());
+        return Err(ClientAuthenticationError::InvalidClient);
+    }
+    Ok(app)
+}
+
+#[cfg(test)]
+pub(crate) fn basic_authorization(client_id: &str, client_secret: &str) -> String {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    format!(
+        "Basic {}",
+        STANDARD.encode(format!("{client_id}:{client_secret}"))
+    )
+}
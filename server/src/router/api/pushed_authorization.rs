@@ -0,0 +1,358 @@
+use std::borrow::Cow;
+use std::time::Duration;
+
+use axum::extract::RawForm;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+
+use super::prelude::{
+    authenticate_client, ClientAuthenticationError, ClientAuthorization, ClientCredentials,
+};
+use crate::router::ui::authorize::{
+    merge_request_object, parse_request, BaseQueryParams, RequestObjectError, RequestObjectParams,
+};
+use crate::router::ui::client_error::ErrorCode;
+use crate::service::base_url::BaseUrl;
+
+// the request uri has to remain valid until the user is done with the login page
+pub(super) const PUSHED_AUTHORIZATION_TTL: Duration = Duration::new(600, 0);
+// RFC 9126 §2.2: the request uri is a reference, not a locator
+pub(in crate::router) const REQUEST_URI_PREFIX: &str = "urn:ietf:params:oauth:request_uri:";
+
+pub(crate) enum ResponseError {
+    ClientAuthentication(ClientAuthenticationError),
+    InvalidRequest(ErrorCode, Cow<'static, str>),
+    InvalidRequestObject,
+    Database,
+}
+
+impl From<sqlx::Error> for ResponseError {
+    fn from(value: sqlx::Error) -> Self {
+        tracing::error!(message = "database interaction failed", error = %value);
+        Self::Database
+    }
+}
+
+impl From<ClientAuthenticationError> for ResponseError {
+    fn from(value: ClientAuthenticationError) -> Self {
+        Self::ClientAuthentication(value)
+    }
+}
+
+impl IntoResponse for ResponseError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::ClientAuthentication(inner) => inner.into_response(),
+            Self::InvalidRequest(code, description) => {
+                super::error::Error::bad_request(code.as_str())
+                    .with_description(description)
+                    .into_response()
+            }
+            Self::InvalidRequestObject => {
+                super::error::Error::bad_request("invalid_request_object")
+                    .with_description("the signed request object is invalid")
+                    .into_response()
+            }
+            Self::Database => super::error::Error::internal().into_response(),
+        }
+    }
+}
+
+fn invalid_request(code: ErrorCode, description: Cow<'static, str>) -> ResponseError {
+    tracing::debug!(message = "invalid pushed authorization request", description = %description);
+    ResponseError::InvalidRequest(code, description)
+}
+
+#[derive(serde::Serialize)]
+#[cfg_attr(test, derive(serde::Deserialize))]
+pub(crate) struct ResponsePayload {
+    request_uri: String,
+    expires_in: u64,
+}
+
+pub(super) async fn handle(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Extension(base_url): Extension<BaseUrl>,
+    ClientAuthorization(basic): ClientAuthorization,
+    RawForm(body): RawForm,
+) -> Result<(StatusCode, Json<ResponsePayload>), ResponseError> {
+    let body = String::from_utf8_lossy(&body);
+    let credentials: ClientCredentials = serde_urlencoded::from_str(&body)
+        .map_err(|err| invalid_request(ErrorCode::InvalidRequest, err.to_string().into()))?;
+    let client = authenticate_client(database.as_ref(), basic, credentials).await?;
+
+    // RFC 9126 §3: the parameters can also be pushed as a signed request object
+    let body = match serde_urlencoded::from_str::<RequestObjectParams>(&body) {
+        Ok(signed) => {
+            let mut conn = database.as_ref().acquire().await?;
+            merge_request_object(&mut conn, &client, &base_url, &signed.request)
+                .await
+                .map_err(|err| match err {
+                    RequestObjectError::Database => ResponseError::Database,
+                    other => {
+                        tracing::debug!(message = "invalid request object", source = %other);
+                        ResponseError::InvalidRequestObject
+                    }
+                })?
+                .into()
+        }
+        Err(_) => body,
+    };
+
+    let mut params: BaseQueryParams =
+        parse_request(&body).map_err(|(code, description)| invalid_request(code, description))?;
+    if params.client_id != client.id {
+        return Err(invalid_request(
+            ErrorCode::InvalidRequest,
+            "client_id doesn't match the authenticated client".into(),
+        ));
+    }
+    if !client.accepts_redirect_uri(&params.redirect_uri) {
+        return Err(invalid_request(
+            ErrorCode::InvalidRequest,
+            "redirect_uri is not registered".into(),
+        ));
+    }
+    params.code_challenge_method = client
+        .check_code_challenge(
+            params.code_challenge.as_deref(),
+            params.code_challenge_method,
+        )
+        .map_err(|err| invalid_request(ErrorCode::InvalidRequest, err.to_string().into()))?;
+    params.scope = client
+        .grant_scope(params.scope.as_deref())
+        .map_err(|err| invalid_request(ErrorCode::InvalidScope, err.to_string().into()))?;
+    client
+        .grant_resource(params.resource.as_deref())
+        .map_err(|err| invalid_request(ErrorCode::InvalidTarget, err.to_string().into()))?;
+
+    let parameters = serde_urlencoded::to_string(&params).map_err(|err| {
+        tracing::error!(message = "unable to encode pushed parameters", source = %err);
+        ResponseError::Database
+    })?;
+    let request_uri = format!("{REQUEST_URI_PREFIX}{}", crate::helper::generate_token(32));
+    crate::entity::pushed_authorization::Create {
+        request_uri: request_uri.as_str(),
+        client_id: client.id,
+        parameters: parameters.as_str(),
+        time_to_live: PUSHED_AUTHORIZATION_TTL,
+    }
+    .execute(database.as_ref())
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ResponsePayload {
+            request_uri,
+            expires_in: PUSHED_AUTHORIZATION_TTL.as_secs(),
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
+
+    use crate::router::api::prelude::basic_authorization;
+    use crate::service::dataset::{ALICE_ID, CLIENT_ID, CLIENT_SECRET, REDIRECT_URI};
+
+    fn push_request(params: &[(&str, &str)], authorization: Option<String>) -> Request<Body> {
+        let builder = Request::builder()
+            .uri("/api/par")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .method("POST");
+        let builder = match authorization {
+            Some(value) => builder.header("Authorization", value),
+            None => builder,
+        };
+        builder
+            .body(Body::from(serde_urlencoded::to_string(params).unwrap()))
+            .unwrap()
+    }
+
+    fn valid_params(client_id: &str) -> Vec<(&str, &str)> {
+        vec![
+            ("client_id", client_id),
+            ("redirect_uri", REDIRECT_URI),
+            ("state", "the-state"),
+            ("code_challenge", "code-challenge"),
+            ("code_challenge_method", "plain"),
+            ("response_type", "code"),
+        ]
+    }
+
+    fn get(uri: String) -> Request<Body> {
+        Request::builder()
+            .uri(uri)
+            .method("GET")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    async fn push(app: &crate::app::Application) -> String {
+        let client_id = CLIENT_ID.to_string();
+        let res = app
+            .handle(push_request(
+                &valid_params(&client_id),
+                Some(basic_authorization(&client_id, CLIENT_SECRET)),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: super::ResponsePayload = serde_json::from_slice(&body).unwrap();
+        assert!(body.request_uri.starts_with(super::REQUEST_URI_PREFIX));
+        assert_eq!(body.expires_in, 600);
+        body.request_uri
+    }
+
+    fn pushed_query(request_uri: &str) -> String {
+        serde_urlencoded::to_string([
+            ("client_id", CLIENT_ID.to_string().as_str()),
+            ("request_uri", request_uri),
+        ])
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_authorize_with_request_uri() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+
+        let request_uri = push(&app).await;
+        let query = pushed_query(&request_uri);
+
+        let res = app.handle(get(format!("/authorize?{query}"))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let user = ALICE_ID.to_string();
+        let res = app
+            .handle(get(format!(
+                "/authorize/profiles/login?{query}&user={user}"
+            )))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains(REDIRECT_URI));
+        assert!(body.contains("state=the-state"));
+
+        // the request uri can only be used once
+        let res = app.handle(get(format!("/authorize?{query}"))).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_requests() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let client_id = CLIENT_ID.to_string();
+
+        let res = app
+            .handle(push_request(&valid_params(&client_id), None))
+            .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let mut params = valid_params(&client_id);
+        params[1] = ("redirect_uri", "http://attacker/redirect");
+        let res = app
+            .handle(push_request(
+                &params,
+                Some(basic_authorization(&client_id, CLIENT_SECRET)),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_request");
+
+        let mut params = valid_params(&client_id);
+        params.push(("scope", "admin"));
+        let res = app
+            .handle(push_request(
+                &params,
+                Some(basic_authorization(&client_id, CLIENT_SECRET)),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_scope");
+    }
+
+    #[tokio::test]
+    async fn should_require_pushed_authorization() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        sqlx::query("update applications set require_pushed_authorization = true where id = $1")
+            .bind(CLIENT_ID)
+            .execute(app.database())
+            .await
+            .unwrap();
+        let client_id = CLIENT_ID.to_string();
+
+        let query = serde_urlencoded::to_string(valid_params(&client_id)).unwrap();
+        let res = app.handle(get(format!("/authorize?{query}"))).await;
+        assert_eq!(res.status(), StatusCode::SEE_OTHER);
+        let location = res.headers().get("Location").unwrap().to_str().unwrap();
+        assert!(location.contains("error=invalid_request"));
+
+        let request_uri = push(&app).await;
+        let res = app
+            .handle(get(format!("/authorize?{}", pushed_query(&request_uri))))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_push_signed_request_object() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let client_id = CLIENT_ID.to_string();
+
+        let (pair, jwk) = crate::jose::tests::ed25519_key("client-key");
+        let jwks = crate::jose::JwkSet { keys: vec![jwk] };
+        sqlx::query("update applications set jwks = $2 where id = $1")
+            .bind(CLIENT_ID)
+            .bind(serde_json::to_string(&jwks).unwrap())
+            .execute(app.database())
+            .await
+            .unwrap();
+
+        let mut claims: serde_json::Map<String, serde_json::Value> = valid_params(&client_id)
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.into()))
+            .collect();
+        claims.insert("scope".into(), "email".into());
+        claims.insert("exp".into(), (chrono::Utc::now().timestamp() + 60).into());
+        let request = crate::jose::tests::sign(&pair, "client-key", &claims.into());
+        let res = app
+            .handle(push_request(
+                &[("client_id", &client_id), ("request", &request)],
+                Some(basic_authorization(&client_id, CLIENT_SECRET)),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: super::ResponsePayload = serde_json::from_slice(&body).unwrap();
+        let pushed = crate::entity::pushed_authorization::FindByRequestUri::new(&body.request_uri)
+            .execute(app.database())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(pushed.parameters.contains("scope=email"));
+
+        let res = app
+            .handle(push_request(
+                &[("client_id", &client_id), ("request", "not-a-token")],
+                Some(basic_authorization(&client_id, CLIENT_SECRET)),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_request_object");
+    }
+}
@@ -0,0 +1,658 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use uuid::Uuid;
+
+use super::prelude::{AuthorizationToken, TokenScheme};
+use crate::entity::application::{ApplicationKind, AuthMethod};
+use crate::jose::JwkSet;
+use crate::service::base_url::BaseUrl;
+use crate::service::registration::InitialAccessToken;
+
+#[derive(Debug)]
+pub(crate) enum ResponseError {
+    InvalidToken,
+    InvalidRedirectUri(&'static str),
+    InvalidClientMetadata(Cow<'static, str>),
+    Database,
+}
+
+impl From<sqlx::Error> for ResponseError {
+    fn from(value: sqlx::Error) -> Self {
+        tracing::error!(message = "database interaction failed", error = %value);
+        Self::Database
+    }
+}
+
+impl IntoResponse for ResponseError {
+    fn into_response(self) -> axum::response::Response {
+        // RFC 7591 §3.2.2
+        match self {
+            Self::InvalidToken => super::error::Error::bearer(
+                StatusCode::UNAUTHORIZED,
+                Some("invalid_token"),
+                "the access token is invalid or doesn't give access to this application",
+            ),
+            Self::InvalidRedirectUri(description) => {
+                super::error::Error::bad_request("invalid_redirect_uri")
+                    .with_description(description)
+            }
+            Self::InvalidClientMetadata(description) => {
+                super::error::Error::bad_request("invalid_client_metadata")
+                    .with_description(description)
+            }
+            Self::Database => super::error::Error::internal(),
+        }
+        .into_response()
+    }
+}
+
+// RFC 7591 §2: the fields that are not supported are ignored
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct ClientMetadata {
+    // RFC 7592 §2.2: only given when updating the application
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    #[serde(default = "default_auth_method")]
+    pub token_endpoint_auth_method: AuthMethod,
+    #[serde(default = "default_grant_types")]
+    pub grant_types: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    // the scopes granted when the client doesn't request any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_scope: Option<String>,
+    #[serde(default)]
+    pub jwks: JwkSet,
+    #[serde(default)]
+    pub require_consent: bool,
+    // RFC 9126 §6
+    #[serde(default)]
+    pub require_pushed_authorization_requests: bool,
+    #[serde(default = "enabled")]
+    pub require_pkce: bool,
+    #[serde(default = "enabled")]
+    pub allow_plain_pkce: bool,
+    // RFC 8707: the resources the tokens can be requested for
+    #[serde(default)]
+    pub resources: Vec<String>,
+    // RFC 9068
+    #[serde(default)]
+    pub jwt_access_token: bool,
+}
+
+const fn default_auth_method() -> AuthMethod {
+    AuthMethod::ClientSecretBasic
+}
+
+// RFC 7591 §2 defaults to the authorization code grant, which needs a user, so the
+// registered applications default to the client credentials grant
+fn default_grant_types() -> Vec<String> {
+    vec!["client_credentials".into()]
+}
+
+// the registered applications have no provider, so no user can sign in to them
+const USER_GRANT_TYPES: [&str; 3] = [
+    "authorization_code",
+    "password",
+    "urn:ietf:params:oauth:grant-type:device_code",
+];
+
+const fn enabled() -> bool {
+    true
+}
+
+fn split_scope(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(String::from)
+        .collect()
+}
+
+impl ClientMetadata {
+    fn kind(&self) -> ApplicationKind {
+        match self.token_endpoint_auth_method {
+            AuthMethod::None => ApplicationKind::Public,
+            _ => ApplicationKind::Confidential,
+        }
+    }
+
+    // the same rules as the applications defined in the configuration
+    fn validate(&self) -> Result<(), ResponseError> {
+        // RFC 6749 §3.1.2: the redirect uri is absolute and has no fragment
+        if self
+            .redirect_uris
+            .iter()
+            .any(|uri| !uri.contains("://") || uri.contains('#'))
+        {
+            return Err(ResponseError::InvalidRedirectUri(
+                "redirect uris must be absolute and without fragment",
+            ));
+        }
+        let supported = super::supported_grant_types(true, true);
+        if let Some(grant_type) = self
+            .grant_types
+            .iter()
+            .find(|item| !supported.contains(&item.as_str()))
+        {
+            return Err(ResponseError::InvalidClientMetadata(
+                format!("grant type {grant_type:?} not supported").into(),
+            ));
+        }
+        if let Some(grant_type) = self
+            .grant_types
+            .iter()
+            .find(|item| USER_GRANT_TYPES.contains(&item.as_str()))
+        {
+            return Err(ResponseError::InvalidClientMetadata(
+                format!(
+                    "grant type {grant_type:?} requires a user, the registered applications have no provider"
+                )
+                .into(),
+            ));
+        }
+        if self.kind() == ApplicationKind::Public && !self.require_pkce {
+            return Err(ResponseError::InvalidClientMetadata(
+                "public applications must require PKCE".into(),
+            ));
+        }
+        let scopes = split_scope(self.scope.as_deref());
+        if let Some(scope) = split_scope(self.default_scope.as_deref())
+            .into_iter()
+            .find(|scope| !scopes.contains(scope))
+        {
+            return Err(ResponseError::InvalidClientMetadata(
+                format!("default scope {scope:?} is not declared").into(),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn save<'c, E: sqlx::Executor<'c, Database = sqlx::Sqlite>>(
+        &self,
+        executor: E,
+        id: Uuid,
+        secrets: &HashSet<String>,
+    ) -> Result<crate::entity::application::Entity, sqlx::Error> {
+        crate::entity::application::Upsert::new(id, secrets, &self.redirect_uris)
+            .with_kind(self.kind())
+            .with_scopes(
+                &split_scope(self.scope.as_deref()),
+                &split_scope(self.default_scope.as_deref()),
+            )
+            .with_require_consent(self.require_consent)
+            .with_require_pushed_authorization(self.require_pushed_authorization_requests)
+            .with_jwks(&self.jwks)
+            .with_pkce(self.require_pkce, self.allow_plain_pkce)
+            .with_resources(&self.resources)
+            .with_jwt_access_token(self.jwt_access_token)
+            .with_token_exchange(
+                self.grant_types
+                    .iter()
+                    .any(|item| item == "urn:ietf:params:oauth:grant-type:token-exchange"),
+            )
+            .with_registered_grant_types(&self.grant_types)
+            .with_token_endpoint_auth_method(self.token_endpoint_auth_method)
+            .execute(executor)
+            .await
+    }
+}
+
+// RFC 7591 §3.2.1: the metadata are returned as registered
+#[derive(serde::Serialize)]
+#[cfg_attr(test, derive(Debug, serde::Deserialize))]
+pub(crate) struct ClientInformation {
+    pub client_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    pub client_id_issued_at: i64,
+    // the secrets don't expire
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret_expires_at: Option<i64>,
+    // only its hash is stored, so it's only given at registration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registration_access_token: Option<String>,
+    pub registration_client_uri: String,
+    #[serde(flatten)]
+    pub metadata: ClientMetadata,
+}
+
+impl ClientInformation {
+    fn new(
+        base_url: &BaseUrl,
+        app: crate::entity::application::Entity,
+        registration: crate::entity::registration::Entity,
+        registration_access_token: Option<String>,
+    ) -> Result<Self, ResponseError> {
+        let metadata = serde_json::from_str(&registration.metadata).map_err(|err| {
+            tracing::error!(message = "unable to decode registered metadata", source = %err);
+            ResponseError::Database
+        })?;
+        let client_secret = app.secrets.into_iter().next();
+        Ok(Self {
+            client_id: app.id,
+            client_secret_expires_at: client_secret.as_ref().map(|_| 0),
+            client_secret,
+            client_id_issued_at: registration.created_at.timestamp(),
+            registration_access_token,
+            registration_client_uri: base_url.join(&format!(
+                "{}{}/{}",
+                crate::router::API_PATH,
+                super::REGISTER_PATH,
+                app.id
+            )),
+            metadata,
+        })
+    }
+}
+
+fn bearer_token(token: Option<AuthorizationToken>) -> Option<String> {
+    token
+        .filter(|value| value.scheme == TokenScheme::Bearer)
+        .map(|value| value.token)
+}
+
+fn parse_metadata(
+    payload: Result<Json<ClientMetadata>, JsonRejection>,
+) -> Result<ClientMetadata, ResponseError> {
+    let Json(metadata) = payload.map_err(|err| {
+        tracing::debug!(message = "unable to decode client metadata", source = %err);
+        ResponseError::InvalidClientMetadata("unable to decode client metadata".into())
+    })?;
+    metadata.validate()?;
+    Ok(metadata)
+}
+
+// the identifier and the secret aren't part of the metadata kept as registered
+fn encode_registered(mut metadata: ClientMetadata) -> Result<String, ResponseError> {
+    metadata.client_id = None;
+    metadata.client_secret = None;
+    serde_json::to_string(&metadata).map_err(|err| {
+        tracing::error!(message = "unable to encode registered metadata", source = %err);
+        ResponseError::Database
+    })
+}
+
+// RFC 7592 §3: the application and its registration access token
+async fn find_registration(
+    conn: &mut sqlx::SqliteConnection,
+    client_id: Uuid,
+    token: Option<AuthorizationToken>,
+) -> Result<
+    (
+        crate::entity::application::Entity,
+        crate::entity::registration::Entity,
+    ),
+    ResponseError,
+> {
+    let token = bearer_token(token).ok_or(ResponseError::InvalidToken)?;
+    let registration = crate::entity::registration::FindByAccessToken::new(client_id, &token)
+        .execute(&mut *conn)
+        .await?
+        .ok_or(ResponseError::InvalidToken)?;
+    let app = crate::entity::application::FindById::new(client_id)
+        .execute(&mut *conn)
+        .await?
+        .ok_or(ResponseError::InvalidToken)?;
+    Ok((app, registration))
+}
+
+fn generate_secrets(kind: ApplicationKind) -> HashSet<String> {
+    match kind {
+        ApplicationKind::Public => HashSet::new(),
+        ApplicationKind::Confidential => HashSet::from([crate::helper::generate_token(42)]),
+    }
+}
+
+pub(super) async fn create(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Extension(base_url): Extension<BaseUrl>,
+    Extension(initial_access_token): Extension<InitialAccessToken>,
+    token: Option<AuthorizationToken>,
+    payload: Result<Json<ClientMetadata>, JsonRejection>,
+) -> Result<(StatusCode, Json<ClientInformation>), ResponseError> {
+    if !initial_access_token.accepts(bearer_token(token).as_deref()) {
+        tracing::warn!(message = "registration attempted without a valid initial access token");
+        return Err(ResponseError::InvalidToken);
+    }
+    let metadata = parse_metadata(payload)?;
+
+    let mut tx = database.as_ref().begin().await?;
+    let app = metadata
+        .save(&mut *tx, Uuid::new_v4(), &generate_secrets(metadata.kind()))
+        .await?;
+    let access_token = crate::helper::generate_token(42);
+    let registration = crate::entity::registration::Create {
+        client_id: app.id,
+        access_token: &access_token,
+        metadata: &encode_registered(metadata)?,
+    }
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ClientInformation::new(
+            &base_url,
+            app,
+            registration,
+            Some(access_token),
+        )?),
+    ))
+}
+
+pub(super) async fn read(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Extension(base_url): Extension<BaseUrl>,
+    Path(client_id): Path<Uuid>,
+    token: Option<AuthorizationToken>,
+) -> Result<Json<ClientInformation>, ResponseError> {
+    let mut conn = database.as_ref().acquire().await?;
+    let (app, registration) = find_registration(&mut conn, client_id, token).await?;
+    Ok(Json(ClientInformation::new(
+        &base_url,
+        app,
+        registration,
+        None,
+    )?))
+}
+
+pub(super) async fn update(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Extension(base_url): Extension<BaseUrl>,
+    Path(client_id): Path<Uuid>,
+    token: Option<AuthorizationToken>,
+    payload: Result<Json<ClientMetadata>, JsonRejection>,
+) -> Result<Json<ClientInformation>, ResponseError> {
+    let mut tx = database.as_ref().begin().await?;
+    let (app, _) = find_registration(&mut tx, client_id, token).await?;
+    let metadata = parse_metadata(payload)?;
+
+    // RFC 7592 §2.2: the identifier and the secret can't be changed
+    if metadata.client_id != Some(app.id) {
+        return Err(ResponseError::InvalidClientMetadata(
+            "client_id doesn't match the application".into(),
+        ));
+    }
+    if metadata
+        .client_secret
+        .as_ref()
+        .is_some_and(|secret| !app.secrets.contains(secret))
+    {
+        return Err(ResponseError::InvalidClientMetadata(
+            "client_secret doesn't match the application".into(),
+        ));
+    }
+    let secrets = if metadata.kind() == app.kind {
+        app.secrets
+    } else {
+        generate_secrets(metadata.kind())
+    };
+
+    let app = metadata.save(&mut *tx, app.id, &secrets).await?;
+    let registration =
+        crate::entity::registration::UpdateMetadata::new(app.id, &encode_registered(metadata)?)
+            .execute(&mut *tx)
+            .await?
+            .ok_or(ResponseError::InvalidToken)?;
+    tx.commit().await?;
+
+    Ok(Json(ClientInformation::new(
+        &base_url,
+        app,
+        registration,
+        None,
+    )?))
+}
+
+pub(super) async fn delete(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Path(client_id): Path<Uuid>,
+    token: Option<AuthorizationToken>,
+) -> Result<StatusCode, ResponseError> {
+    let mut tx = database.as_ref().begin().await?;
+    let (app, _) = find_registration(&mut tx, client_id, token).await?;
+    crate::entity::application::Delete::new(app.id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
+
+    use super::ClientInformation;
+    use crate::router::api::prelude::basic_authorization;
+
+    fn request(
+        method: &str,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<serde_json::Value>,
+    ) -> Request<Body> {
+        let builder = Request::builder().uri(uri).method(method);
+        let builder = match token {
+            Some(value) => builder.header("Authorization", format!("Bearer {value}")),
+            None => builder,
+        };
+        match body {
+            Some(value) => builder
+                .header("Content-Type", "application/json")
+                .body(Body::from(value.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        }
+    }
+
+    async fn json_body<T: serde::de::DeserializeOwned>(res: axum::response::Response) -> T {
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn metadata() -> serde_json::Value {
+        serde_json::json!({
+            "redirect_uris": ["http://preview/callback"],
+            "grant_types": ["client_credentials"],
+            "token_endpoint_auth_method": "client_secret_post",
+            "scope": "profile service",
+            "default_scope": "profile",
+        })
+    }
+
+    #[tokio::test]
+    async fn should_register_and_manage_client() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+
+        let res = app
+            .handle(request("POST", "/api/register", None, Some(metadata())))
+            .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let created: ClientInformation = json_body(res).await;
+        let secret = created.client_secret.clone().unwrap();
+        assert_eq!(created.client_secret_expires_at, Some(0));
+        assert_eq!(
+            created.registration_client_uri,
+            format!("http://localhost:8080/api/register/{}", created.client_id)
+        );
+        assert_eq!(created.metadata.scope.as_deref(), Some("profile service"));
+        // the metadata are returned as registered
+        assert_eq!(created.metadata.grant_types, vec!["client_credentials"]);
+        assert_eq!(
+            created.metadata.token_endpoint_auth_method,
+            super::AuthMethod::ClientSecretPost
+        );
+        // only the hash of the registration access token is stored
+        let token = created.registration_access_token.clone().unwrap();
+        let stored: (i64,) =
+            sqlx::query_as("select count(*) from registrations where access_token_hash = $1")
+                .bind(&token)
+                .fetch_one(app.database())
+                .await
+                .unwrap();
+        assert_eq!(stored.0, 0);
+
+        // the registered application can be used right away, as registered
+        let client_id = created.client_id.to_string();
+        let token_request = |body: String| {
+            Request::builder()
+                .uri("/api/access-token")
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .method("POST")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let res = app
+            .handle(token_request(format!(
+                "grant_type=client_credentials&scope=service&client_id={client_id}&client_secret={secret}"
+            )))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let mut basic = token_request("grant_type=client_credentials".into());
+        basic.headers_mut().insert(
+            "Authorization",
+            basic_authorization(&client_id, &secret).parse().unwrap(),
+        );
+        let res = app.handle(basic).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = app
+            .handle(token_request(format!(
+                "grant_type=refresh_token&refresh_token=unknown&client_id={client_id}&client_secret={secret}"
+            )))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = json_body(res).await;
+        assert_eq!(body["error"], "unauthorized_client");
+
+        let uri = format!("/api/register/{}", created.client_id);
+        let token = token.as_str();
+        let res = app.handle(request("GET", &uri, Some(token), None)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let read: ClientInformation = json_body(res).await;
+        assert_eq!(read.client_secret.as_deref(), Some(secret.as_str()));
+        assert!(read.registration_access_token.is_none());
+        assert_eq!(read.metadata.grant_types, vec!["client_credentials"]);
+
+        let res = app
+            .handle(request("GET", &uri, Some("unknown"), None))
+            .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let mut update = metadata();
+        update["client_id"] = serde_json::Value::from(created.client_id.to_string());
+        update["scope"] = serde_json::Value::from("profile");
+        let res = app
+            .handle(request("PUT", &uri, Some(token), Some(update)))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let updated: ClientInformation = json_body(res).await;
+        assert_eq!(updated.metadata.scope.as_deref(), Some("profile"));
+        assert_eq!(updated.client_secret.as_deref(), Some(secret.as_str()));
+
+        let res = app.handle(request("DELETE", &uri, Some(token), None)).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let found = crate::entity::application::FindById::new(created.client_id)
+            .execute(app.database())
+            .await
+            .unwrap();
+        assert!(found.is_none());
+        let res = app.handle(request("GET", &uri, Some(token), None)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn should_require_initial_access_token() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test()
+            .await
+            .with_initial_access_token("initial");
+
+        let res = app
+            .handle(request("POST", "/api/register", None, Some(metadata())))
+            .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = app
+            .handle(request(
+                "POST",
+                "/api/register",
+                Some("wrong"),
+                Some(metadata()),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let res = app
+            .handle(request(
+                "POST",
+                "/api/register",
+                Some("initial"),
+                Some(metadata()),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_metadata() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+
+        let mut public = metadata();
+        public["token_endpoint_auth_method"] = serde_json::Value::from("none");
+        public["require_pkce"] = serde_json::Value::from(false);
+        let res = app
+            .handle(request("POST", "/api/register", None, Some(public)))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = json_body(res).await;
+        assert_eq!(body["error"], "invalid_client_metadata");
+
+        let mut fragment = metadata();
+        fragment["redirect_uris"] = serde_json::json!(["http://preview/callback#fragment"]);
+        let res = app
+            .handle(request("POST", "/api/register", None, Some(fragment)))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = json_body(res).await;
+        assert_eq!(body["error"], "invalid_redirect_uri");
+
+        // no user can sign in to a registered application
+        for grant_types in [
+            serde_json::json!(["authorization_code"]),
+            serde_json::json!(["client_credentials", "password"]),
+        ] {
+            let mut user = metadata();
+            user["grant_types"] = grant_types;
+            let res = app
+                .handle(request("POST", "/api/register", None, Some(user)))
+                .await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+            let body: serde_json::Value = json_body(res).await;
+            assert_eq!(body["error"], "invalid_client_metadata");
+        }
+        // without grant types, the client credentials grant is registered
+        let mut default = metadata();
+        default.as_object_mut().unwrap().remove("grant_types");
+        let res = app
+            .handle(request("POST", "/api/register", None, Some(default)))
+            .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let created: ClientInformation = json_body(res).await;
+        assert_eq!(created.metadata.grant_types, vec!["client_credentials"]);
+    }
+}
@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use axum::extract::Path;
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+
+use super::access_token::AnyContentType;
+use super::prelude::{
+    authenticate_client, ClientAuthenticationError, ClientAuthorization, ClientCredentials,
+};
+use crate::service::base_url::BaseUrl;
+
+// the request object has to remain available until the user is done with the login page
+const REQUEST_OBJECT_TTL: Duration = Duration::new(600, 0);
+// RFC 9101 §4: media type of the request objects
+const REQUEST_OBJECT_CONTENT_TYPE: &str = "application/oauth-authz-req+jwt";
+
+pub(crate) enum ResponseError {
+    ClientAuthentication(ClientAuthenticationError),
+    InvalidRequestObject,
+    NotFound,
+    Database,
+}
+
+impl From<sqlx::Error> for ResponseError {
+    fn from(value: sqlx::Error) -> Self {
+        tracing::error!(message = "database interaction failed", error = %value);
+        Self::Database
+    }
+}
+
+impl From<ClientAuthenticationError> for ResponseError {
+    fn from(value: ClientAuthenticationError) -> Self {
+        Self::ClientAuthentication(value)
+    }
+}
+
+impl IntoResponse for ResponseError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::ClientAuthentication(inner) => inner.into_response(),
+            Self::InvalidRequestObject => {
+                super::error::Error::bad_request("invalid_request_object")
+                    .with_description("the signed request object is invalid")
+                    .into_response()
+            }
+            Self::NotFound => StatusCode::NOT_FOUND.into_response(),
+            Self::Database => super::error::Error::internal().into_response(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[cfg_attr(test, derive(Debug, serde::Serialize))]
+pub(crate) struct RequestPayload {
+    #[serde(flatten)]
+    pub client: ClientCredentials,
+    pub request: String,
+}
+
+#[derive(serde::Serialize)]
+#[cfg_attr(test, derive(serde::Deserialize))]
+pub(crate) struct ResponsePayload {
+    request_uri: String,
+    expires_in: u64,
+}
+
+// A stand-in for the client hosting its request objects, so that they can be passed by
+// reference to the authorization endpoint without fetching anything remote
+pub(super) async fn create(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Extension(base_url): Extension<BaseUrl>,
+    ClientAuthorization(basic): ClientAuthorization,
+    AnyContentType(payload): AnyContentType<RequestPayload>,
+) -> Result<(StatusCode, Json<ResponsePayload>), ResponseError> {
+    let client = authenticate_client(database.as_ref(), basic, payload.client).await?;
+
+    // the signature is checked when the request object is used, but garbage is refused early
+    client
+        .jwks
+        .verify::<serde_json::Value>(&payload.request)
+        .map_err(|err| {
+            tracing::debug!(message = "invalid request object", source = %err);
+            ResponseError::InvalidRequestObject
+        })?;
+
+    let mut tx = database.as_ref().begin().await?;
+    crate::entity::request_object::DeleteExpired
+        .execute(&mut tx)
+        .await?;
+    let id = crate::helper::generate_token(32);
+    crate::entity::request_object::Create {
+        id: id.as_str(),
+        client_id: client.id,
+        request: payload.request.as_str(),
+        time_to_live: REQUEST_OBJECT_TTL,
+    }
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ResponsePayload {
+            request_uri: base_url.join(&format!(
+                "{}{}/{id}",
+                crate::router::API_PATH,
+                super::REQUEST_OBJECT_PATH
+            )),
+            expires_in: REQUEST_OBJECT_TTL.as_secs(),
+        }),
+    ))
+}
+
+pub(super) async fn read(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ResponseError> {
+    let found = crate::entity::request_object::FindById::new(&id)
+        .execute(database.as_ref())
+        .await?
+        .ok_or(ResponseError::NotFound)?;
+    Ok((
+        [(header::CONTENT_TYPE, REQUEST_OBJECT_CONTENT_TYPE)],
+        found.request,
+    ))
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
+
+    use crate::router::api::prelude::basic_authorization;
+    use crate::service::dataset::{CLIENT_ID, CLIENT_SECRET, REDIRECT_URI};
+
+    fn get(uri: &str) -> Request<Body> {
+        Request::builder()
+            .uri(uri)
+            .method("GET")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn claims(client_id: &str, state: &str) -> serde_json::Value {
+        serde_json::json!({
+            "iss": client_id,
+            "aud": "http://localhost:8080",
+            "exp": chrono::Utc::now().timestamp() + 60,
+            "client_id": client_id,
+            "redirect_uri": REDIRECT_URI,
+            "state": state,
+            "code_challenge": "code-challenge",
+            "code_challenge_method": "plain",
+            "response_type": "code",
+        })
+    }
+
+    async fn store(app: &crate::app::Application, request: &str) -> axum::response::Response {
+        let client_id = CLIENT_ID.to_string();
+        let req = Request::builder()
+            .uri("/api/request-objects")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header(
+                "Authorization",
+                basic_authorization(&client_id, CLIENT_SECRET),
+            )
+            .method("POST")
+            .body(Body::from(
+                serde_urlencoded::to_string([("request", request)]).unwrap(),
+            ))
+            .unwrap();
+        app.handle(req).await
+    }
+
+    #[tokio::test]
+    async fn should_authorize_with_request_object_reference() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let client_id = CLIENT_ID.to_string();
+
+        let (pair, jwk) = crate::jose::tests::ed25519_key("client-key");
+        let jwks = crate::jose::JwkSet { keys: vec![jwk] };
+        sqlx::query("update applications set jwks = $2 where id = $1")
+            .bind(CLIENT_ID)
+            .bind(serde_json::to_string(&jwks).unwrap())
+            .execute(app.database())
+            .await
+            .unwrap();
+
+        let request =
+            crate::jose::tests::sign(&pair, "client-key", &claims(&client_id, "signed-state"));
+        let res = store(&app, &request).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: super::ResponsePayload = serde_json::from_slice(&body).unwrap();
+        assert!(body
+            .request_uri
+            .starts_with("http://localhost:8080/api/request-objects/"));
+        assert_eq!(body.expires_in, 600);
+
+        // the request object is served as the client would
+        let path = body.request_uri.trim_start_matches("http://localhost:8080");
+        let res = app.handle(get(path)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get("Content-Type").unwrap(),
+            "application/oauth-authz-req+jwt"
+        );
+        let body_bytes = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body_bytes, request.as_bytes());
+
+        let query = serde_urlencoded::to_string([
+            ("client_id", client_id.as_str()),
+            ("request_uri", body.request_uri.as_str()),
+            ("state", "query-state"),
+        ])
+        .unwrap();
+        let res = app.handle(get(&format!("/authorize?{query}"))).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let page = res.into_body().collect().await.unwrap().to_bytes();
+        let page = String::from_utf8_lossy(&page);
+        assert!(!page.contains("query-state"));
+        // the verified parameters are kept on the server
+        let (parameters,): (String,) =
+            sqlx::query_as("select parameters from pushed_authorizations")
+                .fetch_one(app.database())
+                .await
+                .unwrap();
+        assert!(parameters.contains("state=signed-state"));
+
+        // remote request objects aren't fetched
+        let query = serde_urlencoded::to_string([
+            ("client_id", client_id.as_str()),
+            ("request_uri", "https://client.example/request.jwt"),
+        ])
+        .unwrap();
+        let res = app.handle(get(&format!("/authorize?{query}"))).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn should_reject_unsigned_request_object() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+
+        let res = store(&app, "not-a-token").await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_request_object");
+
+        let res = app.handle(get("/api/request-objects/unknown")).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
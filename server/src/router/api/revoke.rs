@@ -0,0 +1,304 @@
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Extension;
+
+use super::access_token::{session_token, AnyContentType};
+use super::prelude::{
+    authenticate_client, ClientAuthenticationError, ClientAuthorization, ClientCredentials,
+};
+use crate::service::keys::Keys;
+
+pub(crate) enum ResponseError {
+    ClientAuthentication(ClientAuthenticationError),
+    Database,
+}
+
+impl From<sqlx::Error> for ResponseError {
+    fn from(value: sqlx::Error) -> Self {
+        tracing::error!(message = "database interaction failed", error = %value);
+        Self::Database
+    }
+}
+
+impl From<ClientAuthenticationError> for ResponseError {
+    fn from(value: ClientAuthenticationError) -> Self {
+        Self::ClientAuthentication(value)
+    }
+}
+
+impl IntoResponse for ResponseError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::ClientAuthentication(inner) => inner.into_response(),
+            Self::Database => super::error::Error::internal().into_response(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TokenTypeHint {
+    AccessToken,
+    RefreshToken,
+    // RFC 7009 §2.1: unknown hints are ignored
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(serde::Deserialize)]
+#[cfg_attr(test, derive(Debug, serde::Serialize))]
+pub(crate) struct RequestPayload {
+    #[serde(flatten)]
+    pub client: ClientCredentials,
+    pub token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_type_hint: Option<TokenTypeHint>,
+}
+
+async fn revoke_access_token(
+    conn: &mut sqlx::SqliteConnection,
+    keys: &Keys,
+    client: &crate::entity::application::Entity,
+    token: &str,
+) -> Result<bool, sqlx::Error> {
+    session_token(keys, token)
+        .revoke(client.id)
+        .execute(&mut *conn)
+        .await
+}
+
+async fn revoke_refresh_token(
+    conn: &mut sqlx::SqliteConnection,
+    client: &crate::entity::application::Entity,
+    token: &str,
+) -> Result<bool, sqlx::Error> {
+    let Some(found) = crate::entity::refresh_token::FindByToken::new(token)
+        .execute(&mut *conn)
+        .await?
+    else {
+        return Ok(false);
+    };
+    if found.client_id != client.id {
+        tracing::warn!(message = "refresh token revoked by another client", client_id = %client.id);
+        return Ok(false);
+    }
+    // RFC 7009 §2.1: revoking a refresh token also invalidates the access tokens of the same grant
+    let revoked = crate::entity::session::RevokeByRefreshFamily::new(&found.family)
+        .execute(&mut *conn)
+        .await?;
+    Ok(revoked > 0)
+}
+
+pub(super) async fn handle(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Extension(keys): Extension<Keys>,
+    ClientAuthorization(basic): ClientAuthorization,
+    AnyContentType(payload): AnyContentType<RequestPayload>,
+) -> Result<StatusCode, ResponseError> {
+    let client = authenticate_client(database.as_ref(), basic, payload.client).await?;
+
+    let mut tx = database.as_ref().begin().await?;
+    let token = payload.token.as_str();
+    // the hint only changes the lookup order
+    let revoked = match payload.token_type_hint {
+        Some(TokenTypeHint::RefreshToken) => {
+            revoke_refresh_token(&mut tx, &client, token).await?
+                || revoke_access_token(&mut tx, &keys, &client, token).await?
+        }
+        _ => {
+            revoke_access_token(&mut tx, &keys, &client, token).await?
+                || revoke_refresh_token(&mut tx, &client, token).await?
+        }
+    };
+    tx.commit().await?;
+
+    if !revoked {
+        // RFC 7009 §2.2: invalid tokens don't cause an error response
+        tracing::debug!(message = "no token to revoke", client_id = %client.id);
+    }
+
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use uuid::Uuid;
+
+    use crate::router::api::prelude::basic_authorization;
+    use crate::service::dataset::{ALICE_ID, CLIENT_ID, CLIENT_SECRET, REDIRECT_URI};
+
+    const LOCAL_TTL: Duration = Duration::new(60, 0);
+
+    async fn create_session(app: &crate::app::Application, access_token: &str, family: &str) {
+        crate::entity::session::Create {
+            access_token,
+            jti: None,
+            client_id: CLIENT_ID,
+            user_id: Some(ALICE_ID),
+            scope: None,
+            authorization_code: None,
+            dpop_jkt: None,
+            audience: None,
+            actor: None,
+            time_to_live: LOCAL_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+        crate::entity::refresh_token::Create {
+            token: &format!("refresh-{access_token}"),
+            family,
+            access_token,
+            time_to_live: LOCAL_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+    }
+
+    fn revoke_request(body: String, authorization: Option<String>) -> Request<Body> {
+        let builder = Request::builder()
+            .uri("/api/revoke")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .method("POST");
+        let builder = match authorization {
+            Some(value) => builder.header("Authorization", value),
+            None => builder,
+        };
+        builder.body(Body::from(body)).unwrap()
+    }
+
+    async fn user_info_status(app: &crate::app::Application, access_token: &str) -> StatusCode {
+        let req = Request::builder()
+            .uri("/api/user-info")
+            .header("Authorization", format!("Bearer {access_token}"))
+            .method("GET")
+            .body(Body::empty())
+            .unwrap();
+        app.handle(req).await.status()
+    }
+
+    #[tokio::test]
+    async fn should_revoke_access_token() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        create_session(&app, "aaaaaaaaaaaaaaaaaaa", "family-a").await;
+        assert_eq!(
+            user_info_status(&app, "aaaaaaaaaaaaaaaaaaa").await,
+            StatusCode::OK
+        );
+
+        let res = app
+            .handle(revoke_request(
+                "token=aaaaaaaaaaaaaaaaaaa&token_type_hint=access_token".into(),
+                Some(basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET)),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        assert_eq!(
+            user_info_status(&app, "aaaaaaaaaaaaaaaaaaa").await,
+            StatusCode::UNAUTHORIZED
+        );
+        // the refresh token issued with the session can't be used anymore
+        let refresh = crate::entity::refresh_token::FindByToken::new("refresh-aaaaaaaaaaaaaaaaaaa")
+            .execute(app.database())
+            .await
+            .unwrap();
+        assert!(refresh.is_none());
+    }
+
+    #[tokio::test]
+    async fn should_revoke_refresh_token_family() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        create_session(&app, "aaaaaaaaaaaaaaaaaaa", "family-a").await;
+        create_session(&app, "bbbbbbbbbbbbbbbbbbb", "family-a").await;
+        create_session(&app, "ccccccccccccccccccc", "family-c").await;
+
+        let res = app
+            .handle(revoke_request(
+                format!("token=refresh-bbbbbbbbbbbbbbbbbbb&client_id={CLIENT_ID}&client_secret={CLIENT_SECRET}"),
+                None,
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        assert_eq!(
+            user_info_status(&app, "aaaaaaaaaaaaaaaaaaa").await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            user_info_status(&app, "bbbbbbbbbbbbbbbbbbb").await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            user_info_status(&app, "ccccccccccccccccccc").await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn should_accept_unknown_token() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+
+        let res = app
+            .handle(revoke_request(
+                "token=unknown".into(),
+                Some(basic_authorization(&CLIENT_ID.to_string(), CLIENT_SECRET)),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_ignore_token_of_another_client() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        create_session(&app, "aaaaaaaaaaaaaaaaaaa", "family-a").await;
+
+        let other_id = Uuid::new_v4();
+        crate::entity::application::Upsert::new(other_id, &HashSet::new(), &[REDIRECT_URI.into()])
+            .execute(app.database())
+            .await
+            .unwrap();
+
+        let res = app
+            .handle(revoke_request(
+                format!("token=aaaaaaaaaaaaaaaaaaa&client_id={other_id}"),
+                None,
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            user_info_status(&app, "aaaaaaaaaaaaaaaaaaa").await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn should_reject_unauthenticated_client() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+
+        let res = app
+            .handle(revoke_request("token=aaaaaaaaaaaaaaaaaaa".into(), None))
+            .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let res = app
+            .handle(revoke_request(
+                "token=aaaaaaaaaaaaaaaaaaa".into(),
+                Some(basic_authorization(&CLIENT_ID.to_string(), "wrong")),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -2,12 +2,21 @@ use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::{Extension, Json};
 
-use super::prelude::AuthorizationToken;
+use super::access_token::session_token;
+use super::dpop::{DPoPError, DPoPHeader};
+use super::prelude::{AuthorizationToken, TokenScheme};
 use crate::entity::user::Entity as UserEntity;
+use crate::service::base_url::BaseUrl;
+use crate::service::keys::Keys;
 
 #[derive(Debug)]
 pub(crate) enum ErrorResponse {
     UserSessionNotFound,
+    SessionWithoutUser,
+    DPoPRequired,
+    DPoPNotBound,
+    DPoPKeyMismatch,
+    DPoP(DPoPError),
     Database,
 }
 
@@ -18,28 +27,80 @@ impl From<sqlx::Error> for ErrorResponse {
     }
 }
 
-impl ErrorResponse {
-    fn status_and_message(&self) -> (StatusCode, &'static str) {
-        match self {
-            Self::UserSessionNotFound => (StatusCode::UNAUTHORIZED, "invalid token"),
-            Self::Database => (StatusCode::INTERNAL_SERVER_ERROR, "something went wrong..."),
-        }
+impl From<DPoPError> for ErrorResponse {
+    fn from(value: DPoPError) -> Self {
+        Self::DPoP(value)
     }
 }
 
 impl IntoResponse for ErrorResponse {
     fn into_response(self) -> axum::response::Response {
-        let (code, message) = self.status_and_message();
-        super::error::Error::new(code, message).into_response()
+        match self {
+            Self::UserSessionNotFound => super::error::Error::bearer(
+                StatusCode::UNAUTHORIZED,
+                Some("invalid_token"),
+                "the access token is invalid or expired",
+            ),
+            Self::SessionWithoutUser => super::error::Error::bearer(
+                StatusCode::FORBIDDEN,
+                Some("insufficient_scope"),
+                "token has been issued to a client, not to a user",
+            ),
+            // RFC 9449 §7.2: a bound token can't be used as a bearer token
+            Self::DPoPRequired => super::error::Error::dpop(
+                StatusCode::UNAUTHORIZED,
+                "invalid_token",
+                "the access token is bound to a key and requires a DPoP proof",
+            ),
+            Self::DPoPNotBound => super::error::Error::dpop(
+                StatusCode::UNAUTHORIZED,
+                "invalid_token",
+                "the access token is not bound to a key",
+            ),
+            Self::DPoPKeyMismatch => super::error::Error::dpop(
+                StatusCode::UNAUTHORIZED,
+                "invalid_dpop_proof",
+                "the DPoP proof is signed by another key",
+            ),
+            Self::DPoP(inner) => inner.resource_error(),
+            Self::Database => super::error::Error::internal(),
+        }
+        .into_response()
     }
 }
 
 #[axum::debug_handler]
 pub(super) async fn handle(
     Extension(database): Extension<crate::service::database::Pool>,
-    AuthorizationToken(token): AuthorizationToken,
+    Extension(base_url): Extension<BaseUrl>,
+    Extension(keys): Extension<Keys>,
+    token: AuthorizationToken,
+    DPoPHeader(proof): DPoPHeader,
 ) -> Result<Json<UserEntity>, ErrorResponse> {
-    let user = crate::entity::user::FindByAccessToken::new(token.token())
+    let session = session_token(&keys, &token.token)
+        .find()
+        .execute(database.as_ref())
+        .await?;
+    let session = session.ok_or(ErrorResponse::UserSessionNotFound)?;
+    match (session.dpop_jkt.as_deref(), token.scheme, proof) {
+        (Some(expected), TokenScheme::DPoP, Some(proof)) => {
+            let found = proof
+                .verify(&database, &base_url, Some(&token.token))
+                .await?;
+            if found != expected {
+                tracing::warn!(message = "DPoP proof signed by another key", client_id = %session.client_id);
+                return Err(ErrorResponse::DPoPKeyMismatch);
+            }
+        }
+        (Some(_), _, _) => return Err(ErrorResponse::DPoPRequired),
+        (None, TokenScheme::DPoP, _) => return Err(ErrorResponse::DPoPNotBound),
+        (None, TokenScheme::Bearer, _) => {}
+    }
+    if session.user_id.is_none() {
+        return Err(ErrorResponse::SessionWithoutUser);
+    }
+
+    let user = crate::entity::user::FindByAccessToken::new(&session.access_token)
         .execute(database.as_ref())
         .await?;
     let user = user.ok_or(ErrorResponse::UserSessionNotFound)?;
@@ -53,7 +114,9 @@ mod integration_tests {
 
     use axum::body::Body;
     use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
 
+    use crate::router::api::dpop::tests::proof;
     use crate::service::dataset::{ALICE_ID, CLIENT_ID};
 
     const LOCAL_TTL: Duration = Duration::new(10, 0);
@@ -64,9 +127,14 @@ mod integration_tests {
         let app = crate::app::Application::test().await;
         crate::entity::session::Create {
             access_token: "aaaaaaaaaaaaaaaaaaa",
+            jti: None,
             client_id: CLIENT_ID,
-            user_id: ALICE_ID,
+            user_id: Some(ALICE_ID),
             scope: None,
+            authorization_code: None,
+            dpop_jkt: None,
+            audience: None,
+            actor: None,
             time_to_live: LOCAL_TTL,
         }
         .execute(app.database())
@@ -95,5 +163,145 @@ mod integration_tests {
             .unwrap();
         let res = app.handle(req).await;
         assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            res.headers().get("WWW-Authenticate").unwrap(),
+            "Bearer realm=\"tekitoi\""
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_access_token_should_fail() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+
+        let req = Request::builder()
+            .uri("/api/user-info")
+            .header("Authorization", "Bearer unknown")
+            .method("GET")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let challenge = res.headers().get("WWW-Authenticate").unwrap();
+        assert!(challenge
+            .to_str()
+            .unwrap()
+            .contains("error=\"invalid_token\""));
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_token");
+    }
+
+    #[tokio::test]
+    async fn should_require_dpop_proof_for_bound_token() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let (pair, jwk) = crate::jose::tests::ed25519_key("client");
+        let (other_pair, other_jwk) = crate::jose::tests::ed25519_key("other");
+        let jkt = jwk.thumbprint();
+        crate::entity::session::Create {
+            access_token: "aaaaaaaaaaaaaaaaaaa",
+            jti: None,
+            client_id: CLIENT_ID,
+            user_id: Some(ALICE_ID),
+            scope: None,
+            authorization_code: None,
+            dpop_jkt: Some(&jkt),
+            audience: None,
+            actor: None,
+            time_to_live: LOCAL_TTL,
+        }
+        .execute(app.database())
+        .await
+        .unwrap();
+        let request = |scheme: &str, proof: Option<String>| {
+            let builder = Request::builder()
+                .uri("/api/user-info")
+                .header("Authorization", format!("{scheme} aaaaaaaaaaaaaaaaaaa"))
+                .method("GET");
+            let builder = match proof {
+                Some(value) => builder.header("DPoP", value),
+                None => builder,
+            };
+            builder.body(Body::empty()).unwrap()
+        };
+
+        // RFC 9449 §7.2: the bound token can't be used as a bearer token
+        let res = app.handle(request("Bearer", None)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let challenge = res.headers().get("WWW-Authenticate").unwrap();
+        assert!(challenge.to_str().unwrap().starts_with("DPoP "));
+
+        let res = app
+            .handle(request(
+                "DPoP",
+                Some(proof(
+                    &pair,
+                    &jwk,
+                    "GET",
+                    "/api/user-info",
+                    None,
+                    Some("aaaaaaaaaaaaaaaaaaa"),
+                )),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let challenge = res.headers().get("WWW-Authenticate").unwrap();
+        assert!(challenge
+            .to_str()
+            .unwrap()
+            .contains("error=\"use_dpop_nonce\""));
+        let nonce = res.headers().get("DPoP-Nonce").unwrap().to_str().unwrap();
+        let nonce = nonce.to_string();
+
+        // proof without the access token hash
+        let res = app
+            .handle(request(
+                "DPoP",
+                Some(proof(
+                    &pair,
+                    &jwk,
+                    "GET",
+                    "/api/user-info",
+                    Some(&nonce),
+                    None,
+                )),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        // proof signed by another key
+        let res = app
+            .handle(request(
+                "DPoP",
+                Some(proof(
+                    &other_pair,
+                    &other_jwk,
+                    "GET",
+                    "/api/user-info",
+                    Some(&nonce),
+                    Some("aaaaaaaaaaaaaaaaaaa"),
+                )),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "invalid_dpop_proof");
+
+        let res = app
+            .handle(request(
+                "DPoP",
+                Some(proof(
+                    &pair,
+                    &jwk,
+                    "GET",
+                    "/api/user-info",
+                    Some(&nonce),
+                    Some("aaaaaaaaaaaaaaaaaaa"),
+                )),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
     }
 }
@@ -1,10 +1,16 @@
 mod api;
 mod asset;
 mod ui;
+mod well_known;
+
+pub(crate) use api::ACCESS_TOKEN_TTL;
+
+const API_PATH: &str = "/api";
 
 pub(crate) fn create() -> axum::Router {
     axum::Router::new()
-        .nest("/api", api::router())
+        .nest(API_PATH, api::router())
+        .nest("/.well-known", well_known::router())
         .merge(asset::router())
         .merge(ui::router())
 }
@@ -2,7 +2,7 @@ use std::borrow::Cow;
 use std::collections::HashSet;
 use std::time::Duration;
 
-use axum::extract::Query;
+use axum::extract::{Query, RawQuery};
 use axum::http::StatusCode;
 use axum::response::{Html, IntoResponse};
 use axum::Extension;
@@ -13,13 +13,22 @@ use crate::entity::code_challenge::CodeChallengeMethod;
 use crate::entity::provider::ProviderKind;
 use crate::entity::response_type::ResponseType;
 use crate::entity::user::Entity as UserEntity;
+use crate::helper::generate_token;
+use crate::jose::JoseError;
+use crate::router::api::{REQUEST_OBJECT_PATH, REQUEST_URI_PREFIX as PUSHED_REQUEST_URI_PREFIX};
+use crate::router::ui::client_error::{ClientError, ErrorCode};
+use crate::router::ui::login::{Flow, FlowError, PushedQueryParams};
+use crate::router::API_PATH;
+use crate::service::base_url::BaseUrl;
 
 // 10 mins
-pub(super) const AUTHORIZATION_TTL: Duration = Duration::new(600, 0);
+pub(crate) const AUTHORIZATION_TTL: Duration = Duration::new(600, 0);
 
 pub(crate) enum ResponseError {
-    ApplicationNotFound,
-    InvalidRedirectUri,
+    Flow(FlowError),
+    InvalidClientParams,
+    InvalidRequestObject,
+    Client(ClientError),
     UnableToBuildPage,
     Database,
 }
@@ -31,34 +40,40 @@ impl From<sqlx::Error> for ResponseError {
     }
 }
 
-impl ResponseError {
-    fn status(&self) -> StatusCode {
-        match self {
-            Self::ApplicationNotFound => StatusCode::NOT_FOUND,
-            Self::InvalidRedirectUri => StatusCode::BAD_REQUEST,
-            Self::UnableToBuildPage | Self::Database => StatusCode::INTERNAL_SERVER_ERROR,
-        }
-    }
-
-    fn message(&self) -> &'static str {
-        match self {
-            Self::ApplicationNotFound => "Application not found with provided client ID.",
-            Self::InvalidRedirectUri => "The provided redirect URI is invalid.",
-            Self::UnableToBuildPage | Self::Database => "Something went wrong...",
-        }
+impl From<FlowError> for ResponseError {
+    fn from(value: FlowError) -> Self {
+        Self::Flow(value)
     }
 }
 
 impl IntoResponse for ResponseError {
     fn into_response(self) -> axum::response::Response {
-        super::error::Error::new(self.status(), self.message()).into_response()
+        match self {
+            Self::Flow(inner) => inner.into_response(),
+            Self::Client(inner) => inner.into_response(),
+            Self::InvalidClientParams => super::error::Error::new(
+                StatusCode::BAD_REQUEST,
+                "The client ID or the redirect URI is missing.",
+            )
+            .into_response(),
+            Self::InvalidRequestObject => super::error::Error::new(
+                StatusCode::BAD_REQUEST,
+                "The signed request object is invalid.",
+            )
+            .into_response(),
+            Self::UnableToBuildPage | Self::Database => super::error::Error::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Something went wrong...",
+            )
+            .into_response(),
+        }
     }
 }
 
 fn credentials_section(
-    params: &QueryParams,
+    flow: &Flow,
 ) -> anyhow::Result<tekitoi_ui::view::authorize::credentials::Section> {
-    let params = serde_urlencoded::to_string(params)?;
+    let params = serde_urlencoded::to_string(flow)?;
     let target = format!("/authorize/{}/login?{params}", ProviderKind::Credentials);
     Ok(tekitoi_ui::view::authorize::credentials::Section::new(
         target,
@@ -66,13 +81,13 @@ fn credentials_section(
 }
 
 fn profiles_section(
-    params: &QueryParams,
+    flow: &Flow,
     users: Vec<UserEntity>,
 ) -> anyhow::Result<tekitoi_ui::view::authorize::profiles::Section> {
     let mut res = tekitoi_ui::view::authorize::profiles::Section::default();
     for user in users {
         let target_params = super::login::profiles::QueryParams {
-            base: Cow::Borrowed(&params.base),
+            flow: Cow::Borrowed(flow),
             user: user.id,
         };
         let target_params = serde_urlencoded::to_string(&target_params)?;
@@ -91,11 +106,19 @@ pub(crate) struct BaseQueryParams {
     pub client_id: Uuid,
     pub redirect_uri: String,
     pub state: String,
-    pub code_challenge: String,
-    pub code_challenge_method: CodeChallengeMethod, // S256
-    pub response_type: ResponseType,                // code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_challenge: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_challenge_method: Option<CodeChallengeMethod>, // S256
+    pub response_type: ResponseType, // code
     #[serde(skip_serializing_if = "Option::is_none")]
     pub scope: Option<String>,
+    // RFC 8707 §2: only one resource per request is supported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+    // OpenID Connect Core §3.1.2.1: given back in the id token to mitigate replay attacks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
 }
 
 #[derive(Clone, serde::Deserialize, serde::Serialize)]
@@ -106,31 +129,233 @@ pub(crate) struct QueryParams {
     pub error: Option<String>,
 }
 
-pub(super) async fn handle(
-    Extension(database): Extension<crate::service::database::Pool>,
-    Query(params): Query<QueryParams>,
-) -> Result<Html<String>, ResponseError> {
-    let mut tx = database.as_ref().begin().await?;
-    let app = crate::entity::application::FindById::new(params.base.client_id)
-        .execute(&mut *tx)
-        .await?;
-    let app = app.ok_or(ResponseError::ApplicationNotFound)?;
-    if !app.redirect_uri.eq(params.base.redirect_uri.as_str()) {
-        return Err(ResponseError::InvalidRedirectUri);
+// The parameters needed to trust the redirect uri, errors before this point can't be sent back to the client
+#[derive(serde::Deserialize)]
+struct ClientParams {
+    client_id: Uuid,
+    redirect_uri: String,
+    state: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct ResponseTypeParams {
+    response_type: Option<String>,
+}
+
+// Shared with the pushed authorization endpoint, the errors are reported to the client
+pub(crate) fn parse_request<T: serde::de::DeserializeOwned>(
+    query: &str,
+) -> Result<T, (ErrorCode, Cow<'static, str>)> {
+    let params: ResponseTypeParams = serde_urlencoded::from_str(query)
+        .map_err(|err| (ErrorCode::InvalidRequest, err.to_string().into()))?;
+    match params.response_type.as_deref() {
+        None => {
+            return Err((
+                ErrorCode::InvalidRequest,
+                "missing field `response_type`".into(),
+            ))
+        }
+        Some(value) if value.parse::<ResponseType>().is_err() => {
+            return Err((
+                ErrorCode::UnsupportedResponseType,
+                format!("response type {value:?} is not supported").into(),
+            ))
+        }
+        Some(_) => {}
+    }
+    if crate::helper::has_repeated_resource(query.as_bytes()) {
+        return Err((
+            ErrorCode::InvalidTarget,
+            "only one resource can be requested".into(),
+        ));
+    }
+    serde_urlencoded::from_str(query)
+        .map_err(|err| (ErrorCode::InvalidRequest, err.to_string().into()))
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct RequestObjectParams {
+    pub client_id: Uuid,
+    pub request: String,
+}
+
+#[derive(Debug)]
+pub(crate) enum RequestObjectError {
+    Signature(JoseError),
+    InvalidClaim(&'static str),
+    Replayed,
+    Database,
+}
+
+impl From<sqlx::Error> for RequestObjectError {
+    fn from(value: sqlx::Error) -> Self {
+        tracing::error!(message = "database interaction failed", error = %value);
+        Self::Database
+    }
+}
+
+impl std::fmt::Display for RequestObjectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Signature(inner) => inner.fmt(f),
+            Self::InvalidClaim(name) => write!(f, "invalid claim {name:?}"),
+            Self::Replayed => write!(f, "request object already used"),
+            Self::Database => write!(f, "database interaction failed"),
+        }
+    }
+}
+
+// RFC 7519 §4.1: the registered claims only describe the request object itself
+const REGISTERED_CLAIMS: [&str; 6] = ["iss", "aud", "exp", "iat", "nbf", "jti"];
+
+// RFC 9101 §6.3: once the signature is verified, only the claims of the request object
+// are used, the parameters of the query are ignored
+pub(crate) async fn merge_request_object(
+    conn: &mut sqlx::SqliteConnection,
+    app: &crate::entity::application::Entity,
+    base_url: &BaseUrl,
+    request: &str,
+) -> Result<String, RequestObjectError> {
+    let (_, claims): (_, serde_json::Map<String, serde_json::Value>) = app
+        .jwks
+        .verify(request)
+        .map_err(RequestObjectError::Signature)?;
+
+    let client_id = app.id.to_string();
+    for name in ["iss", "client_id"] {
+        if claims
+            .get(name)
+            .is_some_and(|value| value.as_str() != Some(client_id.as_str()))
+        {
+            return Err(RequestObjectError::InvalidClaim(name));
+        }
+    }
+    let audience = match claims.get("aud") {
+        None => true,
+        Some(serde_json::Value::String(value)) => value == base_url.as_ref(),
+        Some(serde_json::Value::Array(values)) => values
+            .iter()
+            .any(|value| value.as_str() == Some(base_url.as_ref())),
+        Some(_) => false,
+    };
+    if !audience {
+        return Err(RequestObjectError::InvalidClaim("aud"));
+    }
+    // the request object has to expire, so that its identifier doesn't need to be kept forever
+    let now = chrono::Utc::now();
+    let exp = claims
+        .get("exp")
+        .and_then(serde_json::Value::as_i64)
+        .and_then(|exp| chrono::DateTime::from_timestamp(exp, 0))
+        .filter(|exp| *exp > now)
+        .ok_or(RequestObjectError::InvalidClaim("exp"))?;
+    if claims
+        .get("nbf")
+        .is_some_and(|nbf| nbf.as_i64().is_none_or(|nbf| nbf > now.timestamp()))
+    {
+        return Err(RequestObjectError::InvalidClaim("nbf"));
+    }
+    match claims.get("jti") {
+        None => {}
+        Some(serde_json::Value::String(jti)) => {
+            crate::entity::request_object::DeleteExpired
+                .execute(&mut *conn)
+                .await?;
+            let created = crate::entity::request_object::CreateJti {
+                client_id: app.id,
+                jti: jti.as_str(),
+                valid_until: exp,
+            }
+            .execute(&mut *conn)
+            .await?;
+            if !created {
+                return Err(RequestObjectError::Replayed);
+            }
+        }
+        Some(_) => return Err(RequestObjectError::InvalidClaim("jti")),
+    }
+
+    // the client identifier is given in the query, and checked against the claim above
+    let mut merged: Vec<(String, String)> = Vec::with_capacity(claims.len());
+    if !claims.contains_key("client_id") {
+        merged.push(("client_id".into(), client_id));
     }
+    for (name, value) in claims {
+        if REGISTERED_CLAIMS.contains(&name.as_str()) {
+            continue;
+        }
+        let value = match value {
+            serde_json::Value::String(inner) => inner,
+            serde_json::Value::Number(inner) => inner.to_string(),
+            serde_json::Value::Bool(inner) => inner.to_string(),
+            _ => continue,
+        };
+        merged.push((name, value));
+    }
+    serde_urlencoded::to_string(merged).map_err(|_| RequestObjectError::InvalidClaim("request"))
+}
+
+// RFC 9101 §5.2: only the request objects served by the server itself can be passed by
+// reference, the server doesn't fetch remote ones
+async fn find_request_object(
+    conn: &mut sqlx::SqliteConnection,
+    base_url: &BaseUrl,
+    reference: &PushedQueryParams,
+) -> Result<String, ResponseError> {
+    let id = reference
+        .request_uri
+        .strip_prefix(
+            base_url
+                .join(&format!("{API_PATH}{REQUEST_OBJECT_PATH}/"))
+                .as_str(),
+        )
+        .ok_or(ResponseError::InvalidRequestObject)?;
+    let found = crate::entity::request_object::FindById::new(id)
+        .execute(&mut *conn)
+        .await?
+        .filter(|found| found.client_id == reference.client_id)
+        .ok_or(ResponseError::InvalidRequestObject)?;
+    Ok(found.request)
+}
+
+impl From<RequestObjectError> for ResponseError {
+    fn from(value: RequestObjectError) -> Self {
+        match value {
+            RequestObjectError::Database => Self::Database,
+            other => {
+                tracing::debug!(message = "invalid request object", source = %other);
+                Self::InvalidRequestObject
+            }
+        }
+    }
+}
 
+// Renders the login page, listing the providers enabled for the application
+pub(super) async fn render_login(
+    conn: &mut sqlx::SqliteConnection,
+    app_id: Uuid,
+    flow: &Flow,
+    error: Option<String>,
+) -> Result<Html<String>, ResponseError> {
     let mut success = tekitoi_ui::view::authorize::View::default();
-    let providers = crate::entity::provider::ListByApplication::new(app.id)
-        .execute(&mut *tx)
+    if !matches!(flow, Flow::Device(_)) {
+        let params = serde_urlencoded::to_string(flow).map_err(|err| {
+            tracing::error!(message = "unable to generate cancel link", source = %err);
+            ResponseError::UnableToBuildPage
+        })?;
+        success.set_cancel(format!("/authorize/cancel?{params}"));
+    }
+    let providers = crate::entity::provider::ListByApplication::new(app_id)
+        .execute(&mut *conn)
         .await?;
     let providers: HashSet<_> = providers.into_iter().map(|p| p.kind).collect();
 
     if providers.contains(&ProviderKind::Profiles) {
         let users =
-            crate::entity::user::ListForApplicationAndProvider::new(app.id, ProviderKind::Profiles)
-                .execute(&mut *tx)
+            crate::entity::user::ListForApplicationAndProvider::new(app_id, ProviderKind::Profiles)
+                .execute(&mut *conn)
                 .await?;
-        let section = profiles_section(&params, users).map_err(|err| {
+        let section = profiles_section(flow, users).map_err(|err| {
             tracing::error!(message = "unable to generate profiles section", source = %err);
             ResponseError::UnableToBuildPage
         })?;
@@ -138,18 +363,543 @@ pub(super) async fn handle(
     }
 
     if providers.contains(&ProviderKind::Credentials) {
-        let section = credentials_section(&params).map_err(|err| {
+        let section = credentials_section(flow).map_err(|err| {
             tracing::error!(message = "unable to generate credentials section", source = %err);
             ResponseError::UnableToBuildPage
         })?;
         success.set_credentials(section);
     }
 
-    if let Some(error) = params.error {
+    if let Some(error) = error {
         success.set_error(error);
     }
 
+    Ok(Html(success.render()))
+}
+
+pub(crate) async fn find_client(
+    conn: &mut sqlx::SqliteConnection,
+    client_id: Uuid,
+    redirect_uri: &str,
+) -> Result<crate::entity::application::Entity, FlowError> {
+    let app = crate::entity::application::FindById::new(client_id)
+        .execute(&mut *conn)
+        .await?;
+    let app = app.ok_or(FlowError::ApplicationNotFound)?;
+    if !app.accepts_redirect_uri(redirect_uri) {
+        return Err(FlowError::InvalidRedirectUri);
+    }
+    Ok(app)
+}
+
+// RFC 9126 §4: the parameters pushed by the client replace the ones from the query
+async fn handle_pushed(
+    conn: &mut sqlx::SqliteConnection,
+    pushed: PushedQueryParams,
+) -> Result<Html<String>, ResponseError> {
+    let params = pushed.parameters(&mut *conn).await?;
+    let app = find_client(&mut *conn, params.client_id, &params.redirect_uri).await?;
+    render_login(&mut *conn, app.id, &Flow::Pushed(pushed), None).await
+}
+
+pub(super) async fn handle(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Extension(base_url): Extension<BaseUrl>,
+    RawQuery(query): RawQuery,
+) -> Result<Html<String>, ResponseError> {
+    let mut query = query.unwrap_or_default();
+    let mut signed = false;
+    let mut tx = database.as_ref().begin().await?;
+    match serde_urlencoded::from_str::<PushedQueryParams>(&query) {
+        Ok(pushed) if pushed.request_uri.starts_with(PUSHED_REQUEST_URI_PREFIX) => {
+            let page = handle_pushed(&mut tx, pushed).await?;
+            tx.commit().await?;
+            return Ok(page);
+        }
+        Ok(reference) => {
+            let request = find_request_object(&mut tx, &base_url, &reference).await?;
+            let app = crate::entity::application::FindById::new(reference.client_id)
+                .execute(&mut *tx)
+                .await?
+                .ok_or(FlowError::ApplicationNotFound)?;
+            query = merge_request_object(&mut tx, &app, &base_url, &request).await?;
+            signed = true;
+        }
+        Err(_) => {
+            if let Ok(object) = serde_urlencoded::from_str::<RequestObjectParams>(&query) {
+                let app = crate::entity::application::FindById::new(object.client_id)
+                    .execute(&mut *tx)
+                    .await?
+                    .ok_or(FlowError::ApplicationNotFound)?;
+                query = merge_request_object(&mut tx, &app, &base_url, &object.request).await?;
+                signed = true;
+            }
+        }
+    }
+
+    let client: ClientParams = serde_urlencoded::from_str(&query).map_err(|err| {
+        tracing::debug!(message = "invalid client parameters", source = %err);
+        ResponseError::InvalidClientParams
+    })?;
+    let client_error = |code: ErrorCode, description: Cow<'static, str>| {
+        ResponseError::Client(ClientError::new(
+            client.redirect_uri.as_str(),
+            code,
+            description,
+            client.state.clone(),
+        ))
+    };
+
+    let app = find_client(&mut tx, client.client_id, &client.redirect_uri).await?;
+    if app.require_pushed_authorization {
+        return Err(client_error(
+            ErrorCode::InvalidRequest,
+            "pushed authorization request required".into(),
+        ));
+    }
+    let mut params: QueryParams =
+        parse_request(&query).map_err(|(code, description)| client_error(code, description))?;
+    params.base.code_challenge_method = app
+        .check_code_challenge(
+            params.base.code_challenge.as_deref(),
+            params.base.code_challenge_method,
+        )
+        .map_err(|err| client_error(ErrorCode::InvalidRequest, err.to_string().into()))?;
+    params.base.scope = app
+        .grant_scope(params.base.scope.as_deref())
+        .map_err(|err| client_error(ErrorCode::InvalidScope, err.to_string().into()))?;
+    app.grant_resource(params.base.resource.as_deref())
+        .map_err(|err| client_error(ErrorCode::InvalidTarget, err.to_string().into()))?;
+
+    let flow = if signed {
+        // the verified parameters are kept on the server like the pushed ones, so that they
+        // can't be altered in the login links
+        let parameters = serde_urlencoded::to_string(&params.base).map_err(|err| {
+            tracing::error!(message = "unable to encode signed parameters", source = %err);
+            ResponseError::UnableToBuildPage
+        })?;
+        let request_uri = format!("{PUSHED_REQUEST_URI_PREFIX}{}", generate_token(32));
+        crate::entity::pushed_authorization::Create {
+            request_uri: request_uri.as_str(),
+            client_id: app.id,
+            parameters: parameters.as_str(),
+            time_to_live: AUTHORIZATION_TTL,
+        }
+        .execute(&mut *tx)
+        .await?;
+        Flow::Pushed(PushedQueryParams {
+            client_id: app.id,
+            request_uri,
+        })
+    } else {
+        Flow::Authorization(params.base)
+    };
+    let page = render_login(&mut tx, app.id, &flow, params.error).await?;
     tx.commit().await?;
 
-    Ok(Html(success.render()))
+    Ok(page)
+}
+
+pub(super) async fn cancel(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Query(flow): Query<Flow>,
+) -> Result<axum::response::Response, ResponseError> {
+    let mut tx = database.as_ref().begin().await?;
+    let params = match flow {
+        // RFC 8628 §3.5: the device is told on its next poll
+        Flow::Device(params) => {
+            let user_code = crate::helper::normalize_user_code(&params.user_code);
+            if !crate::entity::device_authorization::Deny::new(&user_code)
+                .execute(&mut *tx)
+                .await?
+            {
+                return Err(FlowError::DeviceCodeNotFound.into());
+            }
+            tx.commit().await?;
+            return Ok(
+                Html(tekitoi_ui::view::device::completed::View::denied().render()).into_response(),
+            );
+        }
+        Flow::Pushed(pushed) => {
+            let params = pushed.parameters(&mut tx).await?;
+            crate::entity::pushed_authorization::Consume::new(&pushed.request_uri)
+                .execute(&mut *tx)
+                .await?;
+            params
+        }
+        Flow::Authorization(params) => params,
+    };
+    find_client(&mut tx, params.client_id, &params.redirect_uri).await?;
+    tx.commit().await?;
+
+    Ok(ClientError::new(
+        params.redirect_uri,
+        ErrorCode::AccessDenied,
+        "the resource owner denied the request",
+        Some(params.state),
+    )
+    .into_response())
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
+    use uuid::Uuid;
+
+    use crate::service::dataset::{ALICE_ID, CLIENT_ID, REDIRECT_URI, RESOURCE};
+
+    fn authorize_request(params: &[(&str, &str)]) -> Request<Body> {
+        let query = serde_urlencoded::to_string(params).unwrap();
+        Request::builder()
+            .uri(format!("/authorize?{query}"))
+            .method("GET")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn valid_params(client_id: &str) -> Vec<(&str, &str)> {
+        vec![
+            ("client_id", client_id),
+            ("redirect_uri", REDIRECT_URI),
+            ("state", "the-state"),
+            ("code_challenge", "code-challenge"),
+            ("code_challenge_method", "plain"),
+            ("response_type", "code"),
+        ]
+    }
+
+    fn redirection_params(res: &axum::response::Response) -> HashMap<String, String> {
+        assert_eq!(res.status(), StatusCode::SEE_OTHER);
+        let location = res.headers().get("Location").unwrap().to_str().unwrap();
+        let (base, query) = location.split_once('?').unwrap();
+        assert_eq!(base, REDIRECT_URI);
+        serde_urlencoded::from_str::<HashMap<Cow<'_, str>, Cow<'_, str>>>(query)
+            .unwrap()
+            .into_iter()
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn should_render_login_page() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let client_id = CLIENT_ID.to_string();
+
+        let res = app
+            .handle(authorize_request(&valid_params(&client_id)))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("/authorize/cancel?"));
+    }
+
+    #[tokio::test]
+    async fn should_not_redirect_to_untrusted_client() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+
+        let unknown_id = Uuid::new_v4().to_string();
+        let res = app
+            .handle(authorize_request(&valid_params(&unknown_id)))
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let client_id = CLIENT_ID.to_string();
+        let mut params = valid_params(&client_id);
+        params[1] = ("redirect_uri", "http://attacker/redirect");
+        let res = app.handle(authorize_request(&params)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let res = app
+            .handle(authorize_request(&[("response_type", "token")]))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn should_redirect_unsupported_response_type() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let client_id = CLIENT_ID.to_string();
+
+        let mut params = valid_params(&client_id);
+        params[5] = ("response_type", "token");
+        let res = app.handle(authorize_request(&params)).await;
+        let params = redirection_params(&res);
+        assert_eq!(params["error"], "unsupported_response_type");
+        assert_eq!(params["state"], "the-state");
+        assert!(params.contains_key("error_description"));
+    }
+
+    #[tokio::test]
+    async fn should_redirect_invalid_request() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let client_id = CLIENT_ID.to_string();
+
+        let mut params = valid_params(&client_id);
+        params.remove(3); // code_challenge
+        let res = app.handle(authorize_request(&params)).await;
+        let params = redirection_params(&res);
+        assert_eq!(params["error"], "invalid_request");
+        assert_eq!(params["state"], "the-state");
+    }
+
+    #[tokio::test]
+    async fn should_redirect_when_user_cancels() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let client_id = CLIENT_ID.to_string();
+
+        let query = serde_urlencoded::to_string(valid_params(&client_id)).unwrap();
+        let req = Request::builder()
+            .uri(format!("/authorize/cancel?{query}"))
+            .method("GET")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.handle(req).await;
+        let params = redirection_params(&res);
+        assert_eq!(params["error"], "access_denied");
+        assert_eq!(params["state"], "the-state");
+    }
+
+    #[tokio::test]
+    async fn should_redirect_invalid_scope() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let client_id = CLIENT_ID.to_string();
+
+        let mut params = valid_params(&client_id);
+        params.push(("scope", "profile admin"));
+        let res = app.handle(authorize_request(&params)).await;
+        let params = redirection_params(&res);
+        assert_eq!(params["error"], "invalid_scope");
+        assert_eq!(params["state"], "the-state");
+    }
+
+    #[tokio::test]
+    async fn should_redirect_invalid_target() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let client_id = CLIENT_ID.to_string();
+
+        let mut params = valid_params(&client_id);
+        params.push(("resource", RESOURCE));
+        let res = app.handle(authorize_request(&params)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let mut params = valid_params(&client_id);
+        params.push(("resource", "http://other/api"));
+        let res = app.handle(authorize_request(&params)).await;
+        let params = redirection_params(&res);
+        assert_eq!(params["error"], "invalid_target");
+        assert_eq!(params["state"], "the-state");
+
+        // RFC 8707 §2: a single resource is supported
+        let mut params = valid_params(&client_id);
+        params.push(("resource", RESOURCE));
+        params.push(("resource", RESOURCE));
+        let res = app.handle(authorize_request(&params)).await;
+        let params = redirection_params(&res);
+        assert_eq!(params["error"], "invalid_target");
+        assert_eq!(params["state"], "the-state");
+    }
+
+    #[tokio::test]
+    async fn should_grant_default_scope() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let client_id = CLIENT_ID.to_string();
+
+        let res = app
+            .handle(authorize_request(&valid_params(&client_id)))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("scope=profile"));
+    }
+
+    #[tokio::test]
+    async fn should_accept_signed_request_object() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let client_id = CLIENT_ID.to_string();
+
+        let (pair, jwk) = crate::jose::tests::ed25519_key("client-key");
+        let jwks = crate::jose::JwkSet { keys: vec![jwk] };
+        sqlx::query("update applications set jwks = $2 where id = $1")
+            .bind(CLIENT_ID)
+            .bind(serde_json::to_string(&jwks).unwrap())
+            .execute(app.database())
+            .await
+            .unwrap();
+
+        let claims = serde_json::json!({
+            "iss": client_id,
+            "aud": "http://localhost:8080",
+            "exp": chrono::Utc::now().timestamp() + 60,
+            "jti": "first-request",
+            "client_id": client_id,
+            "redirect_uri": REDIRECT_URI,
+            "state": "signed-state",
+            "code_challenge": "code-challenge",
+            "code_challenge_method": "plain",
+            "response_type": "code",
+        });
+        let request = crate::jose::tests::sign(&pair, "client-key", &claims);
+        // only the claims are used, the values from the query are ignored
+        let res = app
+            .handle(authorize_request(&[
+                ("client_id", &client_id),
+                ("state", "query-state"),
+                ("scope", "email"),
+                ("request", &request),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8_lossy(&body);
+        assert!(!body.contains("query-state"));
+        // the verified parameters are kept on the server, the links only reference them
+        assert!(!body.contains("signed-state"));
+        let (request_uri, parameters): (String, String) =
+            sqlx::query_as("select request_uri, parameters from pushed_authorizations")
+                .fetch_one(app.database())
+                .await
+                .unwrap();
+        assert!(parameters.contains("state=signed-state"));
+        assert!(!parameters.contains("email"));
+
+        let query = serde_urlencoded::to_string([
+            ("client_id", client_id.as_str()),
+            ("request_uri", request_uri.as_str()),
+            ("user", ALICE_ID.to_string().as_str()),
+        ])
+        .unwrap();
+        let res = app
+            .handle(
+                Request::builder()
+                    .uri(format!("/authorize/profiles/login?{query}"))
+                    .method("GET")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("state=signed-state"));
+
+        let (other, _) = crate::jose::tests::ed25519_key("client-key");
+        let request = crate::jose::tests::sign(&other, "client-key", &claims);
+        let res = app
+            .handle(authorize_request(&[
+                ("client_id", &client_id),
+                ("request", &request),
+            ]))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn should_check_request_object_lifetime() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let client_id = CLIENT_ID.to_string();
+
+        let (pair, jwk) = crate::jose::tests::ed25519_key("client-key");
+        let jwks = crate::jose::JwkSet { keys: vec![jwk] };
+        sqlx::query("update applications set jwks = $2 where id = $1")
+            .bind(CLIENT_ID)
+            .bind(serde_json::to_string(&jwks).unwrap())
+            .execute(app.database())
+            .await
+            .unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        let signed = |claims: serde_json::Value| {
+            let mut merged = serde_json::json!({
+                "client_id": client_id,
+                "redirect_uri": REDIRECT_URI,
+                "state": "signed-state",
+                "code_challenge": "code-challenge",
+                "code_challenge_method": "plain",
+                "response_type": "code",
+            });
+            merged
+                .as_object_mut()
+                .unwrap()
+                .extend(claims.as_object().unwrap().clone());
+            let request = crate::jose::tests::sign(&pair, "client-key", &merged);
+            authorize_request(&[("client_id", &client_id), ("request", &request)])
+        };
+
+        // the expiration is required
+        let res = app.handle(signed(serde_json::json!({}))).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res = app
+            .handle(signed(serde_json::json!({ "exp": now - 10 })))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        // not usable before its time
+        let res = app
+            .handle(signed(
+                serde_json::json!({ "exp": now + 120, "nbf": now + 60 }),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        // an identifier can only be used once
+        let claims = serde_json::json!({ "exp": now + 60, "nbf": now, "jti": "once" });
+        let res = app.handle(signed(claims.clone())).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = app.handle(signed(claims)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn should_apply_pkce_policy() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        let client_id = CLIENT_ID.to_string();
+        sqlx::query("update applications set allow_plain_pkce = false where id = $1")
+            .bind(CLIENT_ID)
+            .execute(app.database())
+            .await
+            .unwrap();
+
+        let res = app
+            .handle(authorize_request(&valid_params(&client_id)))
+            .await;
+        let params = redirection_params(&res);
+        assert_eq!(params["error"], "invalid_request");
+        assert_eq!(
+            params["error_description"],
+            "transform algorithm not supported"
+        );
+
+        let mut params = valid_params(&client_id);
+        params.truncate(3); // without code challenge
+        params.push(("response_type", "code"));
+        let res = app.handle(authorize_request(&params)).await;
+        let redirection = redirection_params(&res);
+        assert_eq!(redirection["error_description"], "code challenge required");
+
+        sqlx::query("update applications set require_pkce = false where id = $1")
+            .bind(CLIENT_ID)
+            .execute(app.database())
+            .await
+            .unwrap();
+        let res = app.handle(authorize_request(&params)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
 }
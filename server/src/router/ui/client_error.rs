@@ -0,0 +1,65 @@
+use std::borrow::Cow;
+
+use axum::response::{IntoResponse, Redirect};
+
+use super::helper::encode_url;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ErrorCode {
+    InvalidRequest,
+    UnsupportedResponseType,
+    InvalidScope,
+    InvalidTarget,
+    AccessDenied,
+}
+
+impl ErrorCode {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::InvalidRequest => "invalid_request",
+            Self::UnsupportedResponseType => "unsupported_response_type",
+            Self::InvalidScope => "invalid_scope",
+            Self::InvalidTarget => "invalid_target",
+            Self::AccessDenied => "access_denied",
+        }
+    }
+}
+
+// RFC 6749 §4.1.2.1: once the client and its redirect uri are trusted,
+// errors are sent back to the client instead of being displayed to the user
+#[derive(Debug)]
+pub(crate) struct ClientError {
+    redirect_uri: String,
+    code: ErrorCode,
+    description: Cow<'static, str>,
+    state: Option<String>,
+}
+
+impl ClientError {
+    pub fn new(
+        redirect_uri: impl Into<String>,
+        code: ErrorCode,
+        description: impl Into<Cow<'static, str>>,
+        state: Option<String>,
+    ) -> Self {
+        Self {
+            redirect_uri: redirect_uri.into(),
+            code,
+            description: description.into(),
+            state,
+        }
+    }
+}
+
+impl IntoResponse for ClientError {
+    fn into_response(self) -> axum::response::Response {
+        let params = [
+            ("error", self.code.as_str()),
+            ("error_description", self.description.as_ref()),
+        ]
+        .into_iter()
+        .chain(self.state.as_deref().map(|state| ("state", state)));
+        let url = encode_url(&self.redirect_uri, params);
+        Redirect::to(url.as_ref()).into_response()
+    }
+}
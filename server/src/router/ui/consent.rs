@@ -0,0 +1,220 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Form};
+
+use super::client_error::{ClientError, ErrorCode};
+use super::error::Error;
+use super::login::code_redirection;
+
+pub(crate) enum ResponseError {
+    AuthorizationNotFound,
+    Database,
+}
+
+impl From<sqlx::Error> for ResponseError {
+    fn from(value: sqlx::Error) -> Self {
+        tracing::error!(message = "database interaction failed", error = %value);
+        Self::Database
+    }
+}
+
+impl IntoResponse for ResponseError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Self::AuthorizationNotFound => Error::new(
+                StatusCode::BAD_REQUEST,
+                "The authorization request is invalid or expired.",
+            )
+            .into_response(),
+            Self::Database => {
+                Error::new(StatusCode::INTERNAL_SERVER_ERROR, "Something went wrong...")
+                    .into_response()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Decision {
+    Approve,
+    Deny,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+pub(crate) struct RequestPayload {
+    consent_id: String,
+    decision: Decision,
+}
+
+pub(super) async fn handle(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Form(payload): Form<RequestPayload>,
+) -> Result<Response, ResponseError> {
+    let mut tx = database.as_ref().begin().await?;
+    let authorization = crate::entity::authorization::FindPendingConsent::new(&payload.consent_id)
+        .execute(&mut *tx)
+        .await?
+        .ok_or(ResponseError::AuthorizationNotFound)?;
+
+    match payload.decision {
+        Decision::Approve => {
+            if !crate::entity::authorization::ApproveConsent::new(&authorization.code)
+                .execute(&mut *tx)
+                .await?
+            {
+                return Err(ResponseError::AuthorizationNotFound);
+            }
+            crate::entity::consent::Create {
+                user_id: authorization.user_id,
+                client_id: authorization.client_id,
+                scope: authorization.scope.as_deref().unwrap_or_default(),
+            }
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+
+            Ok(code_redirection(
+                &authorization.redirect_uri,
+                &authorization.code,
+                &authorization.state,
+            )
+            .into_response())
+        }
+        Decision::Deny => {
+            crate::entity::authorization::Consume::new(&authorization.code)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+
+            Ok(ClientError::new(
+                authorization.redirect_uri,
+                ErrorCode::AccessDenied,
+                "the resource owner denied the request",
+                Some(authorization.state),
+            )
+            .into_response())
+        }
+    }
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
+
+    use crate::service::dataset::{ALICE_ID, CLIENT_ID, REDIRECT_URI};
+
+    async fn require_consent(app: &crate::app::Application) {
+        sqlx::query("update applications set require_consent = true where id = $1")
+            .bind(CLIENT_ID)
+            .execute(app.database())
+            .await
+            .unwrap();
+    }
+
+    async fn login(app: &crate::app::Application) -> String {
+        let query = serde_urlencoded::to_string([
+            ("client_id", CLIENT_ID.to_string().as_str()),
+            ("redirect_uri", REDIRECT_URI),
+            ("state", "the-state"),
+            ("code_challenge", "code-challenge"),
+            ("code_challenge_method", "plain"),
+            ("response_type", "code"),
+            ("scope", "profile email"),
+            ("user", ALICE_ID.to_string().as_str()),
+        ])
+        .unwrap();
+        let req = Request::builder()
+            .uri(format!("/authorize/profiles/login?{query}"))
+            .method("GET")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    fn find_consent_id(page: &str) -> &str {
+        let index = page.find("name=\"consent_id\" value=\"").unwrap() + 25;
+        let len = page[index..].find('"').unwrap();
+        &page[index..(index + len)]
+    }
+
+    fn consent_request(consent_id: &str, decision: &str) -> Request<Body> {
+        Request::builder()
+            .uri("/authorize/consent")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .method("POST")
+            .body(Body::from(format!(
+                "consent_id={consent_id}&decision={decision}"
+            )))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_remember_approval() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        require_consent(&app).await;
+
+        let page = login(&app).await;
+        assert!(page.contains("Authorization request"));
+        let consent_id = find_consent_id(&page);
+        let authorization = crate::entity::authorization::FindPendingConsent::new(consent_id)
+            .execute(app.database())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(authorization.consent_pending);
+        // the code isn't given before the approval
+        assert!(!page.contains(&authorization.code));
+        let res = app
+            .handle(consent_request(&authorization.code, "approve"))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let res = app.handle(consent_request(consent_id, "approve")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains(REDIRECT_URI));
+        assert!(body.contains(&authorization.code));
+
+        // the consent identifier can only be used once
+        let res = app.handle(consent_request(consent_id, "approve")).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        // the same scopes are now approved
+        let page = login(&app).await;
+        assert!(!page.contains("Authorization request"));
+        assert!(page.contains(REDIRECT_URI));
+    }
+
+    #[tokio::test]
+    async fn should_redirect_when_denied() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        require_consent(&app).await;
+
+        let page = login(&app).await;
+        let consent_id = find_consent_id(&page).to_string();
+
+        let res = app.handle(consent_request(&consent_id, "deny")).await;
+        assert_eq!(res.status(), StatusCode::SEE_OTHER);
+        let location = res.headers().get("Location").unwrap().to_str().unwrap();
+        assert!(location.starts_with(REDIRECT_URI));
+        assert!(location.contains("error=access_denied"));
+        assert!(location.contains("state=the-state"));
+
+        // the decision can't be changed afterward
+        let res = app.handle(consent_request(&consent_id, "approve")).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        // nothing has been remembered
+        let page = login(&app).await;
+        assert!(page.contains("Authorization request"));
+    }
+}
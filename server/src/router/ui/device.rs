@@ -0,0 +1,51 @@
+use axum::extract::Query;
+use axum::response::Html;
+use axum::Extension;
+use tekitoi_ui::view::View;
+
+use super::authorize::ResponseError;
+use super::login::{DeviceQueryParams, Flow};
+
+#[derive(serde::Deserialize)]
+pub(crate) struct QueryParams {
+    pub user_code: Option<String>,
+    pub error: Option<String>,
+}
+
+fn entry_view(error: Option<String>) -> Html<String> {
+    let mut view = tekitoi_ui::view::device::View::new("/device");
+    if let Some(error) = error {
+        view.set_error(error);
+    }
+    Html(view.render())
+}
+
+pub(super) async fn handle(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Query(params): Query<QueryParams>,
+) -> Result<Html<String>, ResponseError> {
+    let Some(user_code) = params.user_code else {
+        return Ok(entry_view(params.error));
+    };
+
+    let mut tx = database.as_ref().begin().await?;
+    let normalized = crate::helper::normalize_user_code(&user_code);
+    let found = crate::entity::device_authorization::FindPendingByUserCode::new(&normalized)
+        .execute(&mut *tx)
+        .await?;
+    let Some(found) = found else {
+        tracing::warn!(message = "device authorization not found", user_code = %user_code);
+        return Ok(entry_view(Some(
+            "The provided code is invalid or expired.".into(),
+        )));
+    };
+
+    let flow = Flow::Device(DeviceQueryParams {
+        user_code: crate::helper::format_user_code(&found.user_code),
+    });
+    let page =
+        super::authorize::render_login(&mut tx, found.client_id, &flow, params.error).await?;
+    tx.commit().await?;
+
+    Ok(page)
+}
@@ -17,6 +17,8 @@ pub(super) fn encode_url<'a>(
     params: impl Iterator<Item = (&'a str, &'a str)>,
 ) -> Cow<'a, str> {
     match encode_params(params) {
+        // the redirect uri can already contain a query
+        Some(values) if path.contains('?') => Cow::Owned(format!("{path}&{values}")),
         Some(values) => Cow::Owned(format!("{path}?{values}")),
         None => Cow::Borrowed(path),
     }
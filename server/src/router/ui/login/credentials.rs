@@ -2,18 +2,14 @@ use axum::extract::Query;
 use axum::http::StatusCode;
 use axum::response::{Html, IntoResponse, Redirect};
 use axum::{Extension, Form};
-use tekitoi_ui::view::View;
 
+use super::{Flow, FlowError};
 use crate::entity::user::FindForCredentials;
-use crate::helper::generate_token;
-use crate::router::ui::authorize::{BaseQueryParams, AUTHORIZATION_TTL};
 use crate::router::ui::error::Error;
-use crate::router::ui::helper::encode_url;
 
 pub(crate) enum ResponseError {
-    ApplicationNotFound,
-    InvalidCredentials(BaseQueryParams),
-    InvalidRedirectUri,
+    Flow(FlowError),
+    InvalidCredentials(Flow),
     Database,
 }
 
@@ -24,26 +20,22 @@ impl From<sqlx::Error> for ResponseError {
     }
 }
 
+impl From<FlowError> for ResponseError {
+    fn from(value: FlowError) -> Self {
+        Self::Flow(value)
+    }
+}
+
 impl IntoResponse for ResponseError {
     fn into_response(self) -> axum::response::Response {
         match self {
-            Self::ApplicationNotFound => Error::new(
-                StatusCode::NOT_FOUND,
-                "Application not found with provided client ID.",
-            )
-            .into_response(),
-            Self::InvalidRedirectUri => Error::new(
-                StatusCode::BAD_REQUEST,
-                "The provided redirect URI is invalid.",
-            )
-            .into_response(),
+            Self::Flow(inner) => inner.into_response(),
             Self::Database => {
                 Error::new(StatusCode::INTERNAL_SERVER_ERROR, "Something went wrong...")
                     .into_response()
             }
-            Self::InvalidCredentials(params) => {
-                let params = serde_urlencoded::to_string(&params).unwrap();
-                let uri = format!("/authorize?{params}");
+            Self::InvalidCredentials(flow) => {
+                let uri = flow.login_url().unwrap();
                 Redirect::temporary(uri.as_str()).into_response()
             }
         }
@@ -58,49 +50,30 @@ pub(crate) struct RequestPayload {
 
 pub(crate) async fn handle(
     Extension(database): Extension<crate::service::database::Pool>,
-    Query(params): Query<BaseQueryParams>,
+    Query(flow): Query<Flow>,
     Form(payload): Form<RequestPayload>,
 ) -> Result<Html<String>, ResponseError> {
     let mut tx = database.as_ref().begin().await?;
-    let app = crate::entity::application::FindById::new(params.client_id)
-        .execute(&mut *tx)
-        .await?;
-    let app = app.ok_or(ResponseError::ApplicationNotFound)?;
-    if !app.redirect_uri.eq(params.redirect_uri.as_str()) {
-        return Err(ResponseError::InvalidRedirectUri);
-    }
+    let app = flow.application(&mut tx).await?;
 
     let user = FindForCredentials::new(app.id, payload.email.as_str())
         .execute(&mut *tx)
         .await?;
     let Some(user) = user else {
         tracing::warn!(message = "user not found with provided email", email = %payload.email);
-        return Err(ResponseError::InvalidCredentials(params));
+        return Err(ResponseError::InvalidCredentials(flow));
     };
     if !user.check_password(payload.password.as_str()) {
         tracing::warn!(message = "invalid password", email = %payload.email);
-        return Err(ResponseError::InvalidCredentials(params));
+        return Err(ResponseError::InvalidCredentials(flow));
     }
 
-    let code = generate_token(24);
-    let request = crate::entity::authorization::Create {
-        code: code.as_str(),
-        state: params.state.as_str(),
-        scope: params.scope.as_deref(),
-        code_challenge: params.code_challenge.as_str(),
-        code_challenge_method: params.code_challenge_method, // S256
-        response_type: params.response_type,                 // code
-        client_id: params.client_id,
-        user_id: user.id,
-        time_to_live: AUTHORIZATION_TTL,
-    };
-    request.execute(&mut *tx).await?;
+    // OpenID Connect Core §2: the user authenticated once the password is checked
+    let authenticated_at = chrono::Utc::now();
+    let response = flow
+        .complete(&mut tx, &app, user.id, authenticated_at)
+        .await?;
     tx.commit().await?;
 
-    let redirection_url = encode_url(
-        &params.redirect_uri,
-        [("code", code.as_str()), ("state", params.state.as_str())].into_iter(),
-    );
-    let redirection = tekitoi_ui::view::redirect::View::new(redirection_url).render();
-    Ok(Html(redirection))
+    Ok(response)
 }
@@ -1,2 +1,270 @@
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse};
+use tekitoi_ui::view::View;
+use uuid::Uuid;
+
+use crate::helper::generate_token;
+use crate::router::ui::authorize::{find_client, BaseQueryParams, AUTHORIZATION_TTL};
+use crate::router::ui::error::Error;
+use crate::router::ui::helper::encode_url;
+
 pub(crate) mod credentials;
 pub(crate) mod profiles;
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[cfg_attr(test, derive(Debug))]
+pub(crate) struct DeviceQueryParams {
+    pub user_code: String,
+}
+
+// RFC 9126 §4: the authorization parameters have been pushed by the client beforehand
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[cfg_attr(test, derive(Debug))]
+pub(crate) struct PushedQueryParams {
+    pub client_id: Uuid,
+    pub request_uri: String,
+}
+
+impl PushedQueryParams {
+    pub(crate) async fn parameters(
+        &self,
+        conn: &mut sqlx::SqliteConnection,
+    ) -> Result<BaseQueryParams, FlowError> {
+        let found = crate::entity::pushed_authorization::FindByRequestUri::new(&self.request_uri)
+            .execute(&mut *conn)
+            .await?
+            .ok_or(FlowError::RequestUriNotFound)?;
+        if found.client_id != self.client_id {
+            tracing::warn!(message = "request uri used by another client", client_id = %self.client_id);
+            return Err(FlowError::RequestUriNotFound);
+        }
+        serde_urlencoded::from_str(&found.parameters).map_err(|err| {
+            tracing::error!(message = "unable to decode pushed parameters", source = %err);
+            FlowError::Database
+        })
+    }
+}
+
+// What the user is authenticating for, carried between the login page and the login routes
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[cfg_attr(test, derive(Debug))]
+#[serde(untagged)]
+pub(crate) enum Flow {
+    Device(DeviceQueryParams),
+    Pushed(PushedQueryParams),
+    Authorization(BaseQueryParams),
+}
+
+pub(crate) enum FlowError {
+    ApplicationNotFound,
+    InvalidRedirectUri,
+    InvalidScope,
+    InvalidResource,
+    InvalidCodeChallenge,
+    DeviceCodeNotFound,
+    RequestUriNotFound,
+    PushedAuthorizationRequired,
+    Database,
+}
+
+impl From<sqlx::Error> for FlowError {
+    fn from(value: sqlx::Error) -> Self {
+        tracing::error!(message = "database interaction failed", error = %value);
+        Self::Database
+    }
+}
+
+impl FlowError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::ApplicationNotFound | Self::DeviceCodeNotFound | Self::RequestUriNotFound => {
+                StatusCode::NOT_FOUND
+            }
+            Self::InvalidRedirectUri
+            | Self::InvalidScope
+            | Self::InvalidResource
+            | Self::InvalidCodeChallenge
+            | Self::PushedAuthorizationRequired => StatusCode::BAD_REQUEST,
+            Self::Database => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn message(&self) -> &'static str {
+        match self {
+            Self::ApplicationNotFound => "Application not found with provided client ID.",
+            Self::InvalidRedirectUri => "The provided redirect URI is invalid.",
+            Self::InvalidScope => "The requested scope is invalid.",
+            Self::InvalidResource => "The requested resource is invalid.",
+            Self::InvalidCodeChallenge => "The code challenge is missing or not supported.",
+            Self::DeviceCodeNotFound => "The provided device code is invalid or expired.",
+            Self::RequestUriNotFound => "The provided request URI is invalid or expired.",
+            Self::PushedAuthorizationRequired => {
+                "The authorization request must be pushed by the application."
+            }
+            Self::Database => "Something went wrong...",
+        }
+    }
+}
+
+impl IntoResponse for FlowError {
+    fn into_response(self) -> axum::response::Response {
+        Error::new(self.status(), self.message()).into_response()
+    }
+}
+
+impl Flow {
+    pub(crate) fn login_url(&self) -> Result<String, serde_urlencoded::ser::Error> {
+        let params = serde_urlencoded::to_string(self)?;
+        Ok(match self {
+            Self::Device(_) => format!("/device?{params}"),
+            Self::Pushed(_) | Self::Authorization(_) => format!("/authorize?{params}"),
+        })
+    }
+
+    pub(crate) async fn application(
+        &self,
+        conn: &mut sqlx::SqliteConnection,
+    ) -> Result<crate::entity::application::Entity, FlowError> {
+        match self {
+            Self::Device(params) => {
+                let user_code = crate::helper::normalize_user_code(&params.user_code);
+                let client_id =
+                    crate::entity::device_authorization::FindPendingByUserCode::new(&user_code)
+                        .execute(&mut *conn)
+                        .await?
+                        .ok_or(FlowError::DeviceCodeNotFound)?
+                        .client_id;
+                let app = crate::entity::application::FindById::new(client_id)
+                    .execute(&mut *conn)
+                    .await?;
+                app.ok_or(FlowError::ApplicationNotFound)
+            }
+            Self::Pushed(pushed) => {
+                let params = pushed.parameters(&mut *conn).await?;
+                find_client(conn, params.client_id, &params.redirect_uri).await
+            }
+            Self::Authorization(params) => {
+                let app = find_client(conn, params.client_id, &params.redirect_uri).await?;
+                if app.require_pushed_authorization {
+                    return Err(FlowError::PushedAuthorizationRequired);
+                }
+                Ok(app)
+            }
+        }
+    }
+
+    pub(crate) async fn complete(
+        &self,
+        conn: &mut sqlx::SqliteConnection,
+        app: &crate::entity::application::Entity,
+        user_id: Uuid,
+        authenticated_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Html<String>, FlowError> {
+        match self {
+            Self::Device(params) => {
+                let user_code = crate::helper::normalize_user_code(&params.user_code);
+                if !crate::entity::device_authorization::Approve::new(&user_code, user_id)
+                    .execute(&mut *conn)
+                    .await?
+                {
+                    return Err(FlowError::DeviceCodeNotFound);
+                }
+                Ok(Html(
+                    tekitoi_ui::view::device::completed::View::default().render(),
+                ))
+            }
+            Self::Pushed(pushed) => {
+                let params = pushed.parameters(&mut *conn).await?;
+                if !crate::entity::pushed_authorization::Consume::new(&pushed.request_uri)
+                    .execute(&mut *conn)
+                    .await?
+                {
+                    return Err(FlowError::RequestUriNotFound);
+                }
+                authorize(conn, app, user_id, authenticated_at, &params).await
+            }
+            Self::Authorization(params) => {
+                authorize(conn, app, user_id, authenticated_at, params).await
+            }
+        }
+    }
+}
+
+async fn authorize(
+    conn: &mut sqlx::SqliteConnection,
+    app: &crate::entity::application::Entity,
+    user_id: Uuid,
+    authenticated_at: chrono::DateTime<chrono::Utc>,
+    params: &BaseQueryParams,
+) -> Result<Html<String>, FlowError> {
+    // the parameters have been checked on the authorize page, it's only checked again here
+    let code_challenge_method = app
+        .check_code_challenge(
+            params.code_challenge.as_deref(),
+            params.code_challenge_method,
+        )
+        .map_err(|err| {
+            tracing::warn!(message = "invalid code challenge", source = %err);
+            FlowError::InvalidCodeChallenge
+        })?;
+    let scope = app
+        .grant_scope(Some(params.scope.as_deref().unwrap_or_default()))
+        .map_err(|err| {
+            tracing::warn!(message = "invalid scope", source = %err);
+            FlowError::InvalidScope
+        })?;
+    let resource = app
+        .grant_resource(params.resource.as_deref())
+        .map_err(|err| {
+            tracing::warn!(message = "invalid resource", source = %err);
+            FlowError::InvalidResource
+        })?;
+    let consent_pending = app.require_consent
+        && crate::entity::consent::FindByScope::new(
+            user_id,
+            app.id,
+            scope.as_deref().unwrap_or_default(),
+        )
+        .execute(&mut *conn)
+        .await?
+        .is_none();
+
+    let code = generate_token(24);
+    let request = crate::entity::authorization::Create {
+        code: code.as_str(),
+        state: params.state.as_str(),
+        scope: scope.as_deref(),
+        code_challenge: params.code_challenge.as_deref(),
+        code_challenge_method,               // S256
+        response_type: params.response_type, // code
+        redirect_uri: params.redirect_uri.as_str(),
+        consent_pending,
+        resource,
+        nonce: params.nonce.as_deref(),
+        client_id: params.client_id,
+        user_id,
+        authenticated_at,
+        time_to_live: AUTHORIZATION_TTL,
+    };
+    request.execute(&mut *conn).await?;
+
+    if consent_pending {
+        // the code is only given once the user approved the request
+        let consent_id = generate_token(32);
+        crate::entity::authorization::StartConsent::new(&code, &consent_id)
+            .execute(&mut *conn)
+            .await?;
+        let mut view = tekitoi_ui::view::consent::View::new("/authorize/consent", consent_id);
+        for item in scope.iter().flat_map(|value| value.split_whitespace()) {
+            view.add_scope(item.to_string());
+        }
+        return Ok(Html(view.render()));
+    }
+
+    Ok(code_redirection(&params.redirect_uri, &code, &params.state))
+}
+
+pub(crate) fn code_redirection(redirect_uri: &str, code: &str, state: &str) -> Html<String> {
+    let redirection_url = encode_url(redirect_uri, [("code", code), ("state", state)].into_iter());
+    Html(tekitoi_ui::view::redirect::View::new(redirection_url).render())
+}
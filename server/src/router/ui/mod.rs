@@ -1,16 +1,24 @@
 use axum::routing::{get, post};
 
 pub(super) mod authorize;
+pub(super) mod client_error;
+mod consent;
+mod device;
 mod error;
 mod helper;
 mod login;
 
+pub(super) const AUTHORIZE_PATH: &str = "/authorize";
+
 pub(super) fn router() -> axum::Router {
     axum::Router::new()
-        .route("/authorize", get(authorize::handle))
+        .route(AUTHORIZE_PATH, get(authorize::handle))
+        .route("/authorize/cancel", get(authorize::cancel))
+        .route("/authorize/consent", post(consent::handle))
         .route(
             "/authorize/credentials/login",
             post(login::credentials::handle),
         )
         .route("/authorize/profiles/login", get(login::profiles::handle))
+        .route("/device", get(device::handle))
 }
@@ -0,0 +1,266 @@
+use std::collections::BTreeSet;
+
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Extension, Json};
+
+use super::api::{
+    supported_grant_types, ACCESS_TOKEN_PATH, DEVICE_AUTHORIZATION_PATH, INTROSPECT_PATH,
+    PUSHED_AUTHORIZATION_PATH, REGISTER_PATH, REVOKE_PATH, USER_INFO_PATH,
+};
+use super::ui::AUTHORIZE_PATH;
+use super::API_PATH;
+use crate::entity::application::ApplicationKind;
+use crate::entity::code_challenge::CodeChallengeMethod;
+use crate::entity::response_type::ResponseType;
+use crate::jose::{Algorithm, JwkSet, SUPPORTED_ALGORITHMS};
+use crate::service::base_url::BaseUrl;
+use crate::service::keys::Keys;
+
+const JWKS_PATH: &str = "/.well-known/jwks.json";
+
+// RFC 8414 §2
+#[derive(serde::Serialize)]
+pub(crate) struct Metadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    device_authorization_endpoint: String,
+    pushed_authorization_request_endpoint: String,
+    registration_endpoint: String,
+    introspection_endpoint: String,
+    revocation_endpoint: String,
+    userinfo_endpoint: String,
+    jwks_uri: String,
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    scopes_supported: BTreeSet<String>,
+    response_types_supported: Vec<ResponseType>,
+    grant_types_supported: Vec<&'static str>,
+    code_challenge_methods_supported: Vec<CodeChallengeMethod>,
+    token_endpoint_auth_methods_supported: Vec<&'static str>,
+    revocation_endpoint_auth_methods_supported: Vec<&'static str>,
+    introspection_endpoint_auth_methods_supported: Vec<&'static str>,
+    request_parameter_supported: bool,
+    request_uri_parameter_supported: bool,
+    request_object_signing_alg_values_supported: Vec<Algorithm>,
+    require_pushed_authorization_requests: bool,
+    dpop_signing_alg_values_supported: Vec<Algorithm>,
+    // OpenID Connect Discovery §3
+    subject_types_supported: Vec<&'static str>,
+    id_token_signing_alg_values_supported: Vec<Algorithm>,
+}
+
+async fn handle_authorization_server(
+    Extension(database): Extension<crate::service::database::Pool>,
+    Extension(base_url): Extension<BaseUrl>,
+    Extension(keys): Extension<Keys>,
+) -> Result<Json<Metadata>, StatusCode> {
+    let applications = crate::entity::application::List
+        .execute(database.as_ref())
+        .await
+        .map_err(|err| {
+            tracing::error!(message = "database interaction failed", error = %err);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let api_url = |path: &str| base_url.join(&format!("{API_PATH}{path}"));
+
+    let mut code_challenge_methods = vec![CodeChallengeMethod::S256];
+    if applications.iter().any(|app| app.allow_plain_pkce) {
+        code_challenge_methods.push(CodeChallengeMethod::Plain);
+    }
+    // the public applications only identify themselves
+    let mut client_auth_methods = vec!["client_secret_basic", "client_secret_post"];
+    if applications
+        .iter()
+        .any(|app| app.kind == ApplicationKind::Public)
+    {
+        client_auth_methods.push("none");
+    }
+
+    Ok(Json(Metadata {
+        issuer: base_url.as_ref().to_string(),
+        authorization_endpoint: base_url.join(AUTHORIZE_PATH),
+        token_endpoint: api_url(ACCESS_TOKEN_PATH),
+        device_authorization_endpoint: api_url(DEVICE_AUTHORIZATION_PATH),
+        pushed_authorization_request_endpoint: api_url(PUSHED_AUTHORIZATION_PATH),
+        registration_endpoint: api_url(REGISTER_PATH),
+        introspection_endpoint: api_url(INTROSPECT_PATH),
+        revocation_endpoint: api_url(REVOKE_PATH),
+        userinfo_endpoint: api_url(USER_INFO_PATH),
+        jwks_uri: base_url.join(JWKS_PATH),
+        scopes_supported: applications
+            .iter()
+            .flat_map(|app| app.scopes.iter().cloned())
+            .collect(),
+        response_types_supported: vec![ResponseType::Code],
+        grant_types_supported: supported_grant_types(
+            applications.iter().any(|app| app.allow_password_grant),
+            applications.iter().any(|app| app.allow_token_exchange),
+        ),
+        code_challenge_methods_supported: code_challenge_methods,
+        token_endpoint_auth_methods_supported: client_auth_methods.clone(),
+        revocation_endpoint_auth_methods_supported: client_auth_methods,
+        // RFC 7662 §2.1: only confidential applications can introspect tokens
+        introspection_endpoint_auth_methods_supported: vec![
+            "client_secret_basic",
+            "client_secret_post",
+        ],
+        request_parameter_supported: true,
+        request_uri_parameter_supported: true,
+        request_object_signing_alg_values_supported: SUPPORTED_ALGORITHMS.to_vec(),
+        require_pushed_authorization_requests: !applications.is_empty()
+            && applications
+                .iter()
+                .all(|app| app.require_pushed_authorization),
+        dpop_signing_alg_values_supported: SUPPORTED_ALGORITHMS.to_vec(),
+        subject_types_supported: vec!["public"],
+        id_token_signing_alg_values_supported: keys.algorithm().into_iter().collect(),
+    }))
+}
+
+// RFC 7517 §5: the keys verifying the tokens signed by the server
+async fn handle_jwks(Extension(keys): Extension<Keys>) -> Json<JwkSet> {
+    Json(keys.public_keys())
+}
+
+pub(super) fn router() -> axum::Router {
+    axum::Router::new()
+        .route(
+            "/oauth-authorization-server",
+            get(handle_authorization_server),
+        )
+        // OpenID Connect Discovery §4: the same metadata for the relying parties
+        .route("/openid-configuration", get(handle_authorization_server))
+        .route("/jwks.json", get(handle_jwks))
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt; // for `collect`
+
+    use crate::service::dataset::CLIENT_ID;
+
+    async fn metadata(app: &crate::app::Application) -> serde_json::Value {
+        document(app, "/.well-known/oauth-authorization-server").await
+    }
+
+    async fn document(app: &crate::app::Application, uri: &str) -> serde_json::Value {
+        let req = Request::builder()
+            .uri(uri)
+            .method("GET")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_describe_authorization_server() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+
+        let body = metadata(&app).await;
+        assert_eq!(body["issuer"], "http://localhost:8080");
+        assert_eq!(
+            body["authorization_endpoint"],
+            "http://localhost:8080/authorize"
+        );
+        assert_eq!(
+            body["token_endpoint"],
+            "http://localhost:8080/api/access-token"
+        );
+        assert_eq!(
+            body["jwks_uri"],
+            "http://localhost:8080/.well-known/jwks.json"
+        );
+        assert_eq!(body["response_types_supported"][0], "code");
+        let grant_types = body["grant_types_supported"].as_array().unwrap();
+        assert!(grant_types.contains(&serde_json::Value::from("authorization_code")));
+        assert!(grant_types.contains(&serde_json::Value::from(
+            "urn:ietf:params:oauth:grant-type:token-exchange"
+        )));
+        assert!(body["code_challenge_methods_supported"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::Value::from("S256")));
+    }
+
+    #[tokio::test]
+    async fn should_follow_application_configuration() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+        sqlx::query(
+            "update applications set allow_password_grant = false, allow_plain_pkce = false",
+        )
+        .execute(app.database())
+        .await
+        .unwrap();
+
+        let body = metadata(&app).await;
+        let grant_types = body["grant_types_supported"].as_array().unwrap();
+        assert!(!grant_types.contains(&serde_json::Value::from("password")));
+        assert_eq!(
+            body["code_challenge_methods_supported"],
+            serde_json::json!(["S256"])
+        );
+
+        sqlx::query("update applications set allow_password_grant = true where id = $1")
+            .bind(CLIENT_ID)
+            .execute(app.database())
+            .await
+            .unwrap();
+        let body = metadata(&app).await;
+        let grant_types = body["grant_types_supported"].as_array().unwrap();
+        assert!(grant_types.contains(&serde_json::Value::from("password")));
+    }
+
+    #[tokio::test]
+    async fn should_describe_openid_provider() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+
+        let body = document(&app, "/.well-known/openid-configuration").await;
+        assert_eq!(body, metadata(&app).await);
+        assert_eq!(
+            body["subject_types_supported"],
+            serde_json::json!(["public"])
+        );
+        assert_eq!(
+            body["id_token_signing_alg_values_supported"],
+            serde_json::json!(["EdDSA"])
+        );
+        assert!(body["scopes_supported"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::Value::from("openid")));
+    }
+
+    #[tokio::test]
+    async fn should_publish_signing_keys() {
+        crate::enable_tracing();
+        let app = crate::app::Application::test().await;
+
+        let req = Request::builder()
+            .uri("/.well-known/jwks.json")
+            .method("GET")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.handle(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        let jwks: crate::jose::JwkSet = serde_json::from_slice(&body).unwrap();
+        assert_eq!(jwks, app.keys().public_keys());
+
+        let token = app
+            .keys()
+            .sign("at+jwt", &serde_json::json!({ "sub": "alice" }))
+            .unwrap();
+        let (_, claims): (_, serde_json::Value) = jwks.verify(&token).unwrap();
+        assert_eq!(claims["sub"], "alice");
+    }
+}
@@ -0,0 +1,20 @@
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+pub(crate) struct BaseUrl(Arc<str>);
+
+impl BaseUrl {
+    pub(crate) fn new(value: impl AsRef<str>) -> Self {
+        Self(Arc::from(value.as_ref().trim_end_matches('/')))
+    }
+
+    pub(crate) fn join(&self, path: &str) -> String {
+        format!("{}{path}", self.0)
+    }
+}
+
+impl AsRef<str> for BaseUrl {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
@@ -4,6 +4,9 @@ use std::path::{Path, PathBuf};
 use anyhow::Context;
 use uuid::Uuid;
 
+use crate::entity::application::ApplicationKind;
+use crate::jose::JwkSet;
+
 mod credentials;
 mod profiles;
 
@@ -14,6 +17,10 @@ pub(crate) const CLIENT_SECRET: &str = "secret";
 #[cfg(test)]
 pub(crate) const REDIRECT_URI: &str = "http://service/redirect";
 #[cfg(test)]
+pub(crate) const RESOURCE: &str = "http://service/api";
+#[cfg(test)]
+pub(crate) const LOOPBACK_REDIRECT_URI: &str = "http://127.0.0.1/redirect";
+#[cfg(test)]
 pub(crate) const ALICE_ID: Uuid = Uuid::from_u128(0x00000000000000000000000000000000u128);
 #[cfg(test)]
 pub(crate) const BOB_ID: Uuid = Uuid::from_u128(0x00000000000000000000000000000001u128);
@@ -71,11 +78,59 @@ impl RootConfig {
         tracing::debug!("executing synchro");
         let mut tx = database.as_ref().begin().await?;
         for app in self.applications.iter() {
+            let redirect_uris = app.redirect_uris();
+            if redirect_uris.is_empty() {
+                anyhow::bail!("application {} has no redirect uri", app.client_id);
+            }
+            match app.kind() {
+                ApplicationKind::Public if !app.client_secrets.is_empty() => {
+                    anyhow::bail!("public application {} can't have secrets", app.client_id);
+                }
+                ApplicationKind::Public if !app.require_pkce => {
+                    anyhow::bail!("public application {} must require PKCE", app.client_id);
+                }
+                ApplicationKind::Confidential if app.client_secrets.is_empty() => {
+                    anyhow::bail!("confidential application {} has no secret", app.client_id);
+                }
+                _ => {}
+            }
+            let implicit_scopes;
+            let scopes = match app.scopes.as_deref() {
+                Some(scopes) => scopes,
+                None => {
+                    tracing::warn!(
+                        message = "application doesn't declare its scopes, only the OpenID Connect ones are allowed, this fallback is deprecated",
+                        client_id = %app.client_id,
+                    );
+                    implicit_scopes = IMPLICIT_SCOPES.map(String::from);
+                    &implicit_scopes
+                }
+            };
+            if let Some(scope) = app
+                .default_scopes
+                .iter()
+                .find(|scope| !scopes.contains(scope))
+            {
+                anyhow::bail!(
+                    "application {} has an undeclared default scope {scope:?}",
+                    app.client_id
+                );
+            }
             let created = crate::entity::application::Upsert::new(
                 app.client_id,
                 &app.client_secrets,
-                &app.redirect_uri,
+                &redirect_uris,
             )
+            .with_kind(app.kind())
+            .with_password_grant(app.allow_password_grant)
+            .with_scopes(scopes, &app.default_scopes)
+            .with_require_consent(app.require_consent)
+            .with_require_pushed_authorization(app.require_pushed_authorization)
+            .with_jwks(&app.jwks)
+            .with_pkce(app.require_pkce, app.allow_plain_pkce)
+            .with_resources(&app.resources)
+            .with_jwt_access_token(app.jwt_access_token)
+            .with_token_exchange(app.allow_token_exchange)
             .execute(&mut *tx)
             .await?;
 
@@ -94,8 +149,26 @@ impl RootConfig {
         RootConfig {
             applications: vec![ApplicationConfig {
                 client_id: CLIENT_ID,
-                redirect_uri: REDIRECT_URI.into(),
+                kind: None,
+                redirect_uri: None,
+                redirect_uris: vec![REDIRECT_URI.into(), LOOPBACK_REDIRECT_URI.into()],
                 client_secrets: HashSet::from_iter([CLIENT_SECRET.into()]),
+                allow_password_grant: true,
+                scopes: Some(vec![
+                    "openid".into(),
+                    "profile".into(),
+                    "email".into(),
+                    "service".into(),
+                ]),
+                default_scopes: vec!["profile".into()],
+                require_consent: false,
+                require_pushed_authorization: false,
+                jwks: JwkSet::default(),
+                require_pkce: true,
+                allow_plain_pkce: true,
+                resources: vec![RESOURCE.into()],
+                jwt_access_token: false,
+                allow_token_exchange: true,
                 providers: vec![
                     Provider::Profiles(profiles::Config::test()),
                     Provider::Credentials(credentials::Config::test()),
@@ -108,11 +181,75 @@ impl RootConfig {
 #[derive(serde::Deserialize)]
 struct ApplicationConfig {
     client_id: Uuid,
-    redirect_uri: String,
+    #[serde(default, rename = "type")]
+    kind: Option<ApplicationKind>,
+    // kept for configurations written before multiple redirect uris were supported
+    #[serde(default)]
+    redirect_uri: Option<String>,
+    #[serde(default)]
+    redirect_uris: Vec<String>,
     client_secrets: HashSet<String>,
+    // the resource owner password grant is deprecated, it has to be enabled explicitly
+    #[serde(default)]
+    allow_password_grant: bool,
+    // the scopes the client is allowed to request, the older configurations without it
+    // fall back to the OpenID Connect scopes
+    scopes: Option<Vec<String>>,
+    // the scopes granted when the client doesn't request any
+    #[serde(default)]
+    default_scopes: Vec<String>,
+    // ask the user to approve the requested scopes, the approval is remembered
+    #[serde(default)]
+    require_consent: bool,
+    // the authorization parameters can only be provided through the pushed authorization endpoint
+    #[serde(default)]
+    require_pushed_authorization: bool,
+    // the public keys used to verify the request objects signed by the client
+    #[serde(default)]
+    jwks: JwkSet,
+    // confidential clients can opt out of PKCE, public clients can't
+    #[serde(default = "enabled")]
+    require_pkce: bool,
+    // OAuth 2.1 §4.1.1: the plain method can be refused to only accept S256
+    #[serde(default = "enabled")]
+    allow_plain_pkce: bool,
+    // RFC 8707: the resources the client can request a token for, used as audience
+    #[serde(default)]
+    resources: Vec<String>,
+    // RFC 9068: the access tokens are signed JWTs instead of opaque strings
+    #[serde(default)]
+    jwt_access_token: bool,
+    // RFC 8693: the client can exchange the tokens it received for other ones
+    #[serde(default)]
+    allow_token_exchange: bool,
     providers: Vec<Provider>,
 }
 
+// the scopes understood by the server itself, allowed when an application doesn't declare any
+const IMPLICIT_SCOPES: [&str; 3] = ["openid", "profile", "email"];
+
+const fn enabled() -> bool {
+    true
+}
+
+impl ApplicationConfig {
+    fn kind(&self) -> ApplicationKind {
+        self.kind.unwrap_or(if self.client_secrets.is_empty() {
+            ApplicationKind::Public
+        } else {
+            ApplicationKind::Confidential
+        })
+    }
+
+    fn redirect_uris(&self) -> Vec<String> {
+        self.redirect_uri
+            .iter()
+            .chain(self.redirect_uris.iter())
+            .cloned()
+            .collect()
+    }
+}
+
 #[derive(serde::Deserialize)]
 #[serde(tag = "type", rename_all = "kebab-case")]
 enum Provider {
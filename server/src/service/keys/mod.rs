@@ -0,0 +1,394 @@
+use std::path::PathBuf;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+
+use anyhow::Context;
+
+use crate::entity::signing_key::{Create, DeleteRetired, ListPublished, RetireOthers};
+use crate::helper::{from_env_or, parse_env_or};
+use crate::jose::{Algorithm, Header, JoseError, JwkSet};
+
+mod signing_key;
+
+use signing_key::SigningKey;
+
+const DAY: u64 = 60 * 60 * 24;
+// how often the age of the generated key is checked
+const ROTATION_CHECK_INTERVAL: Duration = Duration::new(60 * 60, 0);
+// a new key is published a day before signing, so that the cached key sets get refreshed
+const ACTIVATION_DELAY: Duration = Duration::new(DAY, 0);
+
+// The generated keys are replaced once older than the interval, the new ones sign
+// after the activation delay and the previous ones remain published during the retention,
+// until the tokens they signed have expired
+#[derive(Clone, Copy, Debug)]
+struct Rotation {
+    algorithm: Algorithm,
+    interval: Duration,
+    activation: Duration,
+    retention: Duration,
+}
+
+impl Rotation {
+    fn new(algorithm: Algorithm, interval: Duration, retention: Duration) -> anyhow::Result<Self> {
+        if interval < ACTIVATION_DELAY {
+            anyhow::bail!(
+                "SIGNING_KEY_ROTATION_DAYS is shorter than the activation delay of the new keys (1 day)"
+            );
+        }
+        if retention < crate::router::ACCESS_TOKEN_TTL {
+            anyhow::bail!(
+                "SIGNING_KEY_RETENTION_DAYS is shorter than the lifetime of the access tokens (1 day)"
+            );
+        }
+        Ok(Self {
+            algorithm,
+            interval,
+            activation: ACTIVATION_DELAY,
+            retention,
+        })
+    }
+
+    // the most recent key that is old enough, the oldest one when none is yet
+    fn signing_index(&self, published: &[crate::entity::signing_key::Entity]) -> Option<usize> {
+        let now = chrono::Utc::now();
+        published
+            .iter()
+            .position(|entity| entity.created_at + self.activation <= now)
+            .or(published.len().checked_sub(1))
+    }
+}
+
+pub(crate) struct Config {
+    paths: Vec<PathBuf>,
+    rotation: Rotation,
+}
+
+#[cfg(test)]
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            rotation: Rotation {
+                algorithm: Algorithm::EdDSA,
+                interval: Duration::from_secs(30 * DAY),
+                activation: ACTIVATION_DELAY,
+                retention: Duration::from_secs(2 * DAY),
+            },
+        }
+    }
+}
+
+impl Config {
+    pub(crate) fn from_env() -> anyhow::Result<Self> {
+        let algorithm = match from_env_or("SIGNING_KEY_ALGORITHM", "EdDSA").as_ref() {
+            "EdDSA" => Algorithm::EdDSA,
+            "ES256" => Algorithm::ES256,
+            "ES384" => Algorithm::ES384,
+            other => anyhow::bail!(
+                "unsupported SIGNING_KEY_ALGORITHM={other:?}, expected EdDSA, ES256 or ES384"
+            ),
+        };
+        let paths = std::env::var("SIGNING_KEY_PATHS")
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|path| !path.is_empty())
+                    .map(PathBuf::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            paths,
+            rotation: Rotation::new(
+                algorithm,
+                Duration::from_secs(parse_env_or("SIGNING_KEY_ROTATION_DAYS", 30)? * DAY),
+                Duration::from_secs(parse_env_or("SIGNING_KEY_RETENTION_DAYS", 2)? * DAY),
+            )?,
+        })
+    }
+
+    pub(crate) async fn build(
+        self,
+        database: &crate::service::database::Pool,
+    ) -> anyhow::Result<Keys> {
+        // the provided keys are rotated by changing the files
+        if !self.paths.is_empty() {
+            let keys = self
+                .paths
+                .iter()
+                .map(|path| {
+                    let content = std::fs::read_to_string(path)
+                        .with_context(|| format!("reading signing key {path:?}"))?;
+                    SigningKey::from_pem(&content)
+                        .with_context(|| format!("loading signing key {path:?}"))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            return Ok(Keys {
+                inner: Arc::new(RwLock::new(keys)),
+                rotation: None,
+            });
+        }
+
+        let keys = Keys {
+            inner: Arc::new(RwLock::new(Vec::new())),
+            rotation: Some(self.rotation),
+        };
+        keys.rotate(database).await?;
+        Ok(keys)
+    }
+}
+
+// The keys signing the tokens issued by the server
+#[derive(Clone)]
+pub(crate) struct Keys {
+    // the first key signs the tokens, the others are only published
+    inner: Arc<RwLock<Vec<SigningKey>>>,
+    rotation: Option<Rotation>,
+}
+
+impl Keys {
+    // generates a new key when the current one is too old, and drops the retired ones
+    pub(crate) async fn rotate(
+        &self,
+        database: &crate::service::database::Pool,
+    ) -> anyhow::Result<()> {
+        let Some(rotation) = self.rotation else {
+            return Ok(());
+        };
+        let now = chrono::Utc::now();
+        let mut tx = database.as_ref().begin().await?;
+        let published = ListPublished.execute(&mut *tx).await?;
+        if published
+            .first()
+            .is_none_or(|current| current.created_at + rotation.interval <= now)
+        {
+            let private_key = SigningKey::generate(rotation.algorithm)?;
+            let key = SigningKey::from_pkcs8(&private_key)?;
+            Create {
+                kid: key.kid(),
+                private_key: &private_key,
+            }
+            .execute(&mut *tx)
+            .await?;
+            // the previous key signs until the new one is activated
+            RetireOthers::new(key.kid(), now + rotation.activation + rotation.retention)
+                .execute(&mut *tx)
+                .await?;
+            tracing::info!(message = "signing key generated", kid = %key.kid());
+        }
+        DeleteRetired.execute(&mut *tx).await?;
+        let mut published = ListPublished.execute(&mut *tx).await?;
+        tx.commit().await?;
+
+        // the signing key goes first, the keys waiting for their activation are only published
+        if let Some(index) = rotation.signing_index(&published) {
+            published[..=index].rotate_right(1);
+        }
+        let keys = published
+            .iter()
+            .map(|entity| {
+                tracing::debug!(
+                    message = "signing key published",
+                    kid = %entity.kid,
+                    retired_at = ?entity.retired_at,
+                );
+                SigningKey::from_pkcs8(&entity.private_key)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        *self.inner.write().unwrap_or_else(PoisonError::into_inner) = keys;
+        Ok(())
+    }
+
+    pub(crate) fn schedule_rotation(&self, database: crate::service::database::Pool) {
+        if self.rotation.is_none() {
+            return;
+        }
+        let keys = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(ROTATION_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(err) = keys.rotate(&database).await {
+                    tracing::error!(message = "unable to rotate signing keys", error = %err);
+                }
+            }
+        });
+    }
+
+    pub(crate) fn sign<T: serde::Serialize>(
+        &self,
+        typ: &str,
+        claims: &T,
+    ) -> Result<String, JoseError> {
+        let keys = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+        let key = keys.first().ok_or(JoseError::KeyNotFound)?;
+        let header = Header {
+            alg: key.alg(),
+            kid: Some(key.kid().to_string()),
+            typ: Some(typ.to_string()),
+            jwk: None,
+        };
+        crate::jose::encode(&header, claims, |message| key.sign(message))
+    }
+
+    // the algorithm of the key signing the tokens
+    pub(crate) fn algorithm(&self) -> Option<Algorithm> {
+        let keys = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+        keys.first().map(SigningKey::alg)
+    }
+
+    pub(crate) fn verify<T: serde::de::DeserializeOwned>(
+        &self,
+        token: &str,
+    ) -> Result<(Header, T), JoseError> {
+        self.public_keys().verify(token)
+    }
+
+    // RFC 7517 §5: the public part of every published key
+    pub(crate) fn public_keys(&self) -> JwkSet {
+        let keys = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+        JwkSet {
+            keys: keys.iter().map(|key| key.public().clone()).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Keys {
+    // without key, every signature fails
+    pub(crate) fn empty() -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(Vec::new())),
+            rotation: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use super::Config;
+
+    async fn database() -> crate::service::database::Pool {
+        let database = crate::service::database::Config::default()
+            .build()
+            .await
+            .unwrap();
+        database.upgrade().await.unwrap();
+        database
+    }
+
+    fn kid(keys: &super::Keys, token: &str) -> Option<String> {
+        let (header, _) = keys.verify::<serde_json::Value>(token).unwrap();
+        header.kid
+    }
+
+    #[tokio::test]
+    async fn should_keep_generated_key() {
+        let database = database().await;
+        let first = Config::default().build(&database).await.unwrap();
+        let second = Config::default().build(&database).await.unwrap();
+        assert_eq!(first.public_keys(), second.public_keys());
+        assert_eq!(first.public_keys().keys.len(), 1);
+
+        let token = first.sign("at+jwt", &serde_json::json!({})).unwrap();
+        assert!(second.verify::<serde_json::Value>(&token).is_ok());
+    }
+
+    #[tokio::test]
+    async fn should_rotate_generated_keys() {
+        let database = database().await;
+        let mut config = Config::default();
+        config.rotation.interval = Duration::ZERO;
+        let keys = config.build(&database).await.unwrap();
+        let previous = keys.sign("at+jwt", &serde_json::json!({})).unwrap();
+
+        keys.rotate(&database).await.unwrap();
+        // the new key is published, but the previous one signs until its activation
+        assert_eq!(keys.public_keys().keys.len(), 2);
+        let pending = keys.sign("at+jwt", &serde_json::json!({})).unwrap();
+        assert_eq!(kid(&keys, &previous), kid(&keys, &pending));
+
+        sqlx::query("update signing_keys set created_at = $1 where kid != $2")
+            .bind(chrono::Utc::now() - super::ACTIVATION_DELAY)
+            .bind(kid(&keys, &previous))
+            .execute(database.as_ref())
+            .await
+            .unwrap();
+        sqlx::query("update signing_keys set created_at = $1 where kid = $2")
+            .bind(chrono::Utc::now() - super::ACTIVATION_DELAY * 2)
+            .bind(kid(&keys, &previous))
+            .execute(database.as_ref())
+            .await
+            .unwrap();
+        let keys = Config::default().build(&database).await.unwrap();
+        let current = keys.sign("at+jwt", &serde_json::json!({})).unwrap();
+        assert_ne!(kid(&keys, &previous), kid(&keys, &current));
+        // the previous key remains published during the retention
+        assert_eq!(keys.public_keys().keys.len(), 2);
+
+        // once the retention is over, the previous key is dropped
+        sqlx::query("update signing_keys set retired_at = $1 where retired_at is not null")
+            .bind(chrono::Utc::now())
+            .execute(database.as_ref())
+            .await
+            .unwrap();
+        let keys = Config::default().build(&database).await.unwrap();
+        assert_eq!(keys.public_keys().keys.len(), 1);
+        assert!(keys.verify::<serde_json::Value>(&previous).is_err());
+        assert!(keys.verify::<serde_json::Value>(&current).is_ok());
+    }
+
+    #[test]
+    fn should_reject_short_retention() {
+        let interval = Duration::from_secs(30 * super::DAY);
+        // the tokens signed by a retired key have to remain verifiable until they expire
+        assert!(super::Rotation::new(super::Algorithm::EdDSA, interval, Duration::ZERO).is_err());
+        assert!(super::Rotation::new(
+            super::Algorithm::EdDSA,
+            interval,
+            Duration::from_secs(super::DAY)
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn should_reject_short_interval() {
+        let retention = Duration::from_secs(2 * super::DAY);
+        // a key replaced before its activation would never sign
+        assert!(super::Rotation::new(super::Algorithm::EdDSA, Duration::ZERO, retention).is_err());
+        assert!(super::Rotation::new(
+            super::Algorithm::EdDSA,
+            Duration::from_secs(super::DAY),
+            retention
+        )
+        .is_ok());
+    }
+
+    #[tokio::test]
+    async fn should_load_key_files() {
+        let database = database().await;
+        let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/service/keys/fixtures");
+        let config = Config {
+            paths: vec![fixtures.join("rsa.pem"), fixtures.join("ed25519.pem")],
+            ..Config::default()
+        };
+        let keys = config.build(&database).await.unwrap();
+        let jwks = keys.public_keys();
+        assert_eq!(jwks.keys.len(), 2);
+
+        let token = keys.sign("at+jwt", &serde_json::json!({})).unwrap();
+        assert_eq!(kid(&keys, &token), jwks.keys[0].kid);
+
+        // nothing is generated in the database
+        let count: (i64,) = sqlx::query_as("select count(*) from signing_keys")
+            .fetch_one(database.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(count.0, 0);
+    }
+}
@@ -0,0 +1,214 @@
+use anyhow::Context;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use ring::rand::SystemRandom;
+use ring::signature::{
+    EcdsaKeyPair, Ed25519KeyPair, KeyPair, RsaKeyPair, RsaPublicKeyComponents,
+    ECDSA_P256_SHA256_FIXED_SIGNING, ECDSA_P384_SHA384_FIXED_SIGNING, RSA_PKCS1_SHA256,
+};
+
+use crate::jose::{Algorithm, JoseError, Jwk, JwkKey};
+
+enum Pair {
+    Rsa(RsaKeyPair),
+    Ecdsa(EcdsaKeyPair),
+    Ed25519(Ed25519KeyPair),
+}
+
+pub(super) struct SigningKey {
+    alg: Algorithm,
+    kid: String,
+    pair: Pair,
+    public: Jwk,
+}
+
+// RFC 7468 §2: the base64 content between the encapsulation boundaries
+fn decode_pem(content: &str) -> anyhow::Result<(&str, Vec<u8>)> {
+    let mut lines = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+    let label = lines
+        .next()
+        .and_then(|line| line.strip_prefix("-----BEGIN "))
+        .and_then(|line| line.strip_suffix("-----"))
+        .context("missing PEM header")?;
+    let footer = format!("-----END {label}-----");
+    let body: String = lines.take_while(|line| *line != footer).collect();
+    let der = STANDARD.decode(body).context("invalid PEM content")?;
+    Ok((label, der))
+}
+
+fn rsa_jwk(pair: &RsaKeyPair) -> JwkKey {
+    let components: RsaPublicKeyComponents<Vec<u8>> = pair.public().into();
+    JwkKey::Rsa {
+        n: URL_SAFE_NO_PAD.encode(components.n),
+        e: URL_SAFE_NO_PAD.encode(components.e),
+    }
+}
+
+fn ec_jwk(pair: &EcdsaKeyPair, crv: &str) -> JwkKey {
+    // SEC1 uncompressed point, both coordinates have the same length
+    let point = &pair.public_key().as_ref()[1..];
+    let (x, y) = point.split_at(point.len() / 2);
+    JwkKey::Ec {
+        crv: crv.to_string(),
+        x: URL_SAFE_NO_PAD.encode(x),
+        y: URL_SAFE_NO_PAD.encode(y),
+    }
+}
+
+impl SigningKey {
+    fn new(alg: Algorithm, pair: Pair) -> Self {
+        let key = match pair {
+            Pair::Rsa(ref inner) => rsa_jwk(inner),
+            Pair::Ecdsa(ref inner) if alg == Algorithm::ES384 => ec_jwk(inner, "P-384"),
+            Pair::Ecdsa(ref inner) => ec_jwk(inner, "P-256"),
+            Pair::Ed25519(ref inner) => JwkKey::Okp {
+                crv: "Ed25519".into(),
+                x: URL_SAFE_NO_PAD.encode(inner.public_key().as_ref()),
+            },
+        };
+        let mut public = Jwk {
+            kid: None,
+            alg: Some(alg),
+            key,
+        };
+        // RFC 7638 §1: the thumbprint is a stable identifier for the key
+        let kid = public.thumbprint();
+        public.kid = Some(kid.clone());
+        Self {
+            alg,
+            kid,
+            pair,
+            public,
+        }
+    }
+
+    // the key type is found by trying every supported one
+    pub(super) fn from_pkcs8(der: &[u8]) -> anyhow::Result<Self> {
+        let rng = SystemRandom::new();
+        if let Ok(pair) = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der) {
+            return Ok(Self::new(Algorithm::EdDSA, Pair::Ed25519(pair)));
+        }
+        if let Ok(pair) = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, der, &rng) {
+            return Ok(Self::new(Algorithm::ES256, Pair::Ecdsa(pair)));
+        }
+        if let Ok(pair) = EcdsaKeyPair::from_pkcs8(&ECDSA_P384_SHA384_FIXED_SIGNING, der, &rng) {
+            return Ok(Self::new(Algorithm::ES384, Pair::Ecdsa(pair)));
+        }
+        let pair = RsaKeyPair::from_pkcs8(der)
+            .map_err(|err| anyhow::anyhow!("unsupported private key: {err}"))?;
+        Ok(Self::new(Algorithm::RS256, Pair::Rsa(pair)))
+    }
+
+    pub(super) fn from_pem(content: &str) -> anyhow::Result<Self> {
+        let (label, der) = decode_pem(content)?;
+        match label {
+            "PRIVATE KEY" => Self::from_pkcs8(&der),
+            // RFC 8017 §A.1.2
+            "RSA PRIVATE KEY" => {
+                let pair = RsaKeyPair::from_der(&der)
+                    .map_err(|err| anyhow::anyhow!("invalid RSA private key: {err}"))?;
+                Ok(Self::new(Algorithm::RS256, Pair::Rsa(pair)))
+            }
+            other => anyhow::bail!(
+                "unsupported PEM label {other:?}, the key should be converted to PKCS#8"
+            ),
+        }
+    }
+
+    // RSA keys can't be generated, they have to be provided as files
+    pub(super) fn generate(alg: Algorithm) -> anyhow::Result<Vec<u8>> {
+        let rng = SystemRandom::new();
+        let document = match alg {
+            Algorithm::EdDSA => Ed25519KeyPair::generate_pkcs8(&rng),
+            Algorithm::ES256 => {
+                EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+            }
+            Algorithm::ES384 => {
+                EcdsaKeyPair::generate_pkcs8(&ECDSA_P384_SHA384_FIXED_SIGNING, &rng)
+            }
+            other => anyhow::bail!("unable to generate {} keys", other.as_str()),
+        }
+        .map_err(|err| anyhow::anyhow!("unable to generate signing key: {err}"))?;
+        Ok(document.as_ref().to_vec())
+    }
+
+    pub(super) fn alg(&self) -> Algorithm {
+        self.alg
+    }
+
+    pub(super) fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    pub(super) fn public(&self) -> &Jwk {
+        &self.public
+    }
+
+    pub(super) fn sign(&self, message: &[u8]) -> Result<Vec<u8>, JoseError> {
+        let rng = SystemRandom::new();
+        match self.pair {
+            Pair::Rsa(ref pair) => {
+                let mut signature = vec![0; pair.public().modulus_len()];
+                pair.sign(&RSA_PKCS1_SHA256, &rng, message, &mut signature)
+                    .map_err(|_| JoseError::Signing)?;
+                Ok(signature)
+            }
+            Pair::Ecdsa(ref pair) => pair
+                .sign(&rng, message)
+                .map(|signature| signature.as_ref().to_vec())
+                .map_err(|_| JoseError::Signing),
+            Pair::Ed25519(ref pair) => Ok(pair.sign(message).as_ref().to_vec()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SigningKey;
+    use crate::jose::{Algorithm, Header, JwkSet};
+
+    fn should_sign_with(key: SigningKey, alg: Algorithm) {
+        assert_eq!(key.alg(), alg);
+        let header = Header {
+            alg,
+            kid: Some(key.kid().to_string()),
+            typ: None,
+            jwk: None,
+        };
+        let token = crate::jose::encode(&header, &serde_json::json!({ "sub": "alice" }), |msg| {
+            key.sign(msg)
+        })
+        .unwrap();
+        let keys = JwkSet {
+            keys: vec![key.public().clone()],
+        };
+        let (_, claims): (_, serde_json::Value) = keys.verify(&token).unwrap();
+        assert_eq!(claims["sub"], "alice");
+    }
+
+    #[test]
+    fn should_load_pem_files() {
+        let key = SigningKey::from_pem(include_str!("fixtures/rsa.pem")).unwrap();
+        should_sign_with(key, Algorithm::RS256);
+        let key = SigningKey::from_pem(include_str!("fixtures/ec.pem")).unwrap();
+        should_sign_with(key, Algorithm::ES256);
+        let key = SigningKey::from_pem(include_str!("fixtures/ed25519.pem")).unwrap();
+        should_sign_with(key, Algorithm::EdDSA);
+
+        assert!(
+            SigningKey::from_pem("-----BEGIN CERTIFICATE-----\n-----END CERTIFICATE-----").is_err()
+        );
+    }
+
+    #[test]
+    fn should_generate_keys() {
+        for alg in [Algorithm::EdDSA, Algorithm::ES256, Algorithm::ES384] {
+            let der = SigningKey::generate(alg).unwrap();
+            should_sign_with(SigningKey::from_pkcs8(&der).unwrap(), alg);
+        }
+        assert!(SigningKey::generate(Algorithm::RS256).is_err());
+    }
+}
@@ -1,2 +1,5 @@
+pub(crate) mod base_url;
 pub(crate) mod database;
 pub(crate) mod dataset;
+pub(crate) mod keys;
+pub(crate) mod registration;
@@ -0,0 +1,18 @@
+use std::sync::Arc;
+
+// RFC 7591 §3: the registration can be restricted to the holders of an initial access token
+#[derive(Clone, Debug, Default)]
+pub(crate) struct InitialAccessToken(Option<Arc<str>>);
+
+impl InitialAccessToken {
+    pub(crate) fn new(value: Option<String>) -> Self {
+        Self(value.map(Arc::from))
+    }
+
+    pub(crate) fn accepts(&self, provided: Option<&str>) -> bool {
+        match self.0 {
+            Some(ref expected) => provided == Some(expected.as_ref()),
+            None => true,
+        }
+    }
+}
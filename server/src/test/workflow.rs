@@ -2,7 +2,7 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 
 use oauth2::reqwest::async_http_client;
-use oauth2::{AuthorizationCode, TokenResponse};
+use oauth2::{AuthorizationCode, StandardDeviceAuthorizationResponse, TokenResponse};
 use reqwest::Url;
 
 use crate::service::dataset::{CLIENT_ID, CLIENT_SECRET, REDIRECT_URI};
@@ -19,17 +19,40 @@ fn get_redirection_url(page: &str) -> Option<Url> {
     Url::parse(&page[index..(index + len)]).ok()
 }
 
+async fn wait_for_server(port: u16) {
+    let url = format!("http://localhost:{port}/api/status");
+    for _ in 0..50 {
+        if reqwest::get(&url).await.is_ok() {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("server didn't start on port {port}");
+}
+
 #[tokio::test]
 async fn should_authenticate() {
     let port = 9900;
     let app = crate::app::Application::test_with_port(port).await;
     let _handler = tokio::spawn(async move { app.run().await });
+    wait_for_server(port).await;
+
+    // RFC 8414: the endpoints are discovered from the metadata
+    let metadata: serde_json::Value = reqwest::get(format!(
+        "http://localhost:{port}/.well-known/oauth-authorization-server"
+    ))
+    .await
+    .unwrap()
+    .json()
+    .await
+    .unwrap();
+    let endpoint = |name: &str| metadata[name].as_str().unwrap().to_string();
 
     let client = oauth2::basic::BasicClient::new(
         oauth2::ClientId::new(CLIENT_ID.to_string()),
         Some(oauth2::ClientSecret::new(CLIENT_SECRET.into())),
-        oauth2::AuthUrl::new(format!("http://localhost:{port}/authorize")).unwrap(),
-        Some(oauth2::TokenUrl::new(format!("http://localhost:{port}/api/access-token")).unwrap()),
+        oauth2::AuthUrl::new(endpoint("authorization_endpoint")).unwrap(),
+        Some(oauth2::TokenUrl::new(endpoint("token_endpoint")).unwrap()),
     )
     // Set the URL the user will be redirected to after the authorization process.
     .set_redirect_uri(oauth2::RedirectUrl::new(REDIRECT_URI.to_string()).unwrap());
@@ -67,6 +90,73 @@ async fn should_authenticate() {
         .await
         .unwrap();
 
+    let _user: serde_json::Value = reqwest::Client::new()
+        .get(endpoint("userinfo_endpoint"))
+        .header(
+            "Authorization",
+            format!("Bearer {}", token.access_token().secret()),
+        )
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn should_authenticate_device() {
+    let port = 9901;
+    let app = crate::app::Application::test_with_port(port).await;
+    let _handler = tokio::spawn(async move { app.run().await });
+    wait_for_server(port).await;
+
+    let client = oauth2::basic::BasicClient::new(
+        oauth2::ClientId::new(CLIENT_ID.to_string()),
+        Some(oauth2::ClientSecret::new(CLIENT_SECRET.into())),
+        oauth2::AuthUrl::new(format!("http://localhost:{port}/authorize")).unwrap(),
+        Some(oauth2::TokenUrl::new(format!("http://localhost:{port}/api/access-token")).unwrap()),
+    )
+    .set_device_authorization_url(
+        oauth2::DeviceAuthorizationUrl::new(format!(
+            "http://localhost:{port}/api/device-authorization"
+        ))
+        .unwrap(),
+    );
+
+    let details: StandardDeviceAuthorizationResponse = client
+        .exchange_device_code()
+        .unwrap()
+        .request_async(async_http_client)
+        .await
+        .unwrap();
+    assert_eq!(
+        details.verification_uri().as_str(),
+        format!("http://localhost:{port}/device")
+    );
+
+    // the user opens the verification page on another device
+    let verification_url = details.verification_uri_complete().unwrap().secret();
+    let req = reqwest::get(verification_url.as_str()).await.unwrap();
+    let status = req.status();
+    let body = req.text().await.unwrap();
+    assert_eq!(status, reqwest::StatusCode::OK, "{body}");
+
+    let login_url = get_login_url(&body).unwrap();
+    let login_url = format!("http://localhost:{port}{login_url}");
+    let req = reqwest::get(login_url).await.unwrap();
+    let status = req.status();
+    let body = req.text().await.unwrap();
+    assert_eq!(status, reqwest::StatusCode::OK, "{body}");
+    assert!(body.contains("Device activated"), "{body}");
+
+    let token = client
+        .exchange_device_access_token(&details)
+        .request_async(async_http_client, tokio::time::sleep, None)
+        .await
+        .unwrap();
+
     let _user: serde_json::Value = reqwest::Client::new()
         .get(format!("http://localhost:{port}/api/user-info"))
         .header(
@@ -8,6 +8,7 @@ pub struct View {
     profiles: Option<profiles::Section>,
     credentials: Option<credentials::Section>,
     error: Option<String>,
+    cancel: Option<String>,
     style_path: Option<&'static str>,
 }
 
@@ -16,6 +17,10 @@ impl View {
         self.error = Some(error);
     }
 
+    pub fn set_cancel(&mut self, link: String) {
+        self.cancel = Some(link);
+    }
+
     pub fn set_credentials(&mut self, section: credentials::Section) {
         self.credentials = Some(section);
     }
@@ -56,9 +61,19 @@ impl View {
                     } else {
                         buf
                     };
-                    self.credentials
+                    let buf = self
+                        .credentials
                         .iter()
-                        .fold(buf, |buf, section| section.render(buf))
+                        .fold(buf, |buf, section| section.render(buf));
+                    self.cancel.iter().fold(buf, |buf, link| {
+                        buf.node("div")
+                            .attr(("class", "card-footer text-center"))
+                            .content(|buf| {
+                                buf.node("a")
+                                    .attr(("href", link.as_str()))
+                                    .content(|buf| buf.text("Cancel"))
+                            })
+                    })
                 })
         })
     }
@@ -0,0 +1,107 @@
+use std::borrow::Cow;
+
+use another_html_builder::{Body, Buffer};
+
+#[derive(Debug)]
+pub struct View {
+    target: Cow<'static, str>,
+    consent_id: String,
+    scopes: Vec<String>,
+    style_path: Option<&'static str>,
+}
+
+impl View {
+    pub fn new(target: impl Into<Cow<'static, str>>, consent_id: impl Into<String>) -> Self {
+        Self {
+            target: target.into(),
+            consent_id: consent_id.into(),
+            scopes: Vec::new(),
+            style_path: None,
+        }
+    }
+
+    pub fn add_scope(&mut self, scope: String) {
+        self.scopes.push(scope);
+    }
+
+    pub fn with_style_path(mut self, style_path: &'static str) -> Self {
+        self.style_path = Some(style_path);
+        self
+    }
+
+    fn render_scopes<'b, W: std::fmt::Write>(
+        &self,
+        buf: Buffer<W, Body<'b>>,
+    ) -> Buffer<W, Body<'b>> {
+        if self.scopes.is_empty() {
+            return buf
+                .node("p")
+                .content(|buf| buf.text("The application requests access to your account."));
+        }
+        buf.node("p")
+            .content(|buf| buf.text("The application requests access to:"))
+            .node("ul")
+            .attr(("class", "list"))
+            .content(|buf| {
+                self.scopes.iter().fold(buf, |buf, scope| {
+                    buf.node("li")
+                        .attr(("class", "list-item"))
+                        .content(|buf| buf.text(scope.as_str()))
+                })
+            })
+    }
+
+    fn render_body<'b, W: std::fmt::Write>(&self, buf: Buffer<W, Body<'b>>) -> Buffer<W, Body<'b>> {
+        buf.node("body").content(|buf| {
+            buf.node("main")
+                .attr(("class", "card shadow max-w400 mx-auto my-32"))
+                .content(|buf| {
+                    buf.node("div")
+                        .attr(("class", "card-header text-center"))
+                        .content(|buf| buf.text("Authorization request"))
+                        .node("form")
+                        .attr(("class", "card-body"))
+                        .attr(("method", "POST"))
+                        .attr(("action", self.target.as_ref()))
+                        .content(|buf| {
+                            let buf = self.render_scopes(buf);
+                            buf.node("input")
+                                .attr(("type", "hidden"))
+                                .attr(("name", "consent_id"))
+                                .attr(("value", self.consent_id.as_str()))
+                                .close()
+                                .node("div")
+                                .attr(("class", "actions"))
+                                .content(|buf| {
+                                    buf.node("button")
+                                        .attr(("type", "submit"))
+                                        .attr(("name", "decision"))
+                                        .attr(("value", "deny"))
+                                        .attr(("class", "hover_shadow"))
+                                        .content(|buf| buf.text("Deny"))
+                                        .node("button")
+                                        .attr(("type", "submit"))
+                                        .attr(("name", "decision"))
+                                        .attr(("value", "approve"))
+                                        .attr(("class", "hover_shadow success"))
+                                        .content(|buf| buf.text("Approve"))
+                                })
+                        })
+                })
+        })
+    }
+}
+
+impl crate::view::View for View {
+    fn render(self) -> String {
+        Buffer::default()
+            .doctype()
+            .node("html")
+            .attr(("lang", "en"))
+            .content(|buf| {
+                let buf = crate::component::head::render(buf, self.style_path);
+                self.render_body(buf)
+            })
+            .into_inner()
+    }
+}
@@ -0,0 +1,57 @@
+use another_html_builder::Buffer;
+
+#[derive(Debug, Default)]
+pub struct View {
+    style_path: Option<&'static str>,
+    denied: bool,
+}
+
+impl View {
+    pub fn denied() -> Self {
+        Self {
+            style_path: None,
+            denied: true,
+        }
+    }
+
+    pub fn with_style_path(mut self, style_path: &'static str) -> Self {
+        self.style_path = Some(style_path);
+        self
+    }
+}
+
+impl crate::view::View for View {
+    fn render(self) -> String {
+        let (title, message) = if self.denied {
+            (
+                "Device denied",
+                "The request has been denied, you can return to your device.",
+            )
+        } else {
+            (
+                "Device activated",
+                "You are now logged in, you can return to your device.",
+            )
+        };
+        Buffer::default()
+            .doctype()
+            .node("html")
+            .attr(("lang", "en"))
+            .content(|buf| {
+                let buf = crate::component::head::render(buf, self.style_path);
+                buf.node("body").content(|buf| {
+                    buf.node("div")
+                        .attr(("class", "card shadow max-w400 mx-auto my-32"))
+                        .content(|buf| {
+                            buf.node("div")
+                                .attr(("class", "card-header text-center"))
+                                .content(|buf| buf.text(title))
+                                .node("div")
+                                .attr(("class", "card-body"))
+                                .content(|buf| buf.text(message))
+                        })
+                })
+            })
+            .into_inner()
+    }
+}
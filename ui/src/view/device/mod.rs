@@ -0,0 +1,88 @@
+use std::borrow::Cow;
+
+use another_html_builder::{Body, Buffer};
+
+pub mod completed;
+
+const fn user_code_field() -> crate::component::text_field::Component {
+    crate::component::text_field::Component {
+        rtype: "text",
+        id: "user_code",
+        name: "user_code",
+        label: "Code displayed on your device",
+        placeholder: "XXXX-XXXX",
+        required: true,
+    }
+}
+
+#[derive(Debug)]
+pub struct View {
+    target: Cow<'static, str>,
+    error: Option<String>,
+    style_path: Option<&'static str>,
+}
+
+impl View {
+    pub fn new(target: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            target: target.into(),
+            error: None,
+            style_path: None,
+        }
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    pub fn with_style_path(mut self, style_path: &'static str) -> Self {
+        self.style_path = Some(style_path);
+        self
+    }
+
+    fn render_body<'b, W: std::fmt::Write>(&self, buf: Buffer<W, Body<'b>>) -> Buffer<W, Body<'b>> {
+        buf.node("body").content(|buf| {
+            let buf = self.error.iter().fold(buf, |buf, error| {
+                buf.node("section")
+                    .attr(("class", "card card-error shadow max-w400 mx-auto my-32"))
+                    .content(|buf| {
+                        buf.node("div")
+                            .attr(("class", "card-body"))
+                            .content(|buf| buf.text(error.as_str()))
+                    })
+            });
+            buf.node("main")
+                .attr(("class", "card shadow max-w400 mx-auto my-32"))
+                .content(|buf| {
+                    buf.node("div")
+                        .attr(("class", "card-header text-center"))
+                        .content(|buf| buf.text("Device activation"))
+                        .node("form")
+                        .attr(("class", "card-body"))
+                        .attr(("method", "GET"))
+                        .attr(("action", self.target.as_ref()))
+                        .content(|buf| {
+                            let buf = user_code_field().render(buf);
+                            buf.node("button")
+                                .attr(("type", "submit"))
+                                .attr(("class", "hover_shadow success"))
+                                .content(|buf| buf.text("Continue"))
+                        })
+                })
+        })
+    }
+}
+
+impl crate::view::View for View {
+    fn render(self) -> String {
+        Buffer::default()
+            .doctype()
+            .node("html")
+            .attr(("lang", "en"))
+            .content(|buf| {
+                let buf = crate::component::head::render(buf, self.style_path);
+                self.render_body(buf)
+            })
+            .into_inner()
+    }
+}
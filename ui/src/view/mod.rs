@@ -1,4 +1,6 @@
 pub mod authorize;
+pub mod consent;
+pub mod device;
 pub mod error;
 pub mod redirect;
 
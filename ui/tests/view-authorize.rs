@@ -50,3 +50,12 @@ fn with_all_and_error() {
     view.set_error("Something went wrong...".into());
     helper::write("/view-authorize-with-all-and-error.html", view);
 }
+
+#[test]
+fn with_cancel() {
+    let mut view = tekitoi_ui::view::authorize::View::default().with_style_path("style.css");
+    let creds = tekitoi_ui::view::authorize::credentials::Section::new("/login");
+    view.set_credentials(creds);
+    view.set_cancel("/authorize/cancel".into());
+    helper::write("/view-authorize-with-cancel.html", view);
+}
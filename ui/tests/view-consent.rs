@@ -0,0 +1,19 @@
+mod helper;
+
+#[test]
+fn without_scope() {
+    helper::write(
+        "/view-consent-without-scope.html",
+        tekitoi_ui::view::consent::View::new("/authorize/consent", "consent-id")
+            .with_style_path("style.css"),
+    );
+}
+
+#[test]
+fn with_scopes() {
+    let mut view = tekitoi_ui::view::consent::View::new("/authorize/consent", "consent-id")
+        .with_style_path("style.css");
+    view.add_scope("profile".into());
+    view.add_scope("email".into());
+    helper::write("/view-consent-with-scopes.html", view);
+}
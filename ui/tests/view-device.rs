@@ -0,0 +1,32 @@
+mod helper;
+
+#[test]
+fn default() {
+    helper::write(
+        "/view-device-default.html",
+        tekitoi_ui::view::device::View::new("/device").with_style_path("style.css"),
+    );
+}
+
+#[test]
+fn with_error() {
+    let mut view = tekitoi_ui::view::device::View::new("/device").with_style_path("style.css");
+    view.set_error("Invalid code.".into());
+    helper::write("/view-device-with-error.html", view);
+}
+
+#[test]
+fn completed() {
+    helper::write(
+        "/view-device-completed.html",
+        tekitoi_ui::view::device::completed::View::default().with_style_path("style.css"),
+    );
+}
+
+#[test]
+fn denied() {
+    helper::write(
+        "/view-device-denied.html",
+        tekitoi_ui::view::device::completed::View::denied().with_style_path("style.css"),
+    );
+}